@@ -0,0 +1,441 @@
+use chair_rb::concurrent::ring_buffer::{
+    ManyToOneRingBuffer, OneToOneRingBuffer, RingBuffer, RingBufferDescriptor,
+};
+use chair_rb::concurrent::{
+    AtomicBuffer, BusySpinIdleStrategy, IdleStrategy, NoOpIdleStrategy, YieldingIdleStrategy,
+};
+use chair_rb::mem::AlignedBox;
+use chair_rb::util::bit_util::CACHE_LINE_LENGTH;
+use core_affinity::CoreId;
+use hdrhistogram::Histogram;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "Usage: cargo run --release --example bench -- [options]
+
+Measures end-to-end latency of the ring buffers and prints percentiles.
+
+Options:
+    --mode <ping-pong|burst>    ping-pong over two OneToOneRingBuffers or bursts into a ManyToOneRingBuffer, defaults to ping-pong
+    --message-sizes <list>      comma separated message sizes in bytes, at least 8, defaults to 32
+    --producers <n>             producer threads for burst mode, defaults to 1
+    --burst-size <n>            messages per burst in burst mode, defaults to 100
+    --messages <n>              messages measured per run, defaults to 1000000
+    --warmup <n>                messages sent before measuring, defaults to 100000
+    --capacity <bytes>          ring buffer capacity, a power of 2, defaults to 65536
+    --idle <busy-spin|no-op|yield>  idle strategy used when there is no work, defaults to busy-spin
+    --pin <list>                comma separated core ids the threads are pinned to in start order
+    --csv                       print results as CSV";
+
+const MSG_TYPE_ID: i32 = 1;
+const POISON_MSG_TYPE_ID: i32 = 2;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    PingPong,
+    Burst,
+}
+
+#[derive(Clone, Copy)]
+enum Idle {
+    BusySpin,
+    NoOp,
+    Yield,
+}
+
+impl Idle {
+    fn create(self) -> Box<dyn IdleStrategy> {
+        match self {
+            Idle::BusySpin => Box::new(BusySpinIdleStrategy),
+            Idle::NoOp => Box::new(NoOpIdleStrategy),
+            Idle::Yield => Box::new(YieldingIdleStrategy),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Idle::BusySpin => "busy-spin",
+            Idle::NoOp => "no-op",
+            Idle::Yield => "yield",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Config {
+    mode: Mode,
+    message_sizes: Vec<i32>,
+    producers: usize,
+    burst_size: u64,
+    messages: u64,
+    warmup: u64,
+    capacity: i32,
+    idle: Idle,
+    pin: Vec<usize>,
+    csv: bool,
+}
+
+impl Config {
+    fn core(&self, thread_index: usize) -> Option<CoreId> {
+        self.pin.get(thread_index).map(|&id| CoreId { id })
+    }
+}
+
+struct RunResult {
+    message_size: i32,
+    histogram: Histogram<u64>,
+    elapsed: Duration,
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config {
+        mode: Mode::PingPong,
+        message_sizes: vec![32],
+        producers: 1,
+        burst_size: 100,
+        messages: 1_000_000,
+        warmup: 100_000,
+        capacity: 64 * 1024,
+        idle: Idle::BusySpin,
+        pin: vec![],
+        csv: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("{} requires a value", arg))
+        };
+
+        match arg.as_str() {
+            "--mode" => {
+                config.mode = match value()?.as_str() {
+                    "ping-pong" => Mode::PingPong,
+                    "burst" => Mode::Burst,
+                    other => return Err(format!("unknown mode {}", other)),
+                }
+            }
+            "--message-sizes" => config.message_sizes = parse_list(&value()?)?,
+            "--producers" => config.producers = parse(&value()?)?,
+            "--burst-size" => config.burst_size = parse(&value()?)?,
+            "--messages" => config.messages = parse(&value()?)?,
+            "--warmup" => config.warmup = parse(&value()?)?,
+            "--capacity" => config.capacity = parse(&value()?)?,
+            "--idle" => {
+                config.idle = match value()?.as_str() {
+                    "busy-spin" => Idle::BusySpin,
+                    "no-op" => Idle::NoOp,
+                    "yield" => Idle::Yield,
+                    other => return Err(format!("unknown idle strategy {}", other)),
+                }
+            }
+            "--pin" => config.pin = parse_list(&value()?)?,
+            "--csv" => config.csv = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ => return Err(format!("unknown option {}", arg)),
+        }
+    }
+
+    let max_msg_length = config.capacity / 8;
+    if config.capacity <= 0 || config.capacity & (config.capacity - 1) != 0 {
+        return Err(format!("capacity {} is not a power of 2", config.capacity));
+    }
+    if let Some(size) = config
+        .message_sizes
+        .iter()
+        .find(|&&size| size < 8 || size > max_msg_length)
+    {
+        return Err(format!(
+            "message size {} must be between 8 and {}",
+            size, max_msg_length
+        ));
+    }
+    if config.producers == 0 || config.burst_size == 0 || config.messages == 0 {
+        return Err("producers, burst size and messages must be greater than zero".to_string());
+    }
+
+    Ok(config)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("invalid value {}: {}", value, e))
+}
+
+fn parse_list<T: std::str::FromStr>(value: &str) -> Result<Vec<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    value.split(',').map(|v| parse(v.trim())).collect()
+}
+
+fn pin(core: Option<CoreId>) {
+    if let Some(core) = core {
+        if !core_affinity::set_for_current(core) {
+            eprintln!("unable to pin thread to core {}", core.id);
+        }
+    }
+}
+
+fn work_count(messages_read: u32) -> i8 {
+    messages_read.min(i8::MAX as u32) as i8
+}
+
+fn new_histogram() -> Histogram<u64> {
+    Histogram::new_with_bounds(1, 60_000_000_000, 3).unwrap()
+}
+
+fn nanos_since(epoch: Instant) -> i64 {
+    epoch.elapsed().as_nanos() as i64
+}
+
+/// Round trip time of a message sent over one ring buffer and echoed back over another.
+fn ping_pong(config: &Config, message_size: i32) -> RunResult {
+    let buffer_size = (config.capacity + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    let mut ping_buf = AlignedBox::<[u8]>::zeroed(buffer_size, CACHE_LINE_LENGTH as usize);
+    let mut pong_buf = AlignedBox::<[u8]>::zeroed(buffer_size, CACHE_LINE_LENGTH as usize);
+    let ping = Arc::new(OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut ping_buf)));
+    let pong = Arc::new(OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut pong_buf)));
+
+    let echo = {
+        let (ping, pong) = (ping.clone(), pong.clone());
+        let (idle, core) = (config.idle, config.core(1));
+
+        thread::spawn(move || {
+            pin(core);
+            let idle = idle.create();
+            let mut running = true;
+
+            while running {
+                let read = ping.read(
+                    |msg_type_id, buffer, index, length| {
+                        while !pong.write(msg_type_id, buffer, index, length) {
+                            idle.idle();
+                        }
+                        running = POISON_MSG_TYPE_ID != msg_type_id;
+                    },
+                    u32::MAX,
+                );
+                idle.idle_work(work_count(read));
+            }
+        })
+    };
+
+    pin(config.core(0));
+    let idle = config.idle.create();
+    let mut src_buf =
+        AlignedBox::<[u8]>::zeroed(message_size as usize, CACHE_LINE_LENGTH as usize);
+    let src_buffer = AtomicBuffer::wrap(&mut src_buf);
+    let mut histogram = new_histogram();
+    let epoch = Instant::now();
+    let mut start = epoch;
+
+    for i in 0..config.warmup + config.messages {
+        if i == config.warmup {
+            start = Instant::now();
+        }
+
+        src_buffer.put_i64(0, nanos_since(epoch));
+        while !ping.write(MSG_TYPE_ID, &src_buffer, 0, message_size) {
+            idle.idle();
+        }
+
+        let mut received = 0;
+        while received == 0 {
+            received = pong.read(
+                |_, buffer, index, _| {
+                    let rtt = nanos_since(epoch) - buffer.get_i64(index);
+                    if i >= config.warmup {
+                        histogram.saturating_record(rtt.max(1) as u64);
+                    }
+                },
+                1,
+            );
+            idle.idle_work(work_count(received));
+        }
+    }
+    let elapsed = start.elapsed();
+
+    while !ping.write(POISON_MSG_TYPE_ID, &src_buffer, 0, message_size) {
+        idle.idle();
+    }
+    echo.join().unwrap();
+
+    RunResult {
+        message_size,
+        histogram,
+        elapsed,
+    }
+}
+
+/// Latency from write to read of messages sent in bursts by several producers.
+fn burst(config: &Config, message_size: i32) -> RunResult {
+    let buffer_size = (config.capacity + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    let mut buf = AlignedBox::<[u8]>::zeroed(buffer_size, CACHE_LINE_LENGTH as usize);
+    let ring_buffer = Arc::new(ManyToOneRingBuffer::new(AtomicBuffer::wrap(&mut buf)));
+    let epoch = Instant::now();
+    let go = Arc::new(AtomicBool::new(false));
+    let per_producer = (config.warmup + config.messages) / config.producers as u64;
+    let total = per_producer * config.producers as u64;
+
+    let producers: Vec<_> = (0..config.producers)
+        .map(|p| {
+            let (ring_buffer, go) = (ring_buffer.clone(), go.clone());
+            let (idle, core, burst_size) = (config.idle, config.core(p + 1), config.burst_size);
+
+            thread::spawn(move || {
+                pin(core);
+                let idle = idle.create();
+                let mut src_buf =
+                    AlignedBox::<[u8]>::zeroed(message_size as usize, CACHE_LINE_LENGTH as usize);
+                let src_buffer = AtomicBuffer::wrap(&mut src_buf);
+
+                while !go.load(Ordering::Acquire) {
+                    thread::yield_now();
+                }
+
+                let mut sent = 0;
+                while sent < per_producer {
+                    for _ in 0..burst_size.min(per_producer - sent) {
+                        src_buffer.put_i64(0, nanos_since(epoch));
+                        while !ring_buffer.write(MSG_TYPE_ID, &src_buffer, 0, message_size) {
+                            idle.idle();
+                        }
+                        sent += 1;
+                    }
+
+                    let pause = Instant::now();
+                    while pause.elapsed() < Duration::from_micros(10) {
+                        idle.idle();
+                    }
+                }
+            })
+        })
+        .collect();
+
+    pin(config.core(0));
+    let idle = config.idle.create();
+    let mut histogram = new_histogram();
+    let mut received = 0;
+    let mut start = Instant::now();
+    go.store(true, Ordering::Release);
+
+    while received < total {
+        let read = ring_buffer.read(
+            |_, buffer, index, _| {
+                if received == config.warmup {
+                    start = Instant::now();
+                }
+                if received >= config.warmup {
+                    let latency = nanos_since(epoch) - buffer.get_i64(index);
+                    histogram.saturating_record(latency.max(1) as u64);
+                }
+                received += 1;
+            },
+            u32::MAX,
+        );
+        idle.idle_work(work_count(read));
+    }
+    let elapsed = start.elapsed();
+
+    for producer in producers {
+        producer.join().unwrap();
+    }
+
+    RunResult {
+        message_size,
+        histogram,
+        elapsed,
+    }
+}
+
+fn print_result(config: &Config, result: &RunResult) {
+    let histogram = &result.histogram;
+    let throughput = histogram.len() as f64 / result.elapsed.as_secs_f64();
+    let mode = match config.mode {
+        Mode::PingPong => "ping-pong",
+        Mode::Burst => "burst",
+    };
+    let producers = match config.mode {
+        Mode::PingPong => 1,
+        Mode::Burst => config.producers,
+    };
+    let pinned = !config.pin.is_empty();
+
+    if config.csv {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{:.0},{:.0}",
+            mode,
+            result.message_size,
+            producers,
+            config.idle.name(),
+            pinned,
+            histogram.len(),
+            histogram.min(),
+            histogram.value_at_quantile(0.5),
+            histogram.value_at_quantile(0.9),
+            histogram.value_at_quantile(0.99),
+            histogram.value_at_quantile(0.999),
+            histogram.value_at_quantile(0.9999),
+            histogram.max(),
+            histogram.mean(),
+            throughput
+        );
+    } else {
+        println!(
+            "{} message_size={} producers={} idle={} pinned={}",
+            mode,
+            result.message_size,
+            producers,
+            config.idle.name(),
+            pinned
+        );
+        println!(
+            "    count={} throughput={:.0} msgs/sec",
+            histogram.len(),
+            throughput
+        );
+        println!(
+            "    latency ns: min={} p50={} p90={} p99={} p99.9={} p99.99={} max={} mean={:.0}",
+            histogram.min(),
+            histogram.value_at_quantile(0.5),
+            histogram.value_at_quantile(0.9),
+            histogram.value_at_quantile(0.99),
+            histogram.value_at_quantile(0.999),
+            histogram.value_at_quantile(0.9999),
+            histogram.max(),
+            histogram.mean()
+        );
+    }
+}
+
+fn main() {
+    let config = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}\n\n{}", message, USAGE);
+        process::exit(2);
+    });
+
+    if config.csv {
+        println!("mode,message_size,producers,idle,pinned,count,min_ns,p50_ns,p90_ns,p99_ns,p99_9_ns,p99_99_ns,max_ns,mean_ns,msgs_per_sec");
+    }
+
+    for &message_size in &config.message_sizes {
+        let result = match config.mode {
+            Mode::PingPong => ping_pong(&config, message_size),
+            Mode::Burst => burst(&config, message_size),
+        };
+
+        print_result(&config, &result);
+    }
+}
@@ -0,0 +1,138 @@
+use chair_rb::concurrent::AtomicBuffer;
+use chair_rb::mem::AlignedBox;
+use chair_rb::util::bit_util::CACHE_LINE_LENGTH;
+use std::hint::black_box;
+use std::mem::size_of;
+use std::process;
+use std::time::{Duration, Instant};
+
+const USAGE: &str =
+    "Usage: cargo run --release --example buffer_access -- [options]
+
+Measures AtomicBuffer get/put against get_unchecked/put_unchecked, which skip the bounds check in
+release builds.
+
+Options:
+    --capacity <bytes>      buffer capacity, a multiple of 8, defaults to 4096
+    --iterations <n>        passes over the buffer per run, defaults to 100000
+    --runs <n>              runs per accessor, the fastest is reported, defaults to 5";
+
+struct Config {
+    capacity: usize,
+    iterations: u64,
+    runs: u32,
+}
+
+fn parse_args() -> Result<Config, String> {
+    let mut config = Config {
+        capacity: 4096,
+        iterations: 100_000,
+        runs: 5,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", arg))
+        };
+        match arg.as_str() {
+            "--capacity" => config.capacity = parse(&value()?)?,
+            "--iterations" => config.iterations = parse(&value()?)?,
+            "--runs" => config.runs = parse(&value()?)?,
+            "-h" | "--help" => return Err(String::new()),
+            other => return Err(format!("unknown option {}", other)),
+        }
+    }
+
+    if config.capacity == 0 || config.capacity % size_of::<i64>() != 0 {
+        return Err(format!(
+            "capacity must be a multiple of 8: {}",
+            config.capacity
+        ));
+    }
+
+    Ok(config)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {}", value))
+}
+
+fn checked(buffer: &AtomicBuffer, iterations: u64) -> i64 {
+    let mut sum = 0_i64;
+    for i in 0..iterations {
+        let mut index = 0;
+        while index < buffer.capacity() {
+            buffer.put::<i64>(index, i as i64);
+            sum = sum.wrapping_add(buffer.get::<i64>(index));
+            index += size_of::<i64>() as i32;
+        }
+    }
+    sum
+}
+
+fn unchecked(buffer: &AtomicBuffer, iterations: u64) -> i64 {
+    let mut sum = 0_i64;
+    for i in 0..iterations {
+        let mut index = 0;
+        while index < buffer.capacity() {
+            // index stays below capacity, which is a multiple of 8
+            unsafe {
+                buffer.put_unchecked::<i64>(index, i as i64);
+                sum = sum.wrapping_add(buffer.get_unchecked::<i64>(index));
+            }
+            index += size_of::<i64>() as i32;
+        }
+    }
+    sum
+}
+
+fn fastest(config: &Config, buffer: &AtomicBuffer, f: fn(&AtomicBuffer, u64) -> i64) -> Duration {
+    (0..config.runs)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f(black_box(buffer), config.iterations));
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let config = parse_args().unwrap_or_else(|message| {
+        if !message.is_empty() {
+            eprintln!("{}\n", message);
+        }
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+
+    let mut bytes = AlignedBox::<[u8]>::zeroed(config.capacity, CACHE_LINE_LENGTH as usize);
+    let buffer = AtomicBuffer::wrap(&mut bytes);
+    let accesses = config.iterations * (config.capacity / size_of::<i64>()) as u64;
+
+    println!(
+        "debug assertions {}",
+        if cfg!(debug_assertions) {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    for (name, f) in [
+        ("get/put", checked as fn(&AtomicBuffer, u64) -> i64),
+        ("get_unchecked/put_unchecked", unchecked),
+    ] {
+        let elapsed = fastest(&config, &buffer, f);
+        println!(
+            "{:<28} {:>10.3} ms {:>8.3} ns/access",
+            name,
+            elapsed.as_secs_f64() * 1e3,
+            elapsed.as_nanos() as f64 / accesses as f64
+        );
+    }
+}
@@ -1,63 +1,96 @@
+use jni::objects::{JByteBuffer, JClass};
+use jni::sys::{jint, jlong};
 use jni::JNIEnv;
-use jni::objects::{JClass, JByteBuffer};
-use jni::sys::{jboolean, jint};
 
 use chair_rb::concurrent::ring_buffer::{OneToOneRingBuffer, RingBuffer};
-use chair_rb::concurrent::AtomicBuffer;
+use chair_rb::concurrent::{AtomicBuffer, BusySpinIdleStrategy};
+use chair_rb::mem::Align64;
+use chair_rb::jni::{close_handle, into_handle, JavaBuffer, JavaRingBuffer, NativeAgent};
 
 use std::mem::size_of;
-use std::sync::Arc;
-use std::thread;
+use std::ops::ControlFlow;
+
 const POISON_MESSAGE_TYPE: i32 = 42;
 
 /*
  * Class:     ChairRB
- * Method:    createAndStartProducer
- * Signature: ([B)Z
+ * Method:    startOneToOneProducer
+ * Signature: (Ljava/nio/ByteBuffer;I)J
  */
 #[no_mangle]
-pub extern "system" fn Java_ChairRB_createAndStartOneToOneProducer(env: JNIEnv,
-// This is the class that owns our static method. It's not going to be used,
-// but still must be present to match the expected signature of a static
-// native method.
-                                             _class: JClass,
-                                             byte_buffer: JByteBuffer,
-                                             mesages_to_produce: jint)
-                                             -> jboolean {
-
-
-    // First, we have to get the byte[] out of java.
-    let buffer = env.get_direct_buffer_address(byte_buffer).unwrap();
-    println!("Input byte buffer size: {}", buffer.len());
-
-    let ab = AtomicBuffer::wrap(&mut *buffer);
-    let ring_buffer = Arc::new(OneToOneRingBuffer::new(ab));
-    let rb = Arc::clone(&ring_buffer);
-
-    thread::spawn(move || {
-        let mut src_buf: [u8; 128] = [0; 128];
-        let src_buffer = AtomicBuffer::wrap(&mut src_buf);
-
-        let mut sent = 0;
-        while sent < mesages_to_produce {
-            src_buffer.put_i64(0, sent as i64);
+pub extern "system" fn Java_ChairRB_startOneToOneProducer<'local>(
+    mut env: JNIEnv<'local>,
+    // This is the class that owns our static method. It's not going to be used,
+    // but still must be present to match the expected signature of a static
+    // native method.
+    _class: JClass<'local>,
+    byte_buffer: JByteBuffer<'local>,
+    messages_to_produce: jint,
+) -> jlong {
+    let ring_buffer = match JavaBuffer::from_byte_buffer(&mut env, &byte_buffer)
+        .and_then(|buffer| JavaRingBuffer::new(buffer, OneToOneRingBuffer::new))
+    {
+        Ok(ring_buffer) => ring_buffer,
+        Err(e) => {
+            e.throw(&mut env);
+            return 0;
+        }
+    };
+    println!("Input byte buffer size: {}", ring_buffer.buffer().capacity());
+
+    let mut sent = 0;
+    let mut src_buf = Align64::new([0_u8; 128]);
+    let producer = NativeAgent::producer(
+        "rust-producer",
+        ring_buffer,
+        BusySpinIdleStrategy,
+        move |rb| {
+            let src_buffer = AtomicBuffer::wrap(&mut *src_buf);
 
-            while !rb.write(1, &src_buffer, 0, size_of::<i64>() as i32) {}
+            if sent == messages_to_produce {
+                src_buffer.put_i64(0, POISON_MESSAGE_TYPE as i64);
+                if !rb.write(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32) {
+                    return ControlFlow::Continue(0);
+                }
+                println!(
+                    "Producer - Sent poison after publishing {} messages to ring buffer",
+                    sent
+                );
+                return ControlFlow::Break(());
+            }
+
+            src_buffer.put_i64(0, sent as i64);
+            if !rb.write(1, &src_buffer, 0, size_of::<i64>() as i32) {
+                return ControlFlow::Continue(0);
+            }
             sent += 1;
 
-            if sent % (mesages_to_produce / 10) == 0 {
+            if sent % (messages_to_produce / 10) == 0 {
                 println!("Rust Producer - Written {} message to ring buffer", sent);
             }
-        }
-        src_buffer.put_i64(0, POISON_MESSAGE_TYPE as i64);
-        println!(
-            "Producer - Sending poison after publishing {} messages to ring buffer",
-            sent
-        );
+            ControlFlow::Continue(1)
+        },
+    );
 
-        while !rb.write(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32) {}
-    });
+    match producer {
+        Ok(producer) => into_handle(producer),
+        Err(e) => {
+            let _ = env.throw_new("java/lang/IllegalStateException", e.to_string());
+            0
+        }
+    }
+}
 
-    true as jboolean
+/*
+ * Class:     ChairRB
+ * Method:    closeProducer
+ * Signature: (J)V
+ */
+#[no_mangle]
+pub extern "system" fn Java_ChairRB_closeProducer<'local>(
+    _env: JNIEnv<'local>,
+    _class: JClass<'local>,
+    handle: jlong,
+) {
+    unsafe { close_handle::<NativeAgent>(handle) }
 }
-                                             
\ No newline at end of file
@@ -5,7 +5,7 @@ fn main() {
 
     let status = if cfg!(target_os = "windows") {
         Command::new("cmd")
-            .args(&["/C", "gradlew", "run"])
+            .args(["/C", "gradlew", "run"])
             .current_dir("examples/java/")
             .status()
             .expect("failed to execute process")
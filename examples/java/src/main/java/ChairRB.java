@@ -15,7 +15,9 @@ class ChairRB {
 
     private static final int MESSAGES_TO_PRODUCE = 1_000_000_000;
 
-    private static native boolean createAndStartOneToOneProducer(ByteBuffer byteBuffer, int messageToProduce);
+    private static native long startOneToOneProducer(ByteBuffer byteBuffer, int messageToProduce);
+
+    private static native void closeProducer(long producerHandle);
 
     static {
         try {
@@ -42,14 +44,15 @@ class ChairRB {
 
         public void run(){
             System.out.println("One To One RB - Rust Producer/Java Consumer ");
-            final boolean started = createAndStartOneToOneProducer(byteBuffer, MESSAGES_TO_PRODUCE);
-            System.out.println("Rust Producer Started: " + started);
+            final long producerHandle = startOneToOneProducer(byteBuffer, MESSAGES_TO_PRODUCE);
+            System.out.println("Rust Producer Started: " + (producerHandle != 0));
 
             final Instant start = Instant.now();
             while (!isPoision){
                 ringBuffer.read(this);
             }
             final Instant finish = Instant.now();
+            closeProducer(producerHandle);
             final Duration elapsed = Duration.between(start, finish);
             System.out.printf(
             "One To One RB - Rust Producer/Java Consumer - Time to receive %d messages %d milliseconds %d message per second\n",
@@ -1,20 +1,21 @@
 use chair_rb::concurrent::ring_buffer::{ManyToOneRingBuffer, RingBuffer, RingBufferDescriptor};
-use chair_rb::concurrent::AtomicBuffer;
-use chair_rb::mem::Align16;
+use chair_rb::concurrent::{AtomicBuffer, YieldingIdleStrategy};
+use chair_rb::mem::Align64;
 use std::mem::size_of;
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::sync::atomic::{AtomicI32, Ordering};
 
 
 const PRODUCER_COUNT: u32 = 3;
 const MESSAGES_PER_PRODUCER: u32 = 100_000_000;
 const POISON_MESSAGE_TYPE: i32 = 42;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
 
 fn main() {
     println!("Many to One Ring Buffer Example");
-    let mut buf = Align16::new([0 as u8; 1024 + RingBufferDescriptor::TRAILER_LENGTH as usize]);
+    let mut buf = Align64::new([0_u8; 1024 + RingBufferDescriptor::TRAILER_LENGTH as usize]);
     let buffer = AtomicBuffer::wrap(&mut *buf);
     let ring_buffer = Arc::new(ManyToOneRingBuffer::new(buffer));
     let producer_id = Arc::new(AtomicI32::new(1));
@@ -25,8 +26,8 @@ fn main() {
         let producer_id = producer_id.clone();
         let ring_buffer = ring_buffer.clone();
         threads.push( thread::spawn(move || {
-            let mut src_buf: [u8; 128] = [0; 128];
-            let src_buffer = AtomicBuffer::wrap(&mut src_buf);
+            let mut src_buf = Align64::new([0_u8; 128]);
+            let src_buffer = AtomicBuffer::wrap(&mut *src_buf);
 
             let producer_id = producer_id.fetch_add(1, Ordering::SeqCst);
 
@@ -35,9 +36,9 @@ fn main() {
             while sent < MESSAGES_PER_PRODUCER {
                 src_buffer.put_i64(0, sent as i64);
 
-                while !ring_buffer.write(producer_id, &src_buffer, 0, size_of::<i64>() as i32) {
-                    thread::yield_now();
-                }
+                ring_buffer
+                    .write_blocking(producer_id, &src_buffer, 0, size_of::<i64>() as i32, &YieldingIdleStrategy, WRITE_TIMEOUT)
+                    .expect("consumer stopped reading");
                 sent += 1;
 
                 if sent % (MESSAGES_PER_PRODUCER / 10) == 0 {
@@ -53,9 +54,9 @@ fn main() {
                 producer_id, sent
             );
 
-            while !ring_buffer.write(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32) {
-                thread::yield_now();
-            }
+            ring_buffer
+                .write_blocking(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32, &YieldingIdleStrategy, WRITE_TIMEOUT)
+                .expect("consumer stopped reading");
 
         }));
     }
@@ -86,7 +87,7 @@ fn main() {
                     );
                 }
             },
-            u32::max_value(),
+            u32::MAX,
         );
     }
 
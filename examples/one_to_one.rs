@@ -1,31 +1,33 @@
 use chair_rb::concurrent::ring_buffer::{OneToOneRingBuffer, RingBuffer, RingBufferDescriptor};
-use chair_rb::concurrent::AtomicBuffer;
-use chair_rb::mem::Align16;
+use chair_rb::concurrent::{AtomicBuffer, BusySpinIdleStrategy};
+use chair_rb::mem::Align64;
 use std::mem::size_of;
 use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 
 const MESSAGES_TO_PRODUCE: u32 = 1_000_000_000;
 const POISON_MESSAGE_TYPE: i32 = 42;
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
 
 fn main() {
     println!("One to One Ring Buffer Example");
-    let mut buf = Align16::new([0 as u8; 1024 + RingBufferDescriptor::TRAILER_LENGTH as usize]);
+    let mut buf = Align64::new([0_u8; 1024 + RingBufferDescriptor::TRAILER_LENGTH as usize]);
     let buffer = AtomicBuffer::wrap(&mut *buf);
     let ring_buffer = Arc::new(OneToOneRingBuffer::new(buffer));
     let rb = Arc::clone(&ring_buffer);
 
     let handle = thread::spawn(move || {
-        let mut src_buf: [u8; 128] = [0; 128];
-        let src_buffer = AtomicBuffer::wrap(&mut src_buf);
+        let mut src_buf = Align64::new([0_u8; 128]);
+        let src_buffer = AtomicBuffer::wrap(&mut *src_buf);
 
         let mut sent = 0;
         while sent < MESSAGES_TO_PRODUCE {
             src_buffer.put_i64(0, sent as i64);
 
-            while !rb.write(1, &src_buffer, 0, size_of::<i64>() as i32) {}
+            rb.write_blocking(1, &src_buffer, 0, size_of::<i64>() as i32, &BusySpinIdleStrategy, WRITE_TIMEOUT)
+                .expect("consumer stopped reading");
             sent += 1;
 
             if sent % (MESSAGES_TO_PRODUCE / 10) == 0 {
@@ -39,7 +41,8 @@ fn main() {
             sent
         );
 
-        while !rb.write(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32) {}
+        rb.write_blocking(POISON_MESSAGE_TYPE, &src_buffer, 0, size_of::<i64>() as i32, &BusySpinIdleStrategy, WRITE_TIMEOUT)
+            .expect("consumer stopped reading");
 
     });
 
@@ -69,7 +72,7 @@ fn main() {
                     );
                 }
             },
-            u32::max_value(),
+            u32::MAX,
         );
     }
 
@@ -0,0 +1,136 @@
+/* Generated by cbindgen from src/capi.rs, do not edit. */
+
+#ifndef CHAIR_RB_H
+#define CHAIR_RB_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum {
+  AERON_RB_ABORT,
+  AERON_RB_BREAK,
+  AERON_RB_COMMIT,
+  AERON_RB_CONTINUE,
+} aeron_rb_read_action_t;
+
+typedef enum {
+  AERON_RB_SUCCESS = 0,
+  AERON_RB_ERROR = -2,
+  AERON_RB_FULL = -1,
+} aeron_rb_write_result_t;
+
+/**
+ * Layout of the ring buffer trailer, each counter on its own pair of cache lines.
+ */
+typedef struct {
+  uint8_t begin_pad[128];
+  int64_t tail_position;
+  uint8_t tail_pad[120];
+  int64_t head_cache_position;
+  uint8_t head_cache_pad[120];
+  int64_t head_position;
+  uint8_t head_pad[120];
+  int64_t correlation_counter;
+  uint8_t correlation_counter_pad[120];
+  int64_t consumer_heartbeat;
+  uint8_t consumer_heartbeat_pad[120];
+} aeron_rb_descriptor_t;
+
+typedef struct {
+  uint8_t *buffer;
+  aeron_rb_descriptor_t *descriptor;
+  size_t capacity;
+  size_t max_message_length;
+} aeron_mpsc_rb_t;
+
+typedef void (*aeron_rb_handler_t)(int32_t, const void*, size_t, void*);
+
+typedef aeron_rb_read_action_t (*aeron_rb_controlled_handler_t)(int32_t, const void*, size_t, void*);
+
+typedef struct {
+  uint8_t *buffer;
+  aeron_rb_descriptor_t *descriptor;
+  size_t capacity;
+  size_t max_message_length;
+} aeron_spsc_rb_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+int aeron_mpsc_rb_init(aeron_mpsc_rb_t *ring_buffer, void *buffer, size_t length);
+
+aeron_rb_write_result_t aeron_mpsc_rb_write(aeron_mpsc_rb_t *ring_buffer,
+                                            int32_t msg_type_id,
+                                            const void *msg,
+                                            size_t length);
+
+int32_t aeron_mpsc_rb_try_claim(aeron_mpsc_rb_t *ring_buffer, int32_t msg_type_id, size_t length);
+
+int aeron_mpsc_rb_commit(aeron_mpsc_rb_t *ring_buffer, int32_t offset);
+
+int aeron_mpsc_rb_abort(aeron_mpsc_rb_t *ring_buffer, int32_t offset);
+
+size_t aeron_mpsc_rb_read(aeron_mpsc_rb_t *ring_buffer,
+                          aeron_rb_handler_t handler,
+                          void *clientd,
+                          size_t message_count_limit);
+
+size_t aeron_mpsc_rb_controlled_read(aeron_mpsc_rb_t *ring_buffer,
+                                     aeron_rb_controlled_handler_t handler,
+                                     void *clientd,
+                                     size_t message_count_limit);
+
+int64_t aeron_mpsc_rb_next_correlation_id(aeron_mpsc_rb_t *ring_buffer);
+
+void aeron_mpsc_rb_consumer_heartbeat_time(aeron_mpsc_rb_t *ring_buffer, int64_t now_ms);
+
+int64_t aeron_mpsc_rb_consumer_heartbeat_time_value(aeron_mpsc_rb_t *ring_buffer);
+
+bool aeron_mpsc_rb_unblock(aeron_mpsc_rb_t *ring_buffer);
+
+int64_t aeron_mpsc_rb_consumer_position(aeron_mpsc_rb_t *ring_buffer);
+
+int64_t aeron_mpsc_rb_producer_position(aeron_mpsc_rb_t *ring_buffer);
+
+int aeron_spsc_rb_init(aeron_spsc_rb_t *ring_buffer, void *buffer, size_t length);
+
+aeron_rb_write_result_t aeron_spsc_rb_write(aeron_spsc_rb_t *ring_buffer,
+                                            int32_t msg_type_id,
+                                            const void *msg,
+                                            size_t length);
+
+int32_t aeron_spsc_rb_try_claim(aeron_spsc_rb_t *ring_buffer, int32_t msg_type_id, size_t length);
+
+int aeron_spsc_rb_commit(aeron_spsc_rb_t *ring_buffer, int32_t offset);
+
+int aeron_spsc_rb_abort(aeron_spsc_rb_t *ring_buffer, int32_t offset);
+
+size_t aeron_spsc_rb_read(aeron_spsc_rb_t *ring_buffer,
+                          aeron_rb_handler_t handler,
+                          void *clientd,
+                          size_t message_count_limit);
+
+size_t aeron_spsc_rb_controlled_read(aeron_spsc_rb_t *ring_buffer,
+                                     aeron_rb_controlled_handler_t handler,
+                                     void *clientd,
+                                     size_t message_count_limit);
+
+int64_t aeron_spsc_rb_next_correlation_id(aeron_spsc_rb_t *ring_buffer);
+
+void aeron_spsc_rb_consumer_heartbeat_time(aeron_spsc_rb_t *ring_buffer, int64_t now_ms);
+
+int64_t aeron_spsc_rb_consumer_heartbeat_time_value(aeron_spsc_rb_t *ring_buffer);
+
+bool aeron_spsc_rb_unblock(aeron_spsc_rb_t *ring_buffer);
+
+int64_t aeron_spsc_rb_consumer_position(aeron_spsc_rb_t *ring_buffer);
+
+int64_t aeron_spsc_rb_producer_position(aeron_spsc_rb_t *ring_buffer);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* CHAIR_RB_H */
@@ -0,0 +1,269 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use chair_rb::concurrent::ring_buffer::inspector::{RecordInfo, RecordKind};
+use chair_rb::concurrent::ring_buffer::{RingBufferInspector, ZeroFill};
+use chair_rb::concurrent::AtomicBuffer;
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::process;
+
+const USAGE: &str = "Usage: chair-rb-inspect [--hex] [--offset <bytes>] [--length <bytes>] <file>
+       chair-rb-inspect validate [--repair] [--headers-only] [--offset <bytes>] [--length <bytes>] <file>
+
+Prints the trailer counters of a memory-mapped ring buffer and walks the records between head and tail.
+The validate command checks the trailer positions and record chain, exiting with status 3 if issues are found.
+
+Options:
+    --hex               hex dump the encoded message of each record
+    --repair            reset a stale head cache and unblock a record blocking the consumer, writes to the file
+    --headers-only      the consumer zeroes only record headers, so free space is not expected to be zeroed
+    --offset <bytes>    offset of the ring buffer within the file, defaults to 0
+    --length <bytes>    length of the ring buffer including the trailer, defaults to the rest of the file";
+
+struct Args {
+    validate: bool,
+    repair: bool,
+    headers_only: bool,
+    hex: bool,
+    offset: usize,
+    length: Option<usize>,
+    path: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut validate = false;
+    let mut repair = false;
+    let mut headers_only = false;
+    let mut hex = false;
+    let mut offset = 0;
+    let mut length = None;
+    let mut path = None;
+    let mut args = std::env::args().skip(1).peekable();
+
+    if args.peek().map(String::as_str) == Some("validate") {
+        validate = true;
+        args.next();
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hex" if !validate => hex = true,
+            "--repair" if validate => repair = true,
+            "--headers-only" if validate => headers_only = true,
+            "--offset" => offset = parse_number(args.next(), "--offset")?,
+            "--length" => length = Some(parse_number(args.next(), "--length")?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+
+    match path {
+        Some(path) => Ok(Args {
+            validate,
+            repair,
+            headers_only,
+            hex,
+            offset,
+            length,
+            path,
+        }),
+        None => Err("missing ring buffer file".to_string()),
+    }
+}
+
+fn parse_number(value: Option<String>, option: &str) -> Result<usize, String> {
+    value
+        .ok_or_else(|| format!("{} requires a value", option))?
+        .parse()
+        .map_err(|e| format!("invalid value for {}: {}", option, e))
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|message| {
+        eprintln!("{}\n\n{}", message, USAGE);
+        process::exit(2);
+    });
+
+    let result = map_file(&args).and_then(|mut mmap| {
+        let buffer = wrap_region(&args, &mut mmap)?;
+        if args.validate {
+            validate(&args, buffer)
+        } else {
+            inspect(&args, buffer).map(|_| true)
+        }
+    });
+
+    match result {
+        Ok(true) => {}
+        Ok(false) => process::exit(3),
+        Err(message) => {
+            eprintln!("chair-rb-inspect: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn map_file(args: &Args) -> Result<MmapMut, String> {
+    let to_message = |e: std::io::Error| format!("{}: {}", args.path, e);
+
+    if args.repair {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&args.path)
+            .map_err(to_message)?;
+
+        unsafe { MmapOptions::new().map_mut(&file) }.map_err(to_message)
+    } else {
+        let file = File::open(&args.path).map_err(to_message)?;
+
+        // A private mapping lets the buffer be wrapped mutably without any writes reaching the file.
+        unsafe { MmapOptions::new().map_copy(&file) }.map_err(to_message)
+    }
+}
+
+fn wrap_region(args: &Args, mmap: &mut MmapMut) -> Result<AtomicBuffer, String> {
+    let end = match args.length {
+        Some(length) => args.offset.checked_add(length),
+        None => Some(mmap.len()),
+    };
+    let end = match end {
+        Some(end) if args.offset <= end && end <= mmap.len() => end,
+        _ => {
+            return Err(format!(
+                "region offset={} length={} is outside of file length={}",
+                args.offset,
+                args.length
+                    .unwrap_or(mmap.len().saturating_sub(args.offset)),
+                mmap.len()
+            ))
+        }
+    };
+
+    AtomicBuffer::try_wrap(&mut mmap[args.offset..end])
+        .map_err(|e| format!("region offset={}: {}", args.offset, e))
+}
+
+fn validate(args: &Args, buffer: AtomicBuffer) -> Result<bool, String> {
+    let zero_fill = if args.headers_only {
+        ZeroFill::HeadersOnly
+    } else {
+        ZeroFill::Eager
+    };
+    let inspector = RingBufferInspector::with_zero_fill(buffer, zero_fill)
+        .map_err(|e| format!("{}: {}", args.path, e))?;
+
+    if args.repair {
+        for repair in inspector.repair() {
+            println!("repaired: {}", repair);
+        }
+    }
+
+    let report = inspector.validate();
+    println!(
+        "head={} head cache={} tail={} capacity={}",
+        report.state.head, report.state.head_cache, report.state.tail, report.state.capacity
+    );
+
+    for issue in &report.issues {
+        println!("issue: {}", issue);
+    }
+    println!("{} issue(s)", report.issues.len());
+
+    Ok(report.is_valid())
+}
+
+fn inspect(args: &Args, buffer: AtomicBuffer) -> Result<(), String> {
+    let inspector =
+        RingBufferInspector::new(buffer).map_err(|e| format!("{}: {}", args.path, e))?;
+    let state = inspector.state();
+
+    println!("capacity:            {}", state.capacity);
+    println!("head:                {}", state.head);
+    println!("head cache:          {}", state.head_cache);
+    println!("tail:                {}", state.tail);
+    println!("consumer position:   {}", state.consumer_position);
+    println!("correlation counter: {}", state.correlation_counter);
+    println!("consumer heartbeat:  {}", state.consumer_heartbeat);
+    println!(
+        "pending bytes:       {}",
+        state.tail.wrapping_sub(state.head)
+    );
+    println!();
+    println!(
+        "{:>20} {:>10} {:>10} {:>12}  kind",
+        "position", "offset", "length", "msg_type_id"
+    );
+
+    let mut count = 0;
+    for record in inspector.records() {
+        count += 1;
+        print_record(&record);
+
+        if args.hex && (RecordKind::Message == record.kind || RecordKind::InFlight == record.kind) {
+            // A corrupt length can run past the end of the buffer, so only dump what is there.
+            let available = (inspector.capacity() - record.encoded_msg_offset()).max(0);
+            hex_dump(
+                inspector.buffer(),
+                record.encoded_msg_offset(),
+                record.encoded_msg_length().min(available),
+            );
+        }
+    }
+    println!("{} record(s)", count);
+
+    Ok(())
+}
+
+fn print_record(record: &RecordInfo) {
+    let kind = match record.kind {
+        RecordKind::Message => "message",
+        RecordKind::Padding => "padding",
+        RecordKind::InFlight => "in-flight",
+        RecordKind::Unwritten => "unwritten",
+    };
+
+    println!(
+        "{:>20} {:>10} {:>10} {:>12}  {}",
+        record.position, record.offset, record.length, record.msg_type_id, kind
+    );
+}
+
+fn hex_dump(buffer: &AtomicBuffer, index: i32, length: i32) {
+    for line_start in (0..length).step_by(16) {
+        let line_length = (length - line_start).min(16);
+        let mut hex = String::with_capacity(48);
+        let mut ascii = String::with_capacity(16);
+
+        for i in 0..line_length {
+            let byte = buffer.get::<u8>(index + line_start + i);
+            hex.push_str(&format!("{:02x} ", byte));
+            ascii.push(if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            });
+        }
+
+        println!("{:>24}{:08x}  {:<48} |{}|", "", line_start, hex, ascii);
+    }
+}
@@ -0,0 +1,152 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::buffer::{bounds_check, expanded_capacity, DirectBuffer, MutableDirectBuffer, Primitive};
+use crate::util::Index;
+use std::mem::size_of;
+
+/// Expandable buffer backed by a `Vec<u8>`, growing when written past its capacity. Values may be
+/// unaligned, so use an [`ExpandableDirectByteBuffer`](crate::buffer::ExpandableDirectByteBuffer)
+/// when an encoding needs aligned access.
+#[derive(Debug, Clone)]
+pub struct ExpandableArrayBuffer {
+    byte_array: Vec<u8>,
+}
+
+impl ExpandableArrayBuffer {
+    pub const INITIAL_CAPACITY: Index = 128;
+
+    pub fn new() -> ExpandableArrayBuffer {
+        Self::with_capacity(Self::INITIAL_CAPACITY)
+    }
+
+    pub fn with_capacity(initial_capacity: Index) -> ExpandableArrayBuffer {
+        ExpandableArrayBuffer {
+            byte_array: vec![0; initial_capacity.max(0) as usize],
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.byte_array
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        &mut self.byte_array
+    }
+
+    fn ensure_capacity(&mut self, index: Index, length: usize) {
+        if let Some(new_capacity) = expanded_capacity(self.capacity(), index, length) {
+            self.byte_array.resize(new_capacity as usize, 0);
+        }
+    }
+}
+
+impl Default for ExpandableArrayBuffer {
+    fn default() -> Self {
+        ExpandableArrayBuffer::new()
+    }
+}
+
+impl DirectBuffer for ExpandableArrayBuffer {
+    fn capacity(&self) -> Index {
+        self.byte_array.len() as Index
+    }
+
+    fn get<T: Primitive>(&self, index: Index) -> T {
+        bounds_check(self.capacity(), index, size_of::<T>());
+        unsafe {
+            std::ptr::read_unaligned(self.byte_array.as_ptr().offset(index as isize) as *const T)
+        }
+    }
+
+    fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        bounds_check(self.capacity(), index, dst.len());
+        dst.copy_from_slice(&self.byte_array[index as usize..index as usize + dst.len()]);
+    }
+}
+
+impl MutableDirectBuffer for ExpandableArrayBuffer {
+    fn is_expandable(&self) -> bool {
+        true
+    }
+
+    fn put<T: Primitive>(&mut self, index: Index, value: T) {
+        self.ensure_capacity(index, size_of::<T>());
+        unsafe {
+            std::ptr::write_unaligned(
+                self.byte_array.as_mut_ptr().offset(index as isize) as *mut T,
+                value,
+            );
+        }
+    }
+
+    fn put_slice(&mut self, index: Index, src: &[u8]) {
+        self.ensure_capacity(index, src.len());
+        self.byte_array[index as usize..index as usize + src.len()].copy_from_slice(src);
+    }
+
+    fn set_memory(&mut self, index: Index, length: Index, value: u8) {
+        self.ensure_capacity(index, length.max(0) as usize);
+        self.byte_array[index as usize..(index + length.max(0)) as usize].fill(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_start_with_initial_capacity() {
+        let buffer = ExpandableArrayBuffer::default();
+
+        assert_eq!(buffer.capacity(), ExpandableArrayBuffer::INITIAL_CAPACITY);
+        assert!(buffer.as_slice().iter().all(|b| *b == 0));
+    }
+
+    #[test]
+    fn should_grow_when_writing_past_capacity() {
+        let mut buffer = ExpandableArrayBuffer::with_capacity(8);
+
+        buffer.put_i64(6, 42);
+        buffer.put_slice(20, b"tail");
+
+        assert_eq!(buffer.capacity(), 27);
+        assert_eq!(buffer.get_i64(6), 42);
+        let mut tail = [0_u8; 4];
+        buffer.get_bytes(20, &mut tail);
+        assert_eq!(&tail, b"tail");
+    }
+
+    #[test]
+    fn should_set_memory_past_capacity() {
+        let mut buffer = ExpandableArrayBuffer::with_capacity(4);
+
+        buffer.set_memory(2, 4, 7);
+
+        assert_eq!(buffer.as_slice(), &[0, 0, 7, 7, 7, 7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index=2 length=8 capacity=8")]
+    fn should_panic_when_reading_past_capacity() {
+        let buffer = ExpandableArrayBuffer::with_capacity(8);
+
+        buffer.get_i64(2);
+    }
+}
@@ -0,0 +1,198 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::buffer::{bounds_check, expanded_capacity, DirectBuffer, MutableDirectBuffer, Primitive};
+use crate::util::bit_util::CACHE_LINE_LENGTH;
+use crate::util::Index;
+use std::alloc::{self, Layout};
+use std::mem::size_of;
+use std::ptr::NonNull;
+
+/// Expandable buffer in memory aligned to [`CACHE_LINE_LENGTH`], growing when written past its
+/// capacity. Growing moves the contents, so addresses taken from
+/// [`as_mut_slice`](ExpandableDirectByteBuffer::as_mut_slice) do not survive a write.
+#[derive(Debug)]
+pub struct ExpandableDirectByteBuffer {
+    address: NonNull<u8>,
+    capacity: Index,
+}
+
+unsafe impl Send for ExpandableDirectByteBuffer {}
+
+unsafe impl Sync for ExpandableDirectByteBuffer {}
+
+impl ExpandableDirectByteBuffer {
+    pub const INITIAL_CAPACITY: Index = 128;
+
+    pub fn new() -> ExpandableDirectByteBuffer {
+        Self::with_capacity(Self::INITIAL_CAPACITY)
+    }
+
+    pub fn with_capacity(initial_capacity: Index) -> ExpandableDirectByteBuffer {
+        let capacity = initial_capacity.max(0);
+
+        ExpandableDirectByteBuffer {
+            address: Self::allocate(capacity),
+            capacity,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.address.as_ptr(), self.capacity as usize) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.address.as_ptr(), self.capacity as usize) }
+    }
+
+    fn layout(capacity: Index) -> Layout {
+        Layout::from_size_align(capacity.max(1) as usize, CACHE_LINE_LENGTH as usize).unwrap()
+    }
+
+    fn allocate(capacity: Index) -> NonNull<u8> {
+        let layout = Self::layout(capacity);
+        match NonNull::new(unsafe { alloc::alloc_zeroed(layout) }) {
+            Some(address) => address,
+            None => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    fn ensure_capacity(&mut self, index: Index, length: usize) {
+        if let Some(new_capacity) = expanded_capacity(self.capacity, index, length) {
+            let address = Self::allocate(new_capacity);
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    self.address.as_ptr(),
+                    address.as_ptr(),
+                    self.capacity as usize,
+                );
+                alloc::dealloc(self.address.as_ptr(), Self::layout(self.capacity));
+            }
+
+            self.address = address;
+            self.capacity = new_capacity;
+        }
+    }
+}
+
+impl Default for ExpandableDirectByteBuffer {
+    fn default() -> Self {
+        ExpandableDirectByteBuffer::new()
+    }
+}
+
+impl Drop for ExpandableDirectByteBuffer {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.address.as_ptr(), Self::layout(self.capacity)) }
+    }
+}
+
+impl DirectBuffer for ExpandableDirectByteBuffer {
+    fn capacity(&self) -> Index {
+        self.capacity
+    }
+
+    fn get<T: Primitive>(&self, index: Index) -> T {
+        bounds_check(self.capacity, index, size_of::<T>());
+        unsafe {
+            std::ptr::read_unaligned(self.address.as_ptr().offset(index as isize) as *const T)
+        }
+    }
+
+    fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        bounds_check(self.capacity, index, dst.len());
+        dst.copy_from_slice(&self.as_slice()[index as usize..index as usize + dst.len()]);
+    }
+}
+
+impl MutableDirectBuffer for ExpandableDirectByteBuffer {
+    fn is_expandable(&self) -> bool {
+        true
+    }
+
+    fn put<T: Primitive>(&mut self, index: Index, value: T) {
+        self.ensure_capacity(index, size_of::<T>());
+        unsafe {
+            std::ptr::write_unaligned(
+                self.address.as_ptr().offset(index as isize) as *mut T,
+                value,
+            );
+        }
+    }
+
+    fn put_slice(&mut self, index: Index, src: &[u8]) {
+        self.ensure_capacity(index, src.len());
+        self.as_mut_slice()[index as usize..index as usize + src.len()].copy_from_slice(src);
+    }
+
+    fn set_memory(&mut self, index: Index, length: Index, value: u8) {
+        self.ensure_capacity(index, length.max(0) as usize);
+        self.as_mut_slice()[index as usize..(index + length.max(0)) as usize].fill(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::AtomicBuffer;
+
+    #[test]
+    fn should_allocate_aligned_to_cache_line() {
+        let mut buffer = ExpandableDirectByteBuffer::with_capacity(8);
+        buffer.put_i32(100, 1);
+
+        assert_eq!(buffer.capacity(), 135);
+        assert_eq!(
+            buffer.as_slice().as_ptr() as usize % CACHE_LINE_LENGTH as usize,
+            0
+        );
+    }
+
+    #[test]
+    fn should_keep_contents_when_growing() {
+        let mut buffer = ExpandableDirectByteBuffer::with_capacity(0);
+
+        buffer.put_slice(0, b"chair");
+        buffer.put_i64(64, 7);
+
+        let mut name = [0_u8; 5];
+        buffer.get_bytes(0, &mut name);
+        assert_eq!(&name, b"chair");
+        assert_eq!(buffer.get_i64(64), 7);
+    }
+
+    #[test]
+    fn should_wrap_contents_for_atomic_access() {
+        let mut buffer = ExpandableDirectByteBuffer::new();
+        buffer.put_i64(8, 1);
+
+        let atomic_buffer = AtomicBuffer::wrap(buffer.as_mut_slice());
+
+        assert_eq!(atomic_buffer.get_and_add_i64(8, 1), 1);
+        assert_eq!(buffer.get_i64(8), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "index=0 length=8 capacity=4")]
+    fn should_panic_when_reading_past_capacity() {
+        let buffer = ExpandableDirectByteBuffer::with_capacity(4);
+
+        buffer.get_i64(0);
+    }
+}
@@ -0,0 +1,310 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Buffer traits shared by [`AtomicBuffer`](crate::concurrent::AtomicBuffer) and the expandable
+//! buffers, so encoders can be written once against any of them.
+
+use crate::util::Index;
+use std::mem::size_of;
+
+pub use self::expandable_array_buffer::ExpandableArrayBuffer;
+pub use self::expandable_direct_byte_buffer::ExpandableDirectByteBuffer;
+pub use self::read_only_buffer::ReadOnlyBuffer;
+
+pub mod expandable_array_buffer;
+pub mod expandable_direct_byte_buffer;
+pub mod read_only_buffer;
+
+/// Byte order of a value in a buffer, for encodings which do not use the native order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: ByteOrder = ByteOrder::LittleEndian;
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: ByteOrder = ByteOrder::BigEndian;
+}
+
+/// Plain-data types, and arrays of them, which can be read from or written to a buffer as raw
+/// bytes. Every bit pattern of these types is a valid value, so reading one from arbitrary bytes
+/// can not produce an invalid `bool`, `char` or enum. The trait is sealed.
+pub trait Primitive: Copy + private::Sealed {}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! primitive {
+    ($($t:ty),*) => {
+        $(
+            impl private::Sealed for $t {}
+            impl Primitive for $t {}
+        )*
+    };
+}
+
+primitive!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128, usize, isize, f32, f64);
+
+impl<T: Primitive, const N: usize> private::Sealed for [T; N] {}
+impl<T: Primitive, const N: usize> Primitive for [T; N] {}
+
+/// Read access to a buffer, with values at `index` in native byte order unless stated otherwise.
+pub trait DirectBuffer {
+    fn capacity(&self) -> Index;
+
+    fn get<T: Primitive>(&self, index: Index) -> T;
+
+    fn get_bytes(&self, index: Index, dst: &mut [u8]);
+
+    fn get_i32(&self, index: Index) -> i32 {
+        self.get::<i32>(index)
+    }
+
+    fn get_i64(&self, index: Index) -> i64 {
+        self.get::<i64>(index)
+    }
+
+    fn get_i16_with_order(&self, index: Index, byte_order: ByteOrder) -> i16 {
+        let value = self.get::<i16>(index);
+        match byte_order {
+            ByteOrder::LittleEndian => i16::from_le(value),
+            ByteOrder::BigEndian => i16::from_be(value),
+        }
+    }
+
+    fn get_i32_with_order(&self, index: Index, byte_order: ByteOrder) -> i32 {
+        let value = self.get_i32(index);
+        match byte_order {
+            ByteOrder::LittleEndian => i32::from_le(value),
+            ByteOrder::BigEndian => i32::from_be(value),
+        }
+    }
+
+    fn get_i64_with_order(&self, index: Index, byte_order: ByteOrder) -> i64 {
+        let value = self.get_i64(index);
+        match byte_order {
+            ByteOrder::LittleEndian => i64::from_le(value),
+            ByteOrder::BigEndian => i64::from_be(value),
+        }
+    }
+}
+
+/// Plain byte slices can be read as a buffer, e.g. to write a message to a ring buffer from
+/// memory with no particular alignment.
+impl DirectBuffer for [u8] {
+    #[inline]
+    fn capacity(&self) -> Index {
+        self.len() as Index
+    }
+
+    #[inline]
+    fn get<T: Primitive>(&self, index: Index) -> T {
+        bounds_check(self.len() as Index, index, size_of::<T>());
+        unsafe { std::ptr::read_unaligned(self.as_ptr().offset(index as isize) as *const T) }
+    }
+
+    #[inline]
+    fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        bounds_check(self.len() as Index, index, dst.len());
+        dst.copy_from_slice(&self[index as usize..index as usize + dst.len()]);
+    }
+}
+
+/// Write access to a buffer. Expandable buffers grow to fit writes past their capacity, other
+/// buffers panic.
+pub trait MutableDirectBuffer: DirectBuffer {
+    fn is_expandable(&self) -> bool {
+        false
+    }
+
+    fn put<T: Primitive>(&mut self, index: Index, value: T);
+
+    fn put_slice(&mut self, index: Index, src: &[u8]);
+
+    fn set_memory(&mut self, index: Index, length: Index, value: u8);
+
+    fn put_i32(&mut self, index: Index, value: i32) {
+        self.put(index, value);
+    }
+
+    fn put_i64(&mut self, index: Index, value: i64) {
+        self.put(index, value);
+    }
+
+    fn put_i16_with_order(&mut self, index: Index, value: i16, byte_order: ByteOrder) {
+        self.put(
+            index,
+            match byte_order {
+                ByteOrder::LittleEndian => value.to_le(),
+                ByteOrder::BigEndian => value.to_be(),
+            },
+        );
+    }
+
+    fn put_i32_with_order(&mut self, index: Index, value: i32, byte_order: ByteOrder) {
+        self.put_i32(
+            index,
+            match byte_order {
+                ByteOrder::LittleEndian => value.to_le(),
+                ByteOrder::BigEndian => value.to_be(),
+            },
+        );
+    }
+
+    fn put_i64_with_order(&mut self, index: Index, value: i64, byte_order: ByteOrder) {
+        self.put_i64(
+            index,
+            match byte_order {
+                ByteOrder::LittleEndian => value.to_le(),
+                ByteOrder::BigEndian => value.to_be(),
+            },
+        );
+    }
+}
+
+/// Concurrent access to a buffer shared between threads or processes, as Agrona's `AtomicBuffer`
+/// interface. Indexes must be aligned to the size of the value.
+pub trait AtomicDirectBuffer: MutableDirectBuffer {
+    fn get_i32_volatile(&self, index: Index) -> i32;
+
+    fn get_i64_volatile(&self, index: Index) -> i64;
+
+    fn put_i32_ordered(&self, index: Index, value: i32);
+
+    fn put_i64_ordered(&self, index: Index, value: i64);
+
+    fn get_and_add_i32(&self, index: Index, increment: i32) -> i32;
+
+    fn get_and_add_i64(&self, index: Index, increment: i64) -> i64;
+
+    fn compare_and_set_i64(&self, index: Index, expected_value: i64, updated_value: i64) -> bool;
+}
+
+/// Largest capacity an expandable buffer will grow to, as for a Java array.
+pub const MAX_BUFFER_LENGTH: Index = i32::MAX - 8;
+
+/// Capacity an expandable buffer grows to so `index` and `length` fit, or `None` if it is large
+/// enough already. Capacity grows by half until the write fits.
+pub(crate) fn expanded_capacity(capacity: Index, index: Index, length: usize) -> Option<Index> {
+    if index < 0 || length > i32::MAX as usize {
+        panic!("negative value: index={} length={}", index, length as i64);
+    }
+
+    let resulting_position = index as i64 + length as i64;
+    if resulting_position <= capacity as i64 {
+        return None;
+    }
+    if resulting_position > MAX_BUFFER_LENGTH as i64 {
+        panic!(
+            "index={} length={} maxCapacity={}",
+            index, length, MAX_BUFFER_LENGTH
+        );
+    }
+
+    let mut value = capacity.max(2) as i64;
+    while value < resulting_position {
+        value = (value + (value >> 1)).min(MAX_BUFFER_LENGTH as i64);
+    }
+
+    Some(value as Index)
+}
+
+#[inline]
+pub(crate) fn bounds_check(capacity: Index, index: Index, length: usize) {
+    if index < 0 || index as i64 + length as i64 > capacity as i64 {
+        panic!("index={} length={} capacity={}", index, length, capacity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+    use crate::concurrent::AtomicBuffer;
+
+    fn encode<B: MutableDirectBuffer>(buffer: &mut B, name: &str) -> Index {
+        buffer.put_i64(0, -1);
+        buffer.put_i32_with_order(8, name.len() as i32, ByteOrder::BigEndian);
+        buffer.put_slice(12, name.as_bytes());
+
+        12 + name.len() as Index
+    }
+
+    fn decode<B: DirectBuffer>(buffer: &B) -> (String, i64) {
+        let length = buffer.get_i32_with_order(8, ByteOrder::BigEndian);
+        let mut name = vec![0_u8; length as usize];
+        buffer.get_bytes(12, &mut name);
+
+        (String::from_utf8(name).unwrap(), buffer.get_i64(0))
+    }
+
+    #[test]
+    fn should_encode_the_same_through_any_buffer() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let mut atomic_buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut array_buffer = ExpandableArrayBuffer::with_capacity(4);
+        let mut direct_buffer = ExpandableDirectByteBuffer::with_capacity(4);
+
+        let length = encode(&mut atomic_buffer, "chair");
+        assert_eq!(encode(&mut array_buffer, "chair"), length);
+        assert_eq!(encode(&mut direct_buffer, "chair"), length);
+
+        assert!(!atomic_buffer.is_expandable());
+        assert!(array_buffer.is_expandable());
+        assert_eq!(decode(&atomic_buffer), ("chair".to_owned(), -1));
+        assert_eq!(decode(&array_buffer), ("chair".to_owned(), -1));
+        assert_eq!(decode(&direct_buffer), ("chair".to_owned(), -1));
+        assert_eq!(
+            &array_buffer.as_slice()[..length as usize],
+            &bytes[..length as usize]
+        );
+    }
+
+    #[test]
+    fn should_get_and_put_primitives() {
+        let mut buffer = ExpandableArrayBuffer::with_capacity(4);
+
+        buffer.put(0, 1.5_f64);
+        buffer.put(8, u16::MAX);
+        buffer.put(10, -1_i8);
+
+        assert_eq!(buffer.get::<f64>(0), 1.5);
+        assert_eq!(buffer.get::<u16>(8), u16::MAX);
+        assert_eq!(buffer.get::<i8>(10), -1);
+        assert_eq!(buffer.get::<u8>(10), u8::MAX);
+    }
+
+    #[test]
+    fn should_grow_capacity_by_half_until_write_fits() {
+        assert_eq!(expanded_capacity(128, 0, 128), None);
+        assert_eq!(expanded_capacity(128, 100, 29), Some(192));
+        assert_eq!(expanded_capacity(0, 0, 1), Some(2));
+        assert_eq!(expanded_capacity(128, 0, 1000), Some(1458));
+    }
+
+    #[test]
+    #[should_panic(expected = "negative value: index=-1 length=4")]
+    fn should_panic_on_negative_index() {
+        expanded_capacity(128, -1, 4);
+    }
+}
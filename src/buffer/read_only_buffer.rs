@@ -0,0 +1,139 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::buffer::{DirectBuffer, Primitive};
+use crate::concurrent::AtomicBuffer;
+use crate::util::Index;
+
+/// Read-only access to an [`AtomicBuffer`], as passed to ring buffer read handlers so they can
+/// not write into the ring.
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy)]
+pub struct ReadOnlyBuffer {
+    buffer: AtomicBuffer,
+}
+
+impl ReadOnlyBuffer {
+    pub fn new(buffer: AtomicBuffer) -> ReadOnlyBuffer {
+        ReadOnlyBuffer { buffer }
+    }
+
+    /// Borrow an [`AtomicBuffer`] as read-only without copying it.
+    pub fn from_ref(buffer: &AtomicBuffer) -> &ReadOnlyBuffer {
+        unsafe { &*(buffer as *const AtomicBuffer as *const ReadOnlyBuffer) }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> Index {
+        self.buffer.capacity()
+    }
+
+    /// A read-only buffer over `length` bytes starting at `offset`, see [`AtomicBuffer::view`].
+    pub fn view(&self, offset: Index, length: Index) -> ReadOnlyBuffer {
+        ReadOnlyBuffer::new(self.buffer.view(offset, length))
+    }
+
+    #[inline]
+    pub fn get<T: Primitive>(&self, index: Index) -> T {
+        self.buffer.get(index)
+    }
+
+    #[inline]
+    pub fn get_i32(&self, index: Index) -> i32 {
+        self.buffer.get_i32(index)
+    }
+
+    #[inline]
+    pub fn get_i64(&self, index: Index) -> i64 {
+        self.buffer.get_i64(index)
+    }
+
+    #[inline]
+    pub fn get_i32_volatile(&self, index: Index) -> i32 {
+        self.buffer.get_i32_volatile(index)
+    }
+
+    #[inline]
+    pub fn get_i64_volatile(&self, index: Index) -> i64 {
+        self.buffer.get_i64_volatile(index)
+    }
+
+    #[inline]
+    pub fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        self.buffer.get_bytes(index, dst)
+    }
+
+    /// See [`AtomicBuffer::as_ptr`].
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.buffer.as_ptr()
+    }
+}
+
+impl From<AtomicBuffer> for ReadOnlyBuffer {
+    fn from(buffer: AtomicBuffer) -> Self {
+        ReadOnlyBuffer::new(buffer)
+    }
+}
+
+impl DirectBuffer for ReadOnlyBuffer {
+    #[inline]
+    fn capacity(&self) -> Index {
+        ReadOnlyBuffer::capacity(self)
+    }
+
+    #[inline]
+    fn get<T: Primitive>(&self, index: Index) -> T {
+        ReadOnlyBuffer::get(self, index)
+    }
+
+    #[inline]
+    fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        ReadOnlyBuffer::get_bytes(self, index, dst)
+    }
+
+    #[inline]
+    fn get_i32(&self, index: Index) -> i32 {
+        ReadOnlyBuffer::get_i32(self, index)
+    }
+
+    #[inline]
+    fn get_i64(&self, index: Index) -> i64 {
+        ReadOnlyBuffer::get_i64(self, index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+
+    #[test]
+    fn should_read_through_borrowed_buffer() {
+        let mut bytes = Align64::new([0_u8; 32]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        buffer.put_i64(8, 42);
+
+        let read_only = ReadOnlyBuffer::from_ref(&buffer);
+
+        assert_eq!(read_only.capacity(), 32);
+        assert_eq!(read_only.get_i64_volatile(8), 42);
+        assert_eq!(read_only.view(8, 8).get_i64(0), 42);
+    }
+}
@@ -0,0 +1,835 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/aeron/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! C API mirroring the `aeron_mpsc_rb_*` and `aeron_spsc_rb_*` functions of the Aeron C client,
+//! so existing C code can link against this crate unchanged. The header is `include/chair_rb.h`.
+//!
+//! # Safety
+//!
+//! All functions take a ring buffer previously initialised by `aeron_mpsc_rb_init` or
+//! `aeron_spsc_rb_init` over memory which stays valid for as long as the ring buffer is used. The
+//! memory must be aligned to 8 bytes.
+//! Arguments are validated before calling into the ring buffers so that misuse is reported through
+//! return codes, as in Aeron, rather than by unwinding across the FFI boundary.
+#![allow(clippy::missing_safety_doc)]
+#![allow(non_camel_case_types)]
+
+use std::os::raw::{c_int, c_void};
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::concurrent::ring_buffer::{
+    ControlledAction, ManyToOneRingBuffer, OneToOneRingBuffer, RecordDescriptor, RingBuffer,
+    RingBufferDescriptor,
+};
+use crate::concurrent::AtomicBuffer;
+use crate::util::bit_util;
+use crate::util::Index;
+
+/// Layout of the ring buffer trailer, each counter on its own pair of cache lines.
+#[repr(C)]
+pub struct aeron_rb_descriptor_t {
+    begin_pad: [u8; 128],
+    pub tail_position: i64,
+    tail_pad: [u8; 120],
+    pub head_cache_position: i64,
+    head_cache_pad: [u8; 120],
+    pub head_position: i64,
+    head_pad: [u8; 120],
+    pub correlation_counter: i64,
+    correlation_counter_pad: [u8; 120],
+    pub consumer_heartbeat: i64,
+    consumer_heartbeat_pad: [u8; 120],
+}
+
+#[repr(C)]
+pub struct aeron_mpsc_rb_t {
+    pub buffer: *mut u8,
+    pub descriptor: *mut aeron_rb_descriptor_t,
+    pub capacity: usize,
+    pub max_message_length: usize,
+}
+
+#[repr(C)]
+pub struct aeron_spsc_rb_t {
+    pub buffer: *mut u8,
+    pub descriptor: *mut aeron_rb_descriptor_t,
+    pub capacity: usize,
+    pub max_message_length: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum aeron_rb_write_result_t {
+    AERON_RB_SUCCESS = 0,
+    AERON_RB_ERROR = -2,
+    AERON_RB_FULL = -1,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum aeron_rb_read_action_t {
+    AERON_RB_ABORT,
+    AERON_RB_BREAK,
+    AERON_RB_COMMIT,
+    AERON_RB_CONTINUE,
+}
+
+pub type aeron_rb_handler_t = Option<unsafe extern "C" fn(i32, *const c_void, usize, *mut c_void)>;
+
+pub type aeron_rb_controlled_handler_t =
+    Option<unsafe extern "C" fn(i32, *const c_void, usize, *mut c_void) -> aeron_rb_read_action_t>;
+
+impl From<aeron_rb_read_action_t> for ControlledAction {
+    fn from(action: aeron_rb_read_action_t) -> Self {
+        match action {
+            aeron_rb_read_action_t::AERON_RB_ABORT => ControlledAction::Abort,
+            aeron_rb_read_action_t::AERON_RB_BREAK => ControlledAction::Break,
+            aeron_rb_read_action_t::AERON_RB_COMMIT => ControlledAction::Commit,
+            aeron_rb_read_action_t::AERON_RB_CONTINUE => ControlledAction::Continue,
+        }
+    }
+}
+
+unsafe fn init(
+    buffer: *mut c_void,
+    length: usize,
+) -> Option<(*mut u8, *mut aeron_rb_descriptor_t, usize, usize)> {
+    let trailer_length = RingBufferDescriptor::TRAILER_LENGTH as usize;
+    if buffer.is_null()
+        || (buffer as usize) % AtomicBuffer::ALIGNMENT != 0
+        || length < trailer_length
+        || length > Index::MAX as usize
+    {
+        return None;
+    }
+
+    let capacity = length - trailer_length;
+    if !bit_util::is_power_of_two(capacity as Index) {
+        return None;
+    }
+
+    let buffer = buffer as *mut u8;
+    let descriptor = buffer.add(capacity) as *mut aeron_rb_descriptor_t;
+
+    Some((buffer, descriptor, capacity, capacity / 8))
+}
+
+unsafe fn wrap(buffer: *mut u8, capacity: usize) -> AtomicBuffer {
+    AtomicBuffer::wrap(slice::from_raw_parts_mut(
+        buffer,
+        capacity + RingBufferDescriptor::TRAILER_LENGTH as usize,
+    ))
+}
+
+unsafe fn write<R: RingBuffer>(
+    ring_buffer: &R,
+    msg_type_id: i32,
+    msg: *const c_void,
+    length: usize,
+) -> aeron_rb_write_result_t {
+    if msg_type_id < 1
+        || length > ring_buffer.max_msg_length() as usize
+        || (msg.is_null() && length > 0)
+    {
+        return aeron_rb_write_result_t::AERON_RB_ERROR;
+    }
+
+    let msg = if msg.is_null() {
+        NonNull::dangling().as_ptr()
+    } else {
+        msg as *const u8
+    };
+    let src_buffer = slice::from_raw_parts(msg, length);
+
+    if ring_buffer.write(msg_type_id, src_buffer, 0, length as Index) {
+        aeron_rb_write_result_t::AERON_RB_SUCCESS
+    } else {
+        aeron_rb_write_result_t::AERON_RB_FULL
+    }
+}
+
+fn try_claim<R: RingBuffer>(ring_buffer: &R, msg_type_id: i32, length: usize) -> i32 {
+    if msg_type_id < 1 || length > ring_buffer.max_msg_length() as usize {
+        return aeron_rb_write_result_t::AERON_RB_ERROR as i32;
+    }
+
+    let index = ring_buffer.try_claim(msg_type_id, length as Index);
+    if index == R::INSUFFICIENT_CAPACITY {
+        return aeron_rb_write_result_t::AERON_RB_FULL as i32;
+    }
+
+    index
+}
+
+fn is_claimed<R: RingBuffer>(ring_buffer: &R, offset: i32) -> bool {
+    let record_index = offset - RecordDescriptor::HEADER_LENGTH;
+    if record_index < 0
+        || record_index > ring_buffer.capacity() - RecordDescriptor::HEADER_LENGTH
+        || record_index % RecordDescriptor::ALIGNMENT != 0
+    {
+        return false;
+    }
+
+    ring_buffer
+        .buffer()
+        .get_i32_volatile(RecordDescriptor::length_offset(record_index))
+        < 0
+}
+
+fn commit<R: RingBuffer>(ring_buffer: &R, offset: i32) -> c_int {
+    if !is_claimed(ring_buffer, offset) {
+        return -1;
+    }
+
+    ring_buffer.commit(offset);
+    0
+}
+
+fn abort<R: RingBuffer>(ring_buffer: &R, offset: i32) -> c_int {
+    if !is_claimed(ring_buffer, offset) {
+        return -1;
+    }
+
+    ring_buffer.abort(offset);
+    0
+}
+
+unsafe fn read<R: RingBuffer>(
+    ring_buffer: &R,
+    handler: aeron_rb_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    let handler = match handler {
+        Some(handler) => handler,
+        None => return 0,
+    };
+
+    ring_buffer.read(
+        |msg_type_id, buffer, index, length| {
+            handler(
+                msg_type_id,
+                buffer.as_ptr().add(index as usize) as *const c_void,
+                length as usize,
+                clientd,
+            )
+        },
+        message_count_limit.min(u32::MAX as usize) as u32,
+    ) as usize
+}
+
+unsafe fn controlled_read<R: RingBuffer>(
+    ring_buffer: &R,
+    handler: aeron_rb_controlled_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    let handler = match handler {
+        Some(handler) => handler,
+        None => return 0,
+    };
+
+    ring_buffer.controlled_read(
+        |msg_type_id, buffer, index, length| {
+            handler(
+                msg_type_id,
+                buffer.as_ptr().add(index as usize) as *const c_void,
+                length as usize,
+                clientd,
+            )
+            .into()
+        },
+        message_count_limit.min(u32::MAX as usize) as u32,
+    ) as usize
+}
+
+/// The handles keep the layout of Aeron's, so the ring buffer is rebuilt from their fields on
+/// each call, which only copies a pointer and lengths.
+#[inline]
+unsafe fn mpsc(ring_buffer: *mut aeron_mpsc_rb_t) -> ManyToOneRingBuffer {
+    ManyToOneRingBuffer::new(wrap((*ring_buffer).buffer, (*ring_buffer).capacity))
+}
+
+#[inline]
+unsafe fn spsc(ring_buffer: *mut aeron_spsc_rb_t) -> OneToOneRingBuffer {
+    OneToOneRingBuffer::new(wrap((*ring_buffer).buffer, (*ring_buffer).capacity))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_init(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    buffer: *mut c_void,
+    length: usize,
+) -> c_int {
+    if ring_buffer.is_null() {
+        return -1;
+    }
+
+    match init(buffer, length) {
+        Some((buffer, descriptor, capacity, max_message_length)) => {
+            *ring_buffer = aeron_mpsc_rb_t {
+                buffer,
+                descriptor,
+                capacity,
+                max_message_length,
+            };
+            0
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_write(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    msg_type_id: i32,
+    msg: *const c_void,
+    length: usize,
+) -> aeron_rb_write_result_t {
+    write(&mpsc(ring_buffer), msg_type_id, msg, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_try_claim(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    msg_type_id: i32,
+    length: usize,
+) -> i32 {
+    try_claim(&mpsc(ring_buffer), msg_type_id, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_commit(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    offset: i32,
+) -> c_int {
+    commit(&mpsc(ring_buffer), offset)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_abort(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    offset: i32,
+) -> c_int {
+    abort(&mpsc(ring_buffer), offset)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_read(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    handler: aeron_rb_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    read(&mpsc(ring_buffer), handler, clientd, message_count_limit)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_controlled_read(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    handler: aeron_rb_controlled_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    controlled_read(&mpsc(ring_buffer), handler, clientd, message_count_limit)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_next_correlation_id(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+) -> i64 {
+    mpsc(ring_buffer).next_correlation_id()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_consumer_heartbeat_time(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+    now_ms: i64,
+) {
+    mpsc(ring_buffer).set_consumer_heartbeat_time(now_ms)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_consumer_heartbeat_time_value(
+    ring_buffer: *mut aeron_mpsc_rb_t,
+) -> i64 {
+    mpsc(ring_buffer).consumer_heartbeat_time()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_unblock(ring_buffer: *mut aeron_mpsc_rb_t) -> bool {
+    mpsc(ring_buffer).unblock()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_consumer_position(ring_buffer: *mut aeron_mpsc_rb_t) -> i64 {
+    mpsc(ring_buffer).consumer_position()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_mpsc_rb_producer_position(ring_buffer: *mut aeron_mpsc_rb_t) -> i64 {
+    mpsc(ring_buffer).producer_position()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_init(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    buffer: *mut c_void,
+    length: usize,
+) -> c_int {
+    if ring_buffer.is_null() {
+        return -1;
+    }
+
+    match init(buffer, length) {
+        Some((buffer, descriptor, capacity, max_message_length)) => {
+            *ring_buffer = aeron_spsc_rb_t {
+                buffer,
+                descriptor,
+                capacity,
+                max_message_length,
+            };
+            0
+        }
+        None => -1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_write(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    msg_type_id: i32,
+    msg: *const c_void,
+    length: usize,
+) -> aeron_rb_write_result_t {
+    write(&spsc(ring_buffer), msg_type_id, msg, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_try_claim(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    msg_type_id: i32,
+    length: usize,
+) -> i32 {
+    try_claim(&spsc(ring_buffer), msg_type_id, length)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_commit(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    offset: i32,
+) -> c_int {
+    commit(&spsc(ring_buffer), offset)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_abort(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    offset: i32,
+) -> c_int {
+    abort(&spsc(ring_buffer), offset)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_read(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    handler: aeron_rb_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    read(&spsc(ring_buffer), handler, clientd, message_count_limit)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_controlled_read(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    handler: aeron_rb_controlled_handler_t,
+    clientd: *mut c_void,
+    message_count_limit: usize,
+) -> usize {
+    controlled_read(&spsc(ring_buffer), handler, clientd, message_count_limit)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_next_correlation_id(
+    ring_buffer: *mut aeron_spsc_rb_t,
+) -> i64 {
+    spsc(ring_buffer).next_correlation_id()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_consumer_heartbeat_time(
+    ring_buffer: *mut aeron_spsc_rb_t,
+    now_ms: i64,
+) {
+    spsc(ring_buffer).set_consumer_heartbeat_time(now_ms)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_consumer_heartbeat_time_value(
+    ring_buffer: *mut aeron_spsc_rb_t,
+) -> i64 {
+    spsc(ring_buffer).consumer_heartbeat_time()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_unblock(ring_buffer: *mut aeron_spsc_rb_t) -> bool {
+    spsc(ring_buffer).unblock()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_consumer_position(ring_buffer: *mut aeron_spsc_rb_t) -> i64 {
+    spsc(ring_buffer).consumer_position()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn aeron_spsc_rb_producer_position(ring_buffer: *mut aeron_spsc_rb_t) -> i64 {
+    spsc(ring_buffer).producer_position()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::AlignedBox;
+    use std::mem::{size_of, MaybeUninit};
+    use std::ptr;
+
+    const CAPACITY: usize = 1024;
+    const BUFFER_SZ: usize = CAPACITY + RingBufferDescriptor::TRAILER_LENGTH as usize;
+    const MSG_TYPE_ID: i32 = 101;
+
+    struct CApiTest {
+        buffer: AlignedBox<[u8]>,
+    }
+
+    impl Default for CApiTest {
+        fn default() -> Self {
+            CApiTest {
+                buffer: AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64),
+            }
+        }
+    }
+
+    impl CApiTest {
+        fn mpsc(&mut self) -> aeron_mpsc_rb_t {
+            let mut ring_buffer = MaybeUninit::uninit();
+            let result = unsafe {
+                aeron_mpsc_rb_init(
+                    ring_buffer.as_mut_ptr(),
+                    self.buffer.as_mut_ptr() as *mut c_void,
+                    BUFFER_SZ,
+                )
+            };
+
+            assert_eq!(result, 0);
+            unsafe { ring_buffer.assume_init() }
+        }
+
+        fn spsc(&mut self) -> aeron_spsc_rb_t {
+            let mut ring_buffer = MaybeUninit::uninit();
+            let result = unsafe {
+                aeron_spsc_rb_init(
+                    ring_buffer.as_mut_ptr(),
+                    self.buffer.as_mut_ptr() as *mut c_void,
+                    BUFFER_SZ,
+                )
+            };
+
+            assert_eq!(result, 0);
+            unsafe { ring_buffer.assume_init() }
+        }
+    }
+
+    unsafe extern "C" fn collect(
+        msg_type_id: i32,
+        msg: *const c_void,
+        length: usize,
+        clientd: *mut c_void,
+    ) {
+        let messages = &mut *(clientd as *mut Vec<(i32, Vec<u8>)>);
+        messages.push((
+            msg_type_id,
+            slice::from_raw_parts(msg as *const u8, length).to_vec(),
+        ));
+    }
+
+    unsafe extern "C" fn commit_first(
+        _: i32,
+        _: *const c_void,
+        _: usize,
+        clientd: *mut c_void,
+    ) -> aeron_rb_read_action_t {
+        let times_called = &mut *(clientd as *mut i32);
+        *times_called += 1;
+        if *times_called == 1 {
+            aeron_rb_read_action_t::AERON_RB_COMMIT
+        } else {
+            aeron_rb_read_action_t::AERON_RB_ABORT
+        }
+    }
+
+    #[test]
+    fn should_match_trailer_layout() {
+        assert_eq!(
+            size_of::<aeron_rb_descriptor_t>(),
+            RingBufferDescriptor::TRAILER_LENGTH as usize
+        );
+
+        let descriptor = MaybeUninit::<aeron_rb_descriptor_t>::uninit();
+        let base = descriptor.as_ptr();
+        let offset = |field: *const i64| field as usize - base as usize;
+        unsafe {
+            assert_eq!(
+                offset(ptr::addr_of!((*base).tail_position)),
+                RingBufferDescriptor::TAIL_POSITION_OFFSET as usize
+            );
+            assert_eq!(
+                offset(ptr::addr_of!((*base).head_cache_position)),
+                RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET as usize
+            );
+            assert_eq!(
+                offset(ptr::addr_of!((*base).head_position)),
+                RingBufferDescriptor::HEAD_POSITION_OFFSET as usize
+            );
+            assert_eq!(
+                offset(ptr::addr_of!((*base).correlation_counter)),
+                RingBufferDescriptor::CORRELATION_COUNTER_OFFSET as usize
+            );
+            assert_eq!(
+                offset(ptr::addr_of!((*base).consumer_heartbeat)),
+                RingBufferDescriptor::CONSUMER_HEARTBEAT_OFFSET as usize
+            );
+        }
+    }
+
+    #[test]
+    fn should_match_aeron_handle_layout() {
+        let word = size_of::<usize>();
+        assert_eq!(size_of::<aeron_mpsc_rb_t>(), 4 * word);
+        assert_eq!(size_of::<aeron_spsc_rb_t>(), 4 * word);
+
+        let mpsc = MaybeUninit::<aeron_mpsc_rb_t>::uninit();
+        let base = mpsc.as_ptr();
+        let offset = |field: *const u8| field as usize - base as usize;
+        unsafe {
+            assert_eq!(offset(ptr::addr_of!((*base).buffer) as *const u8), 0);
+            assert_eq!(offset(ptr::addr_of!((*base).descriptor) as *const u8), word);
+            assert_eq!(offset(ptr::addr_of!((*base).capacity) as *const u8), 2 * word);
+            assert_eq!(
+                offset(ptr::addr_of!((*base).max_message_length) as *const u8),
+                3 * word
+            );
+        }
+
+        let spsc = MaybeUninit::<aeron_spsc_rb_t>::uninit();
+        let base = spsc.as_ptr();
+        let offset = |field: *const u8| field as usize - base as usize;
+        unsafe {
+            assert_eq!(offset(ptr::addr_of!((*base).buffer) as *const u8), 0);
+            assert_eq!(offset(ptr::addr_of!((*base).descriptor) as *const u8), word);
+            assert_eq!(offset(ptr::addr_of!((*base).capacity) as *const u8), 2 * word);
+            assert_eq!(
+                offset(ptr::addr_of!((*base).max_message_length) as *const u8),
+                3 * word
+            );
+        }
+    }
+
+    #[test]
+    fn should_reject_capacity_not_power_of_two() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = MaybeUninit::<aeron_mpsc_rb_t>::uninit();
+
+        let result = unsafe {
+            aeron_mpsc_rb_init(
+                ring_buffer.as_mut_ptr(),
+                context.buffer.as_mut_ptr() as *mut c_void,
+                BUFFER_SZ - 8,
+            )
+        };
+
+        assert_eq!(result, -1);
+    }
+
+    #[test]
+    fn should_reject_misaligned_buffer() {
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ + 8, 64);
+        let mut ring_buffer = MaybeUninit::<aeron_spsc_rb_t>::uninit();
+
+        let result = unsafe {
+            aeron_spsc_rb_init(
+                ring_buffer.as_mut_ptr(),
+                buffer.as_mut_ptr().add(1) as *mut c_void,
+                BUFFER_SZ,
+            )
+        };
+
+        assert_eq!(result, -1);
+    }
+
+    #[test]
+    fn should_write_and_read_message() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = context.mpsc();
+        let msg = [1_u8, 2, 3, 4, 5];
+
+        assert_eq!(ring_buffer.capacity, CAPACITY);
+        assert_eq!(ring_buffer.max_message_length, CAPACITY / 8);
+
+        let result = unsafe {
+            aeron_mpsc_rb_write(
+                &mut ring_buffer,
+                MSG_TYPE_ID,
+                msg.as_ptr() as *const c_void,
+                msg.len(),
+            )
+        };
+        assert_eq!(result, aeron_rb_write_result_t::AERON_RB_SUCCESS);
+        assert_eq!(unsafe { (*ring_buffer.descriptor).tail_position }, 16);
+
+        let mut messages: Vec<(i32, Vec<u8>)> = vec![];
+        let messages_read = unsafe {
+            aeron_mpsc_rb_read(
+                &mut ring_buffer,
+                Some(collect),
+                &mut messages as *mut _ as *mut c_void,
+                10,
+            )
+        };
+
+        assert_eq!(messages_read, 1);
+        assert_eq!(messages, vec![(MSG_TYPE_ID, msg.to_vec())]);
+        assert_eq!(
+            unsafe { aeron_mpsc_rb_consumer_position(&mut ring_buffer) },
+            16
+        );
+    }
+
+    #[test]
+    fn should_return_error_for_invalid_arguments() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = context.mpsc();
+        let msg = [0_u8; CAPACITY];
+
+        unsafe {
+            assert_eq!(
+                aeron_mpsc_rb_write(&mut ring_buffer, 0, msg.as_ptr() as *const c_void, 8),
+                aeron_rb_write_result_t::AERON_RB_ERROR
+            );
+            assert_eq!(
+                aeron_mpsc_rb_write(
+                    &mut ring_buffer,
+                    MSG_TYPE_ID,
+                    msg.as_ptr() as *const c_void,
+                    CAPACITY / 8 + 1
+                ),
+                aeron_rb_write_result_t::AERON_RB_ERROR
+            );
+            assert_eq!(
+                aeron_mpsc_rb_try_claim(&mut ring_buffer, MSG_TYPE_ID, CAPACITY),
+                aeron_rb_write_result_t::AERON_RB_ERROR as i32
+            );
+            assert_eq!(aeron_mpsc_rb_commit(&mut ring_buffer, 8), -1);
+            assert_eq!(aeron_mpsc_rb_abort(&mut ring_buffer, -8), -1);
+        }
+    }
+
+    #[test]
+    fn should_claim_commit_and_abort() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = context.spsc();
+
+        unsafe {
+            let offset = aeron_spsc_rb_try_claim(&mut ring_buffer, MSG_TYPE_ID, 4);
+            assert_eq!(offset, 8);
+            ptr::write(ring_buffer.buffer.add(offset as usize) as *mut i32, 7);
+            assert_eq!(aeron_spsc_rb_commit(&mut ring_buffer, offset), 0);
+            assert_eq!(aeron_spsc_rb_commit(&mut ring_buffer, offset), -1);
+
+            let offset = aeron_spsc_rb_try_claim(&mut ring_buffer, MSG_TYPE_ID, 4);
+            assert_eq!(offset, 24);
+            assert_eq!(aeron_spsc_rb_abort(&mut ring_buffer, offset), 0);
+
+            let mut messages: Vec<(i32, Vec<u8>)> = vec![];
+            let messages_read = aeron_spsc_rb_read(
+                &mut ring_buffer,
+                Some(collect),
+                &mut messages as *mut _ as *mut c_void,
+                10,
+            );
+
+            assert_eq!(messages_read, 1);
+            assert_eq!(messages, vec![(MSG_TYPE_ID, 7_i32.to_ne_bytes().to_vec())]);
+            assert_eq!(aeron_spsc_rb_consumer_position(&mut ring_buffer), 32);
+        }
+    }
+
+    #[test]
+    fn should_stop_controlled_read_on_abort() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = context.mpsc();
+        let msg = [0_u8; 8];
+
+        unsafe {
+            for _ in 0..2 {
+                assert_eq!(
+                    aeron_mpsc_rb_write(
+                        &mut ring_buffer,
+                        MSG_TYPE_ID,
+                        msg.as_ptr() as *const c_void,
+                        msg.len()
+                    ),
+                    aeron_rb_write_result_t::AERON_RB_SUCCESS
+                );
+            }
+
+            let mut times_called = 0_i32;
+            let messages_read = aeron_mpsc_rb_controlled_read(
+                &mut ring_buffer,
+                Some(commit_first),
+                &mut times_called as *mut _ as *mut c_void,
+                10,
+            );
+
+            assert_eq!(messages_read, 1);
+            assert_eq!(times_called, 2);
+            assert_eq!(aeron_mpsc_rb_consumer_position(&mut ring_buffer), 16);
+            assert_eq!(aeron_mpsc_rb_producer_position(&mut ring_buffer), 32);
+        }
+    }
+
+    #[test]
+    fn should_update_correlation_id_and_heartbeat() {
+        let mut context = CApiTest::default();
+        let mut ring_buffer = context.mpsc();
+
+        unsafe {
+            assert_eq!(aeron_mpsc_rb_next_correlation_id(&mut ring_buffer), 0);
+            assert_eq!(aeron_mpsc_rb_next_correlation_id(&mut ring_buffer), 1);
+
+            aeron_mpsc_rb_consumer_heartbeat_time(&mut ring_buffer, 1234);
+            assert_eq!(
+                aeron_mpsc_rb_consumer_heartbeat_time_value(&mut ring_buffer),
+                1234
+            );
+            assert_eq!((*ring_buffer.descriptor).consumer_heartbeat, 1234);
+        }
+    }
+}
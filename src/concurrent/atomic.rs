@@ -17,7 +17,7 @@
  * limitations under the License.
  */
 
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::atomic::{AtomicI32, AtomicI64, Ordering};
 
 #[inline]
 pub fn thread_fence() {
@@ -42,6 +42,9 @@ pub fn release() {
 #[inline]
 pub fn cpu_pause() {}
 
+/// # Safety
+///
+/// `source` must be valid for reads and properly aligned for `T`.
 #[inline]
 pub unsafe fn get_volatile<T>(source: *const T) -> T {
     let sequence: T = std::ptr::read_volatile(source);
@@ -49,17 +52,34 @@ pub unsafe fn get_volatile<T>(source: *const T) -> T {
     sequence
 }
 
+/// # Safety
+///
+/// `dest` must be valid for writes and properly aligned for `T`.
 #[inline]
 pub unsafe fn put_ordered<T>(dest: *mut T, value: T) {
     release();
     std::ptr::write(dest, value);
 }
 
+/// # Safety
+///
+/// `src` must be valid for reads and writes and aligned to 8 bytes.
 #[inline]
 pub unsafe fn get_and_add_i64(src: *const i64, increment: i64) -> i64 {
     (&*(src as *const AtomicI64)).fetch_add(increment, Ordering::SeqCst)
 }
 
+/// # Safety
+///
+/// `src` must be valid for reads and writes and aligned to 4 bytes.
+#[inline]
+pub unsafe fn get_and_add_i32(src: *const i32, increment: i32) -> i32 {
+    (&*(src as *const AtomicI32)).fetch_add(increment, Ordering::SeqCst)
+}
+
+/// # Safety
+///
+/// `address` must be valid for reads and writes and aligned to 8 bytes.
 #[inline]
 pub unsafe fn compare_exchange(address: *const i64, expected: i64, desired: i64) -> i64 {
     match (&*(address as *const AtomicI64)).compare_exchange(
@@ -0,0 +1,253 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ops::Deref;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Clock returning the time in milliseconds since 1 Jan 1970 UTC.
+pub trait EpochClock {
+    fn time(&self) -> i64;
+}
+
+/// Clock returning a time in nanoseconds, only meaningful relative to other readings of the same
+/// clock unless it documents its epoch.
+pub trait NanoClock {
+    fn nano_time(&self) -> i64;
+}
+
+impl<T: EpochClock + ?Sized> EpochClock for &T {
+    #[inline]
+    fn time(&self) -> i64 {
+        (**self).time()
+    }
+}
+
+impl<T: EpochClock + ?Sized> EpochClock for Arc<T> {
+    #[inline]
+    fn time(&self) -> i64 {
+        self.deref().time()
+    }
+}
+
+impl<T: NanoClock + ?Sized> NanoClock for &T {
+    #[inline]
+    fn nano_time(&self) -> i64 {
+        (**self).nano_time()
+    }
+}
+
+impl<T: NanoClock + ?Sized> NanoClock for Arc<T> {
+    #[inline]
+    fn nano_time(&self) -> i64 {
+        self.deref().nano_time()
+    }
+}
+
+/// The system wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemEpochClock;
+
+impl EpochClock for SystemEpochClock {
+    #[inline]
+    fn time(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// The system monotonic clock, in nanoseconds since the first time it was read in this process.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemNanoClock;
+
+impl NanoClock for SystemNanoClock {
+    #[inline]
+    fn nano_time(&self) -> i64 {
+        static START: OnceLock<Instant> = OnceLock::new();
+
+        START.get_or_init(Instant::now).elapsed().as_nanos() as i64
+    }
+}
+
+/// Nanoseconds since 1 Jan 1970 UTC, derived from the monotonic clock and an offset sampled from
+/// the wall clock on creation. Unlike the wall clock it never goes backwards.
+#[derive(Debug, Clone, Copy)]
+pub struct OffsetEpochNanoClock {
+    initial_instant: Instant,
+    initial_epoch_ns: i64,
+}
+
+impl OffsetEpochNanoClock {
+    pub fn new() -> OffsetEpochNanoClock {
+        let initial_epoch_ns = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as i64)
+            .unwrap_or(0);
+
+        OffsetEpochNanoClock {
+            initial_instant: Instant::now(),
+            initial_epoch_ns,
+        }
+    }
+}
+
+impl Default for OffsetEpochNanoClock {
+    fn default() -> Self {
+        OffsetEpochNanoClock::new()
+    }
+}
+
+impl NanoClock for OffsetEpochNanoClock {
+    #[inline]
+    fn nano_time(&self) -> i64 {
+        self.initial_epoch_ns + self.initial_instant.elapsed().as_nanos() as i64
+    }
+}
+
+impl EpochClock for OffsetEpochNanoClock {
+    #[inline]
+    fn time(&self) -> i64 {
+        self.nano_time() / 1_000_000
+    }
+}
+
+/// An [`EpochClock`] which only changes when updated, so an agent can read the time once per duty
+/// cycle and share it cheaply, and tests can drive time by hand.
+#[derive(Debug, Default)]
+pub struct CachedEpochClock {
+    time_ms: AtomicI64,
+}
+
+impl CachedEpochClock {
+    pub fn new(time_ms: i64) -> CachedEpochClock {
+        CachedEpochClock {
+            time_ms: AtomicI64::new(time_ms),
+        }
+    }
+
+    pub fn update(&self, time_ms: i64) {
+        self.time_ms.store(time_ms, Ordering::Release);
+    }
+
+    pub fn advance(&self, delta_ms: i64) {
+        self.time_ms.fetch_add(delta_ms, Ordering::AcqRel);
+    }
+}
+
+impl EpochClock for CachedEpochClock {
+    #[inline]
+    fn time(&self) -> i64 {
+        self.time_ms.load(Ordering::Acquire)
+    }
+}
+
+/// A [`NanoClock`] which only changes when updated, see [`CachedEpochClock`].
+#[derive(Debug, Default)]
+pub struct CachedNanoClock {
+    time_ns: AtomicI64,
+}
+
+impl CachedNanoClock {
+    pub fn new(time_ns: i64) -> CachedNanoClock {
+        CachedNanoClock {
+            time_ns: AtomicI64::new(time_ns),
+        }
+    }
+
+    pub fn update(&self, time_ns: i64) {
+        self.time_ns.store(time_ns, Ordering::Release);
+    }
+
+    pub fn advance(&self, delta_ns: i64) {
+        self.time_ns.fetch_add(delta_ns, Ordering::AcqRel);
+    }
+}
+
+impl NanoClock for CachedNanoClock {
+    #[inline]
+    fn nano_time(&self) -> i64 {
+        self.time_ns.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn should_only_change_cached_clocks_when_updated() {
+        let epoch_clock = CachedEpochClock::new(1000);
+        let nano_clock = CachedNanoClock::default();
+
+        assert_eq!(epoch_clock.time(), 1000);
+        assert_eq!(nano_clock.nano_time(), 0);
+
+        epoch_clock.advance(5);
+        nano_clock.update(42);
+
+        assert_eq!(epoch_clock.time(), 1005);
+        assert_eq!(nano_clock.nano_time(), 42);
+
+        epoch_clock.update(7);
+        nano_clock.advance(-2);
+
+        assert_eq!(epoch_clock.time(), 7);
+        assert_eq!(nano_clock.nano_time(), 40);
+    }
+
+    #[test]
+    fn should_share_cached_clock_by_reference() {
+        let clock = Arc::new(CachedEpochClock::new(1));
+
+        fn read<C: EpochClock>(clock: C) -> i64 {
+            clock.time()
+        }
+
+        assert_eq!(read(&*clock), 1);
+        clock.advance(1);
+        assert_eq!(read(Arc::clone(&clock)), 2);
+    }
+
+    #[test]
+    fn should_advance_system_nano_clock() {
+        let clock = SystemNanoClock;
+        let start = clock.nano_time();
+
+        thread::sleep(Duration::from_millis(1));
+
+        assert!(clock.nano_time() - start >= 1_000_000);
+    }
+
+    #[test]
+    fn should_track_wall_clock_with_offset_epoch_nano_clock() {
+        let clock = OffsetEpochNanoClock::new();
+        let wall_clock_ms = SystemEpochClock.time();
+
+        let first = clock.nano_time();
+        let second = clock.nano_time();
+
+        assert!((clock.time() - wall_clock_ms).abs() < 1000);
+        assert!(first <= second);
+    }
+}
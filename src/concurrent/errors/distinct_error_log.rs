@@ -0,0 +1,397 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::concurrent::{AtomicBuffer, EpochClock, SystemEpochClock};
+use crate::util::{bit_util, Index};
+use std::any::type_name;
+use std::error::Error;
+use std::sync::Mutex;
+
+/**
+* Distinct record of error observations. Rather than filling a log with repeats of the same error,
+* an error is stored once with a count of how often it has been observed and the times of the
+* first and last observations.
+* <p>
+* The log is append only and laid out in the buffer as a series of records:
+* <pre>
+*   0                   1                   2                   3
+*   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |                            Length                             |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |                     Observation Count                         |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |                Last Observation Timestamp                     |
+*  |                                                               |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |               First Observation Timestamp                     |
+*  |                                                               |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |                     UTF-8 Encoded Error                      ...
+* ...                                                              |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+* </pre>
+* Records are aligned to 8 bytes and a record is only visible to readers once its length has been
+* written. Errors are distinct by type and message, with the error encoded as
+* `type: message` followed by a `Caused by: message` line for each source in its chain.
+*/
+pub struct DistinctErrorLog<C = SystemEpochClock> {
+    buffer: AtomicBuffer,
+    clock: C,
+    observations: Mutex<Observations>,
+}
+
+unsafe impl<C: EpochClock + Send> Send for DistinctErrorLog<C> {}
+
+unsafe impl<C: EpochClock + Sync> Sync for DistinctErrorLog<C> {}
+
+struct Observations {
+    next_offset: Index,
+    distinct: Vec<DistinctObservation>,
+}
+
+struct DistinctObservation {
+    encoded_error: String,
+    offset: Index,
+}
+
+impl DistinctErrorLog {
+    pub const LENGTH_OFFSET: Index = 0;
+    pub const OBSERVATION_COUNT_OFFSET: Index = 4;
+    pub const LAST_OBSERVATION_TIMESTAMP_OFFSET: Index = 8;
+    pub const FIRST_OBSERVATION_TIMESTAMP_OFFSET: Index = 16;
+    pub const ENCODED_ERROR_OFFSET: Index = 24;
+    pub const RECORD_ALIGNMENT: Index = 8;
+}
+
+impl<C: EpochClock> DistinctErrorLog<C> {
+    /// Create a log over `buffer`, which should be zeroed, timestamping observations with `clock`.
+    pub fn new(buffer: AtomicBuffer, clock: C) -> DistinctErrorLog<C> {
+        DistinctErrorLog {
+            buffer,
+            clock,
+            observations: Mutex::new(Observations {
+                next_offset: 0,
+                distinct: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
+    /// Record an observation of an error, returning false if there is no space left in the log for
+    /// a new distinct error.
+    ///
+    /// The type of the error is taken from `E`, so callers must pass the concrete error type. For a
+    /// trait object such as `&*boxed_error` the type is `dyn Error` and every such error would be
+    /// distinct by message alone; use [`record_with_type_name`](Self::record_with_type_name) when
+    /// the concrete type is not known statically.
+    pub fn record<E: Error + ?Sized>(&self, error: &E) -> bool {
+        self.record_with_type_name(type_name::<E>(), error)
+    }
+
+    /// Record an observation of an error under an explicit `type_name`, returning false if there
+    /// is no space left in the log for a new distinct error.
+    pub fn record_with_type_name<E: Error + ?Sized>(&self, type_name: &str, error: &E) -> bool {
+        let timestamp_ms = self.clock.time();
+        let encoded_error = Self::encode(type_name, error);
+
+        let offset = {
+            let mut observations = self.observations.lock().unwrap();
+            match observations
+                .distinct
+                .iter()
+                .find(|observation| observation.encoded_error == encoded_error)
+            {
+                Some(observation) => observation.offset,
+                None => {
+                    match self.new_observation(&mut observations, timestamp_ms, encoded_error) {
+                        Some(offset) => offset,
+                        None => return false,
+                    }
+                }
+            }
+        };
+
+        self.buffer
+            .get_and_add_i32(offset + DistinctErrorLog::OBSERVATION_COUNT_OFFSET, 1);
+        self.buffer.put_i64_ordered(
+            offset + DistinctErrorLog::LAST_OBSERVATION_TIMESTAMP_OFFSET,
+            timestamp_ms,
+        );
+
+        true
+    }
+
+    fn new_observation(
+        &self,
+        observations: &mut Observations,
+        timestamp_ms: i64,
+        encoded_error: String,
+    ) -> Option<Index> {
+        let offset = observations.next_offset;
+        let length = DistinctErrorLog::ENCODED_ERROR_OFFSET as i64 + encoded_error.len() as i64;
+        if offset as i64 + length > self.buffer.capacity() as i64 {
+            return None;
+        }
+        let length = length as Index;
+
+        self.buffer.put_slice(
+            offset + DistinctErrorLog::ENCODED_ERROR_OFFSET,
+            encoded_error.as_bytes(),
+        );
+        self.buffer.put_i64(
+            offset + DistinctErrorLog::FIRST_OBSERVATION_TIMESTAMP_OFFSET,
+            timestamp_ms,
+        );
+        observations.next_offset =
+            bit_util::align(offset + length, DistinctErrorLog::RECORD_ALIGNMENT);
+        observations.distinct.push(DistinctObservation {
+            encoded_error,
+            offset,
+        });
+
+        self.buffer
+            .put_i32_ordered(offset + DistinctErrorLog::LENGTH_OFFSET, length);
+
+        Some(offset)
+    }
+
+    fn encode<E: Error + ?Sized>(type_name: &str, error: &E) -> String {
+        let mut encoded_error = format!("{}: {}", type_name, error);
+
+        let mut source = error.source();
+        while let Some(cause) = source {
+            encoded_error.push_str("\nCaused by: ");
+            encoded_error.push_str(&cause.to_string());
+            source = cause.source();
+        }
+
+        encoded_error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::{CachedEpochClock, ErrorLogReader};
+    use crate::mem::AlignedBox;
+    use std::fmt;
+
+    const BUFFER_SZ: usize = 1024;
+
+    #[derive(Debug)]
+    struct TestError {
+        message: String,
+        source: Option<Box<TestError>>,
+    }
+
+    impl TestError {
+        fn new(message: &str) -> TestError {
+            TestError {
+                message: message.to_owned(),
+                source: None,
+            }
+        }
+    }
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(&self.message)
+        }
+    }
+
+    impl Error for TestError {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            self.source.as_deref().map(|e| e as &(dyn Error + 'static))
+        }
+    }
+
+    struct DistinctErrorLogTest {
+        _buffer: AlignedBox<[u8]>,
+        buffer: AtomicBuffer,
+        clock: CachedEpochClock,
+    }
+
+    impl Default for DistinctErrorLogTest {
+        fn default() -> Self {
+            let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let atomic_buffer = AtomicBuffer::wrap(&mut buffer);
+            DistinctErrorLogTest {
+                _buffer: buffer,
+                buffer: atomic_buffer,
+                clock: CachedEpochClock::new(7),
+            }
+        }
+    }
+
+    impl DistinctErrorLogTest {
+        fn log(&self) -> DistinctErrorLog<&CachedEpochClock> {
+            DistinctErrorLog::new(self.buffer, &self.clock)
+        }
+    }
+
+    #[test]
+    fn should_record_first_observation() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+
+        assert!(log.record(&TestError::new("first")));
+
+        let expected = format!("{}: first", type_name::<TestError>());
+        let length = context
+            .buffer
+            .get_i32_volatile(DistinctErrorLog::LENGTH_OFFSET);
+        assert_eq!(
+            length,
+            DistinctErrorLog::ENCODED_ERROR_OFFSET + expected.len() as Index
+        );
+        assert_eq!(
+            context
+                .buffer
+                .get_i32(DistinctErrorLog::OBSERVATION_COUNT_OFFSET),
+            1
+        );
+        assert_eq!(
+            context
+                .buffer
+                .get_i64(DistinctErrorLog::FIRST_OBSERVATION_TIMESTAMP_OFFSET),
+            7
+        );
+        assert_eq!(
+            context
+                .buffer
+                .get_i64(DistinctErrorLog::LAST_OBSERVATION_TIMESTAMP_OFFSET),
+            7
+        );
+
+        let mut encoded_error = vec![0_u8; expected.len()];
+        context
+            .buffer
+            .get_bytes(DistinctErrorLog::ENCODED_ERROR_OFFSET, &mut encoded_error);
+        assert_eq!(encoded_error, expected.as_bytes());
+    }
+
+    #[test]
+    fn should_count_repeated_observations_of_the_same_error() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+
+        assert!(log.record(&TestError::new("repeated")));
+        context.clock.update(12);
+        assert!(log.record(&TestError::new("repeated")));
+
+        let mut observations = vec![];
+        let entries = ErrorLogReader::read(&context.buffer, |count, first, last, _| {
+            observations.push((count, first, last))
+        });
+
+        assert_eq!(entries, 1);
+        assert_eq!(observations, vec![(2, 7, 12)]);
+    }
+
+    #[test]
+    fn should_record_errors_with_different_messages_separately() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+
+        assert!(log.record(&TestError::new("one")));
+        assert!(log.record(&TestError::new("two")));
+        assert!(log.record(&TestError::new("one")));
+
+        let mut errors = vec![];
+        ErrorLogReader::read(&context.buffer, |count, _, _, encoded_error| {
+            errors.push((count, encoded_error.to_owned()))
+        });
+
+        assert_eq!(
+            errors,
+            vec![
+                (2, format!("{}: one", type_name::<TestError>())),
+                (1, format!("{}: two", type_name::<TestError>())),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_record_boxed_errors_with_explicit_type_names_separately() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+        let first: Box<dyn Error> = Box::new(TestError::new("same"));
+        let second: Box<dyn Error> = Box::new(TestError::new("same"));
+
+        assert!(log.record_with_type_name("FirstError", &*first));
+        assert!(log.record_with_type_name("SecondError", &*second));
+        assert!(log.record_with_type_name("FirstError", &*first));
+
+        let mut errors = vec![];
+        ErrorLogReader::read(&context.buffer, |count, _, _, encoded_error| {
+            errors.push((count, encoded_error.to_owned()))
+        });
+
+        assert_eq!(
+            errors,
+            vec![
+                (2, "FirstError: same".to_owned()),
+                (1, "SecondError: same".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_encode_error_sources() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+        let error = TestError {
+            message: "outer".to_owned(),
+            source: Some(Box::new(TestError {
+                message: "middle".to_owned(),
+                source: Some(Box::new(TestError::new("inner"))),
+            })),
+        };
+
+        assert!(log.record(&error));
+
+        let mut errors = vec![];
+        ErrorLogReader::read(&context.buffer, |_, _, _, encoded_error| {
+            errors.push(encoded_error.to_owned())
+        });
+
+        assert_eq!(
+            errors,
+            vec![format!(
+                "{}: outer\nCaused by: middle\nCaused by: inner",
+                type_name::<TestError>()
+            )]
+        );
+    }
+
+    #[test]
+    fn should_fail_to_record_when_insufficient_space() {
+        let context = DistinctErrorLogTest::default();
+        let log = context.log();
+        assert!(!log.record(&TestError::new(&"x".repeat(BUFFER_SZ))));
+        assert!(!ErrorLogReader::has_errors(&context.buffer));
+
+        assert!(log.record(&TestError::new("fits")));
+        assert!(ErrorLogReader::has_errors(&context.buffer));
+    }
+}
@@ -0,0 +1,222 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::concurrent::errors::DistinctErrorLog;
+use crate::concurrent::AtomicBuffer;
+use crate::util::{bit_util, Index};
+
+/// Reader for a [`DistinctErrorLog`], or a log written by Agrona's `DistinctErrorLog`.
+pub struct ErrorLogReader;
+
+impl ErrorLogReader {
+    /// Has the log recorded any errors.
+    pub fn has_errors(buffer: &AtomicBuffer) -> bool {
+        buffer.capacity() >= DistinctErrorLog::ENCODED_ERROR_OFFSET
+            && buffer.get_i32_volatile(DistinctErrorLog::LENGTH_OFFSET) != 0
+    }
+
+    /// Read all distinct errors in the log, returning the number read.
+    ///
+    /// The consumer is called with the observation count, first and last observation timestamps
+    /// and the encoded error.
+    pub fn read<F>(buffer: &AtomicBuffer, consumer: F) -> i32
+    where
+        F: FnMut(i32, i64, i64, &str),
+    {
+        Self::read_since(buffer, consumer, 0)
+    }
+
+    /// Read the distinct errors in the log last observed at or after `since_timestamp`, returning
+    /// the number read. Reading stops at a record whose length does not fit within the log.
+    pub fn read_since<F>(buffer: &AtomicBuffer, mut consumer: F, since_timestamp: i64) -> i32
+    where
+        F: FnMut(i32, i64, i64, &str),
+    {
+        let mut entries = 0;
+        let mut offset: Index = 0;
+        let capacity = buffer.capacity();
+
+        while offset <= capacity - DistinctErrorLog::ENCODED_ERROR_OFFSET {
+            let length = buffer.get_i32_volatile(offset + DistinctErrorLog::LENGTH_OFFSET);
+            // zero at the end of the log, anything else outside the record bounds is corrupt
+            if length < DistinctErrorLog::ENCODED_ERROR_OFFSET || length > capacity - offset {
+                break;
+            }
+
+            let last_observation_timestamp = buffer
+                .get_i64_volatile(offset + DistinctErrorLog::LAST_OBSERVATION_TIMESTAMP_OFFSET);
+
+            if last_observation_timestamp >= since_timestamp {
+                entries += 1;
+
+                let mut encoded_error =
+                    vec![0u8; (length - DistinctErrorLog::ENCODED_ERROR_OFFSET) as usize];
+                buffer.get_bytes(
+                    offset + DistinctErrorLog::ENCODED_ERROR_OFFSET,
+                    &mut encoded_error,
+                );
+
+                consumer(
+                    buffer.get_i32(offset + DistinctErrorLog::OBSERVATION_COUNT_OFFSET),
+                    buffer.get_i64(offset + DistinctErrorLog::FIRST_OBSERVATION_TIMESTAMP_OFFSET),
+                    last_observation_timestamp,
+                    &String::from_utf8_lossy(&encoded_error),
+                );
+            }
+
+            offset += bit_util::align(length, DistinctErrorLog::RECORD_ALIGNMENT);
+        }
+
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::AlignedBox;
+
+    const BUFFER_SZ: usize = 1024;
+
+    struct ErrorLogReaderTest {
+        _buffer: AlignedBox<[u8]>,
+        buffer: AtomicBuffer,
+    }
+
+    impl Default for ErrorLogReaderTest {
+        fn default() -> Self {
+            let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let atomic_buffer = AtomicBuffer::wrap(&mut buffer);
+            ErrorLogReaderTest {
+                _buffer: buffer,
+                buffer: atomic_buffer,
+            }
+        }
+    }
+
+    impl ErrorLogReaderTest {
+        /// Write a record the way Agrona's `DistinctErrorLog` does, returning the next offset.
+        fn write_record(
+            &self,
+            offset: Index,
+            count: i32,
+            first: i64,
+            last: i64,
+            encoded_error: &str,
+        ) -> Index {
+            let length = DistinctErrorLog::ENCODED_ERROR_OFFSET + encoded_error.len() as Index;
+            self.buffer.put_slice(
+                offset + DistinctErrorLog::ENCODED_ERROR_OFFSET,
+                encoded_error.as_bytes(),
+            );
+            self.buffer.put_i64(
+                offset + DistinctErrorLog::FIRST_OBSERVATION_TIMESTAMP_OFFSET,
+                first,
+            );
+            self.buffer
+                .put_i32(offset + DistinctErrorLog::OBSERVATION_COUNT_OFFSET, count);
+            self.buffer.put_i64(
+                offset + DistinctErrorLog::LAST_OBSERVATION_TIMESTAMP_OFFSET,
+                last,
+            );
+            self.buffer
+                .put_i32_ordered(offset + DistinctErrorLog::LENGTH_OFFSET, length);
+
+            bit_util::align(offset + length, DistinctErrorLog::RECORD_ALIGNMENT)
+        }
+    }
+
+    #[test]
+    fn should_read_no_errors_from_empty_log() {
+        let context = ErrorLogReaderTest::default();
+
+        assert!(!ErrorLogReader::has_errors(&context.buffer));
+        assert_eq!(
+            ErrorLogReader::read(&context.buffer, |_, _, _, _| panic!("no errors")),
+            0
+        );
+    }
+
+    #[test]
+    fn should_read_log_written_by_agrona() {
+        let context = ErrorLogReaderTest::default();
+        let first_error = "java.lang.IllegalStateException: no tail\n\tat Test.main(Test.java:7)\n";
+        let second_error = "java.io.IOException: d\u{e9}j\u{e0} vu\n";
+
+        let offset = context.write_record(0, 3, 100, 300, first_error);
+        assert_eq!(offset % DistinctErrorLog::RECORD_ALIGNMENT, 0);
+        context.write_record(offset, 1, 200, 200, second_error);
+
+        let mut errors = vec![];
+        let entries = ErrorLogReader::read(&context.buffer, |count, first, last, encoded_error| {
+            errors.push((count, first, last, encoded_error.to_owned()))
+        });
+
+        assert!(ErrorLogReader::has_errors(&context.buffer));
+        assert_eq!(entries, 2);
+        assert_eq!(
+            errors,
+            vec![
+                (3, 100, 300, first_error.to_owned()),
+                (1, 200, 200, second_error.to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_only_read_errors_observed_since_timestamp() {
+        let context = ErrorLogReaderTest::default();
+        let offset = context.write_record(0, 1, 100, 100, "old");
+        context.write_record(offset, 2, 150, 250, "recent");
+
+        let mut errors = vec![];
+        let entries = ErrorLogReader::read_since(
+            &context.buffer,
+            |_, _, _, encoded_error| errors.push(encoded_error.to_owned()),
+            200,
+        );
+
+        assert_eq!(entries, 1);
+        assert_eq!(errors, vec!["recent".to_owned()]);
+    }
+
+    #[test]
+    fn should_stop_at_record_with_invalid_length() {
+        let context = ErrorLogReaderTest::default();
+        let offset = context.write_record(0, 1, 100, 100, "valid");
+
+        for length in [
+            DistinctErrorLog::ENCODED_ERROR_OFFSET - 1,
+            -1,
+            BUFFER_SZ as Index - offset + 1,
+        ] {
+            context
+                .buffer
+                .put_i32_ordered(offset + DistinctErrorLog::LENGTH_OFFSET, length);
+
+            let mut errors = vec![];
+            let entries = ErrorLogReader::read(&context.buffer, |_, _, _, encoded_error| {
+                errors.push(encoded_error.to_owned())
+            });
+
+            assert_eq!(entries, 1);
+            assert_eq!(errors, vec!["valid".to_owned()]);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Distinct error logging to shared memory, laid out as Agrona's `org.agrona.concurrent.errors` so
+//! Java and Rust processes can write and read the same log.
+
+pub use self::distinct_error_log::DistinctErrorLog;
+pub use self::error_log_reader::ErrorLogReader;
+
+pub mod distinct_error_log;
+pub mod error_log_reader;
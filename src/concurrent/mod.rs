@@ -17,11 +17,27 @@
  * limitations under the License.
  */
 
+use crate::buffer::{
+    AtomicDirectBuffer, DirectBuffer, MutableDirectBuffer, Primitive, ReadOnlyBuffer,
+};
 use crate::util::Index;
+use std::io;
 use std::mem::size_of;
 
+pub use crate::buffer::ByteOrder;
+
+pub use self::clock::{
+    CachedEpochClock, CachedNanoClock, EpochClock, NanoClock, OffsetEpochNanoClock, SystemEpochClock,
+    SystemNanoClock,
+};
+pub use self::errors::{DistinctErrorLog, ErrorLogReader};
+pub use self::snowflake_id_generator::SnowflakeIdGenerator;
+
 pub mod atomic;
+pub mod clock;
+pub mod errors;
 pub mod ring_buffer;
+pub mod snowflake_id_generator;
 
 pub trait IdleStrategy {
     fn idle_work(&self, work_count: i8);
@@ -85,6 +101,25 @@ impl IdleStrategy for BusySpinIdleStrategy {
     fn reset(&self) {}
 }
 
+pub struct YieldingIdleStrategy;
+
+impl IdleStrategy for YieldingIdleStrategy {
+    #[inline]
+    fn idle_work(&self, work_count: i8) {
+        if work_count <= 0 {
+            std::thread::yield_now();
+        }
+    }
+
+    #[inline]
+    fn idle(&self) {
+        std::thread::yield_now();
+    }
+
+    #[inline]
+    fn reset(&self) {}
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct AtomicBuffer {
     buffer: *mut u8,
@@ -96,15 +131,182 @@ impl AtomicBuffer {
         self.length as Index
     }
 
+    /// Alignment of the start of a wrapped buffer needed for atomic operations on `i64` values.
+    pub const ALIGNMENT: usize = size_of::<i64>();
+
+    /// Wrap `buffer`, which must be aligned to [`ALIGNMENT`](AtomicBuffer::ALIGNMENT) for atomic
+    /// operations. This is asserted in debug builds, use [`try_wrap`](AtomicBuffer::try_wrap)
+    /// where the alignment must be checked.
     pub fn wrap(buffer: &mut [u8]) -> AtomicBuffer {
-        AtomicBuffer { buffer: buffer.as_mut_ptr(), length: buffer.len() as u32 }
+        let atomic_buffer = AtomicBuffer { buffer: buffer.as_mut_ptr(), length: buffer.len() as u32 };
+        atomic_buffer.debug_verify_alignment();
+        atomic_buffer
+    }
+
+    /// Wrap `buffer` as for [`wrap`](AtomicBuffer::wrap), returning an error rather than panicking
+    /// if it is not aligned, e.g. for a `Vec<u8>` or array whose alignment is not known.
+    pub fn try_wrap(buffer: &mut [u8]) -> io::Result<AtomicBuffer> {
+        let atomic_buffer = AtomicBuffer { buffer: buffer.as_mut_ptr(), length: buffer.len() as u32 };
+        if !atomic_buffer.is_aligned() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                atomic_buffer.misaligned_message(),
+            ));
+        }
+
+        Ok(atomic_buffer)
+    }
+
+    /// Wrap `length` bytes of `buffer` starting at `offset`, which must be aligned as for
+    /// [`wrap`](AtomicBuffer::wrap).
+    pub fn wrap_adjusted(buffer: &mut [u8], offset: Index, length: Index) -> AtomicBuffer {
+        let atomic_buffer = AtomicBuffer { buffer: buffer.as_mut_ptr(), length: buffer.len() as u32 }
+            .view(offset, length);
+        atomic_buffer.debug_verify_alignment();
+        atomic_buffer
+    }
+
+    /// Panic if the start of the buffer is not aligned to [`ALIGNMENT`](AtomicBuffer::ALIGNMENT).
+    pub fn verify_alignment(&self) {
+        if !self.is_aligned() {
+            panic!("{}", self.misaligned_message());
+        }
     }
 
-    pub fn get<T: Copy>(&self, index: Index) -> T {
+    #[inline]
+    fn debug_verify_alignment(&self) {
+        debug_assert!(self.is_aligned(), "{}", self.misaligned_message());
+    }
+
+    #[inline]
+    fn is_aligned(&self) -> bool {
+        self.length == 0 || self.buffer as usize % Self::ALIGNMENT == 0
+    }
+
+    fn misaligned_message(&self) -> String {
+        format!(
+            "AtomicBuffer is not correctly aligned: addressOffset={} is not divisible by {}",
+            self.buffer as usize,
+            Self::ALIGNMENT
+        )
+    }
+
+    /// A buffer over `length` bytes starting at `offset`, with indexes and bounds checks relative
+    /// to the view so it can be handed out without exposing the rest of this buffer. The view can
+    /// start at any offset, so atomic operations on it need indexes aligned relative to this
+    /// buffer.
+    pub fn view(&self, offset: Index, length: Index) -> AtomicBuffer {
+        if offset < 0 || length < 0 || offset as i64 + length as i64 > self.length as i64 {
+            panic!(
+                "view out of bounds: offset={} length={} capacity={}",
+                offset, length, self.length
+            );
+        }
+
+        AtomicBuffer {
+            buffer: unsafe { self.buffer.offset(offset as isize) },
+            length: length as u32,
+        }
+    }
+
+    /// Address of the start of the buffer, e.g. to pass a message on to foreign code.
+    #[inline]
+    pub fn as_ptr(&self) -> *const u8 {
+        self.buffer
+    }
+
+    /// Borrow this buffer as read-only.
+    #[inline]
+    pub fn as_read_only(&self) -> &ReadOnlyBuffer {
+        ReadOnlyBuffer::from_ref(self)
+    }
+
+    pub fn get<T: Primitive>(&self, index: Index) -> T {
         self.bounds_check(index, size_of::<T>());
         unsafe { *(self.buffer.offset(index as isize) as *const T) }
     }
 
+    /// Read a `T` at `index` without a bounds check, which is still asserted in debug builds.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be non-negative and `index + size_of::<T>()` must not exceed the capacity.
+    #[inline]
+    pub unsafe fn get_unchecked<T: Primitive>(&self, index: Index) -> T {
+        self.debug_bounds_check(index, size_of::<T>());
+        *(self.buffer.offset(index as isize) as *const T)
+    }
+
+    /// Write `value` at `index` without a bounds check, which is still asserted in debug builds.
+    ///
+    /// # Safety
+    ///
+    /// `index` must be non-negative and `index + size_of::<T>()` must not exceed the capacity.
+    #[inline]
+    pub unsafe fn put_unchecked<T: Primitive>(&self, index: Index, value: T) {
+        self.debug_bounds_check(index, size_of::<T>());
+        std::ptr::write(self.buffer.offset(index as isize) as *mut T, value);
+    }
+
+    /// Volatile read of a `T` at `index` without a bounds check, as for
+    /// [`get_unchecked`](AtomicBuffer::get_unchecked).
+    ///
+    /// # Safety
+    ///
+    /// As for [`get_unchecked`](AtomicBuffer::get_unchecked), and `index` must be aligned to the
+    /// size of `T`.
+    #[inline]
+    pub unsafe fn get_volatile_unchecked<T: Primitive>(&self, index: Index) -> T {
+        self.debug_bounds_check(index, size_of::<T>());
+        atomic::get_volatile(self.buffer.offset(index as isize) as *const T)
+    }
+
+    /// Ordered write of `value` at `index` without a bounds check, as for
+    /// [`put_unchecked`](AtomicBuffer::put_unchecked).
+    ///
+    /// # Safety
+    ///
+    /// As for [`put_unchecked`](AtomicBuffer::put_unchecked), and `index` must be aligned to the
+    /// size of `T`.
+    #[inline]
+    pub unsafe fn put_ordered_unchecked<T: Primitive>(&self, index: Index, value: T) {
+        self.debug_bounds_check(index, size_of::<T>());
+        atomic::put_ordered(self.buffer.offset(index as isize) as *mut T, value);
+    }
+
+    /// Read a `T` at `index` on a ring buffer hot path, where indexes are in bounds by
+    /// construction. Bounds checked unless the `unchecked` feature is enabled, which leaves only
+    /// the debug assertion of [`get_unchecked`](AtomicBuffer::get_unchecked).
+    #[inline]
+    fn get_hot<T: Primitive>(&self, index: Index) -> T {
+        self.hot_bounds_check(index, size_of::<T>());
+        unsafe { self.get_unchecked(index) }
+    }
+
+    /// Write `value` at `index` on a ring buffer hot path, checked as for
+    /// [`get_hot`](AtomicBuffer::get_hot).
+    #[inline]
+    fn put_hot<T: Primitive>(&self, index: Index, value: T) {
+        self.hot_bounds_check(index, size_of::<T>());
+        unsafe { self.put_unchecked(index, value) }
+    }
+
+    /// Volatile read of a `T` at `index` on a ring buffer hot path, checked as for
+    /// [`get_hot`](AtomicBuffer::get_hot).
+    #[inline]
+    fn get_volatile_hot<T: Primitive>(&self, index: Index) -> T {
+        self.hot_bounds_check(index, size_of::<T>());
+        unsafe { self.get_volatile_unchecked(index) }
+    }
+
+    /// Ordered write of `value` at `index` on a ring buffer hot path, checked as for
+    /// [`get_hot`](AtomicBuffer::get_hot).
+    #[inline]
+    fn put_ordered_hot<T: Primitive>(&self, index: Index, value: T) {
+        self.hot_bounds_check(index, size_of::<T>());
+        unsafe { self.put_ordered_unchecked(index, value) }
+    }
+
     pub fn get_i64(&self, index: Index) -> i64 {
         self.bounds_check(index, size_of::<i64>());
         unsafe { *(self.buffer.offset(index as isize) as *const i64) }
@@ -117,6 +319,32 @@ impl AtomicBuffer {
         }
     }
 
+    pub fn get_i32(&self, index: Index) -> i32 {
+        self.bounds_check(index, size_of::<i32>());
+        unsafe { *(self.buffer.offset(index as isize) as *const i32) }
+    }
+
+    pub fn get_and_add_i32(&self, index: Index, increment: i32) -> i32 {
+        self.bounds_check(index, size_of::<i32>());
+        unsafe {
+            atomic::get_and_add_i32(self.buffer.offset(index as isize) as *const i32, increment)
+        }
+    }
+
+    pub fn put_i32(&self, index: Index, value: i32) {
+        self.bounds_check(index, size_of::<i32>());
+        unsafe {
+            std::ptr::write(self.buffer.offset(index as isize) as *mut i32, value);
+        }
+    }
+
+    pub fn put_i32_ordered(&self, index: Index, value: i32) {
+        self.bounds_check(index, size_of::<i32>());
+        unsafe {
+            atomic::put_ordered(self.buffer.offset(index as isize) as *mut i32, value);
+        }
+    }
+
     pub fn put_i64(&self, index: Index, value: i64) {
         self.bounds_check(index, size_of::<i64>());
         unsafe {
@@ -124,14 +352,14 @@ impl AtomicBuffer {
         }
     }
 
-    pub fn put<T>(&self, index: Index, value: T) {
+    pub fn put<T: Primitive>(&self, index: Index, value: T) {
         self.bounds_check(index, size_of::<T>());
         unsafe {
             std::ptr::write(self.buffer.offset(index as isize) as *mut T, value);
         }
     }
 
-    pub fn put_ordered<T>(&self, index: Index, value: T) {
+    pub fn put_ordered<T: Primitive>(&self, index: Index, value: T) {
         self.bounds_check(index, size_of::<T>());
         unsafe {
             atomic::put_ordered(self.buffer.offset(index as isize) as *mut T, value);
@@ -155,19 +383,38 @@ impl AtomicBuffer {
         unsafe { atomic::get_volatile(self.buffer.offset(index as isize) as *const i32) }
     }
 
-    pub fn put_bytes(
+    pub fn put_bytes<B: DirectBuffer + ?Sized>(
         &self,
         index: Index,
-        src_buffer: &AtomicBuffer,
+        src_buffer: &B,
         src_index: Index,
         length: Index,
     ) {
         self.bounds_check(index, length as usize);
+        let dst = unsafe {
+            std::slice::from_raw_parts_mut(self.buffer.offset(index as isize), length as usize)
+        };
+        src_buffer.get_bytes(src_index, dst);
+    }
+
+    pub fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        self.bounds_check(index, dst.len());
         unsafe {
             std::ptr::copy_nonoverlapping(
-                src_buffer.buffer.offset(src_index as isize),
                 self.buffer.offset(index as isize),
-                length as usize,
+                dst.as_mut_ptr(),
+                dst.len(),
+            );
+        }
+    }
+
+    pub fn put_slice(&self, index: Index, src: &[u8]) {
+        self.bounds_check(index, src.len());
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                src.as_ptr(),
+                self.buffer.offset(index as isize),
+                src.len(),
             );
         }
     }
@@ -192,19 +439,289 @@ impl AtomicBuffer {
                 expected_value,
                 updated_value,
             );
-            return original == expected_value;
+            original == expected_value
         }
     }
 
-    #[cfg(disable_bounds_check)]
     #[inline]
-    fn bounds_check(&self, _index: Index, _length: u64) {}
+    fn bounds_check(&self, index: Index, length: usize) {
+        if !self.is_in_bounds(index, length) {
+            panic!(
+                "Index out of bounds: index={} length={} capacity={}",
+                index, length, self.length
+            )
+        }
+    }
 
-    #[cfg(not(disable_bounds_check))]
+    #[cfg(not(feature = "unchecked"))]
     #[inline]
-    fn bounds_check(&self, index: Index, length: usize) {
-        if index < 0 || (self.length as usize - index as usize) < length {
-            panic!("Index out of bounds")
+    fn hot_bounds_check(&self, index: Index, length: usize) {
+        self.bounds_check(index, length);
+    }
+
+    #[cfg(feature = "unchecked")]
+    #[inline]
+    fn hot_bounds_check(&self, _index: Index, _length: usize) {}
+
+    #[inline]
+    fn debug_bounds_check(&self, index: Index, length: usize) {
+        debug_assert!(
+            self.is_in_bounds(index, length),
+            "Index out of bounds: index={} length={} capacity={}",
+            index,
+            length,
+            self.length
+        );
+    }
+
+    #[inline]
+    fn is_in_bounds(&self, index: Index, length: usize) -> bool {
+        index >= 0
+            && index as usize <= self.length as usize
+            && self.length as usize - index as usize >= length
+    }
+}
+
+impl DirectBuffer for AtomicBuffer {
+    #[inline]
+    fn capacity(&self) -> Index {
+        AtomicBuffer::capacity(self)
+    }
+
+    #[inline]
+    fn get<T: Primitive>(&self, index: Index) -> T {
+        AtomicBuffer::get(self, index)
+    }
+
+    #[inline]
+    fn get_bytes(&self, index: Index, dst: &mut [u8]) {
+        AtomicBuffer::get_bytes(self, index, dst)
+    }
+
+    #[inline]
+    fn get_i32(&self, index: Index) -> i32 {
+        AtomicBuffer::get_i32(self, index)
+    }
+
+    #[inline]
+    fn get_i64(&self, index: Index) -> i64 {
+        AtomicBuffer::get_i64(self, index)
+    }
+}
+
+impl MutableDirectBuffer for AtomicBuffer {
+    #[inline]
+    fn put<T: Primitive>(&mut self, index: Index, value: T) {
+        AtomicBuffer::put(self, index, value)
+    }
+
+    #[inline]
+    fn put_slice(&mut self, index: Index, src: &[u8]) {
+        AtomicBuffer::put_slice(self, index, src)
+    }
+
+    #[inline]
+    fn set_memory(&mut self, index: Index, length: Index, value: u8) {
+        AtomicBuffer::set_memory(self, index, length, value)
+    }
+
+    #[inline]
+    fn put_i32(&mut self, index: Index, value: i32) {
+        AtomicBuffer::put_i32(self, index, value)
+    }
+
+    #[inline]
+    fn put_i64(&mut self, index: Index, value: i64) {
+        AtomicBuffer::put_i64(self, index, value)
+    }
+}
+
+impl AtomicDirectBuffer for AtomicBuffer {
+    #[inline]
+    fn get_i32_volatile(&self, index: Index) -> i32 {
+        AtomicBuffer::get_i32_volatile(self, index)
+    }
+
+    #[inline]
+    fn get_i64_volatile(&self, index: Index) -> i64 {
+        AtomicBuffer::get_i64_volatile(self, index)
+    }
+
+    #[inline]
+    fn put_i32_ordered(&self, index: Index, value: i32) {
+        AtomicBuffer::put_i32_ordered(self, index, value)
+    }
+
+    #[inline]
+    fn put_i64_ordered(&self, index: Index, value: i64) {
+        AtomicBuffer::put_i64_ordered(self, index, value)
+    }
+
+    #[inline]
+    fn get_and_add_i32(&self, index: Index, increment: i32) -> i32 {
+        AtomicBuffer::get_and_add_i32(self, index, increment)
+    }
+
+    #[inline]
+    fn get_and_add_i64(&self, index: Index, increment: i64) -> i64 {
+        AtomicBuffer::get_and_add_i64(self, index, increment)
+    }
+
+    #[inline]
+    fn compare_and_set_i64(&self, index: Index, expected_value: i64, updated_value: i64) -> bool {
+        AtomicBuffer::compare_and_set_i64(self, index, expected_value, updated_value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+
+    #[test]
+    fn should_index_view_relative_to_its_offset() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let view = buffer.view(16, 16);
+
+        view.put_i64(8, 42);
+
+        assert_eq!(view.capacity(), 16);
+        assert_eq!(view.get_i64(8), 42);
+        assert_eq!(buffer.get_i64(24), 42);
+        assert_eq!(view.view(8, 8).get_i64(0), 42);
+    }
+
+    #[test]
+    fn should_wrap_adjusted_slice() {
+        let mut bytes = Align64::new([0_u8; 32]);
+        bytes[8] = 7;
+
+        let buffer = AtomicBuffer::wrap_adjusted(&mut *bytes, 8, 8);
+
+        assert_eq!(buffer.capacity(), 8);
+        assert_eq!(buffer.get::<u8>(0), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn should_bounds_check_relative_to_view() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let view = AtomicBuffer::wrap(&mut *bytes).view(0, 16);
+
+        view.get_i64(12);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn should_bounds_check_index_past_capacity() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+
+        buffer.get::<u8>(100);
+    }
+
+    #[test]
+    fn should_get_and_put_unchecked() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+
+        unsafe {
+            buffer.put_unchecked::<i64>(56, 42);
+            assert_eq!(buffer.get_unchecked::<i64>(56), 42);
+        }
+        assert_eq!(buffer.get_i64(56), 42);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Index out of bounds")]
+    fn should_debug_assert_unchecked_index() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+
+        unsafe {
+            buffer.get_unchecked::<i64>(60);
         }
     }
+
+    #[test]
+    #[cfg(any(debug_assertions, not(feature = "unchecked")))]
+    #[should_panic(expected = "Index out of bounds")]
+    fn should_check_hot_path_index() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+
+        buffer.put_ordered_hot::<i64>(60, 7);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "AtomicBuffer is not correctly aligned")]
+    fn should_panic_when_wrapping_misaligned_buffer() {
+        let mut bytes = Align64::new([0_u8; 16]);
+
+        AtomicBuffer::wrap(&mut bytes[1..]);
+    }
+
+    #[test]
+    fn should_view_bytes_at_any_offset() {
+        let mut bytes = Align64::new([0_u8; 16]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        buffer.put_bytes(3, &[1_u8, 2, 3][..], 0, 3);
+
+        let view = buffer.view(3, 8);
+        let mut dst = [0_u8; 3];
+        view.get_bytes(0, &mut dst[..]);
+
+        assert_eq!(view.capacity(), 8);
+        assert_eq!(dst, [1, 2, 3]);
+    }
+
+    #[test]
+    fn should_return_error_when_trying_to_wrap_misaligned_buffer() {
+        let mut bytes = Align64::new([0_u8; 16]);
+
+        let error = AtomicBuffer::try_wrap(&mut bytes[1..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(error
+            .to_string()
+            .starts_with("AtomicBuffer is not correctly aligned"));
+
+        let buffer = AtomicBuffer::try_wrap(&mut bytes[8..]).unwrap();
+        assert_eq!(buffer.capacity(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "view out of bounds: offset=60 length=8 capacity=64")]
+    fn should_panic_when_view_exceeds_buffer() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        AtomicBuffer::wrap(&mut *bytes).view(60, 8);
+    }
+
+    #[test]
+    fn should_get_and_put_with_byte_order() {
+        let mut bytes = Align64::new([0_u8; 16]);
+        let mut buffer = AtomicBuffer::wrap(&mut *bytes);
+
+        buffer.put_i32_with_order(0, 0x0102_0304, ByteOrder::BigEndian);
+        buffer.put_i16_with_order(4, 0x0506, ByteOrder::LittleEndian);
+        buffer.put_i64_with_order(8, 0x0102_0304_0506_0708, ByteOrder::BigEndian);
+
+        assert_eq!(bytes[..6], [1, 2, 3, 4, 6, 5]);
+        assert_eq!(bytes[8..], [1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        assert_eq!(buffer.get_i32_with_order(0, ByteOrder::BigEndian), 0x0102_0304);
+        assert_eq!(buffer.get_i32_with_order(0, ByteOrder::LittleEndian), 0x0403_0201);
+        assert_eq!(buffer.get_i16_with_order(4, ByteOrder::LittleEndian), 0x0506);
+        assert_eq!(
+            buffer.get_i64_with_order(8, ByteOrder::BigEndian),
+            0x0102_0304_0506_0708
+        );
+        assert_eq!(
+            buffer.get_i64_with_order(8, ByteOrder::NATIVE),
+            buffer.get_i64(8)
+        );
+    }
 }
@@ -0,0 +1,401 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::*;
+use crate::concurrent::atomic;
+use crate::mem::AlignedBox;
+
+/// Follows the records of a ring buffer without consuming them, for observers such as
+/// diagnostics or replication running alongside the consumer.
+///
+/// Records stay intact between the consumer position and the producer position, so the cursor
+/// is valid as long as it is not behind the consumer. When the consumer has read past the
+/// cursor, which zeroes the records, the cursor has been lapped: it skips forward to the
+/// consumer position and increments [`lapped_count`](RingBufferCursor::lapped_count).
+///
+/// As with Agrona's `CopyBroadcastReceiver`, each message is copied out of the ring and only
+/// passed to the handler once the consumer position shows it was not consumed during the copy.
+/// A consumer using [`ZeroFill::Batched`] or [`ZeroFill::HeadersOnly`] publishes its position
+/// before zeroing, so handlers never see a record being zeroed or overwritten. A
+/// [`ZeroFill::Eager`] consumer, like Agrona's, only moves the head once it has zeroed the
+/// records, so a record zeroed while it is being copied can reach the handler with zeroed bytes.
+pub struct RingBufferCursor<'a, R: RingBuffer> {
+    ring_buffer: &'a R,
+    position: i64,
+    lapped_count: u64,
+    scratch: AlignedBox<[u8]>,
+}
+
+impl<'a, R: RingBuffer> RingBufferCursor<'a, R> {
+    /// A cursor starting at the current consumer position.
+    pub fn new(ring_buffer: &'a R) -> RingBufferCursor<'a, R> {
+        Self::with_position(ring_buffer, ring_buffer.consumer_position())
+    }
+
+    /// A cursor starting at `position`, which must be at the start of a record.
+    pub fn with_position(ring_buffer: &'a R, position: i64) -> RingBufferCursor<'a, R> {
+        if position < 0 || position % RecordDescriptor::ALIGNMENT as i64 != 0 {
+            panic!("position is not aligned to a record: position={}", position)
+        }
+
+        RingBufferCursor {
+            ring_buffer,
+            position,
+            lapped_count: 0,
+            scratch: AlignedBox::<[u8]>::zeroed(
+                ring_buffer.max_msg_length() as usize,
+                AtomicBuffer::ALIGNMENT,
+            ),
+        }
+    }
+
+    /// The position of the next record the cursor will pass to a handler.
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// The number of times the consumer has overtaken the cursor.
+    pub fn lapped_count(&self) -> u64 {
+        self.lapped_count
+    }
+
+    /// Pass the handler a copy of up to `message_count_limit` messages following the cursor,
+    /// without moving the consumer position or zeroing the records. Returns the number of
+    /// messages.
+    pub fn poll<F>(&mut self, mut handler: F, message_count_limit: u32) -> u32
+    where
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index),
+    {
+        let buffer = self.ring_buffer.buffer();
+        let mask = self.ring_buffer.capacity() as i64 - 1;
+        let tail = self.ring_buffer.producer_position();
+        let scratch = AtomicBuffer::wrap(&mut self.scratch);
+        let mut messages_read = 0;
+
+        self.catch_up();
+
+        while self.position < tail && messages_read < message_count_limit {
+            let record_index = (self.position & mask) as Index;
+            let header = buffer.get_i64_volatile(record_index);
+            let record_length = RecordDescriptor::record_length(header);
+
+            if record_length <= 0 || self.catch_up() {
+                break;
+            }
+
+            let msg_type_id = RecordDescriptor::message_type_id(header);
+            let length = record_length - RecordDescriptor::HEADER_LENGTH;
+            if RecordDescriptor::PADDING_MSG_TYPE_ID != msg_type_id {
+                buffer.get_bytes(
+                    RecordDescriptor::encoded_msg_offset(record_index),
+                    &mut self.scratch[..length as usize],
+                );
+                atomic::acquire();
+
+                if self.catch_up() {
+                    break;
+                }
+            }
+
+            self.position += bit_util::align(record_length, RecordDescriptor::ALIGNMENT) as i64;
+            if RecordDescriptor::PADDING_MSG_TYPE_ID == msg_type_id {
+                continue;
+            }
+
+            messages_read += 1;
+            handler(msg_type_id, &ReadOnlyBuffer::new(scratch.view(0, length)), 0, length);
+        }
+
+        messages_read
+    }
+
+    /// Skip forward to the consumer position if it is ahead of the cursor, returning whether the
+    /// cursor was lapped.
+    fn catch_up(&mut self) -> bool {
+        let head = self.ring_buffer.consumer_position();
+        if head > self.position {
+            self.position = head;
+            self.lapped_count += 1;
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+    use std::cell::Cell;
+
+    const CAPACITY: usize = 1024;
+    const MSG_TYPE_ID: i32 = 7;
+
+    struct RingBufferCursorTest {
+        _buffer: Box<Align64<[u8; CAPACITY + RingBufferDescriptor::TRAILER_LENGTH as usize]>>,
+        ring_buffer: OneToOneRingBuffer,
+    }
+
+    impl Default for RingBufferCursorTest {
+        fn default() -> Self {
+            let mut buffer = Box::new(Align64::new(
+                [0_u8; CAPACITY + RingBufferDescriptor::TRAILER_LENGTH as usize],
+            ));
+            let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut **buffer));
+
+            RingBufferCursorTest {
+                _buffer: buffer,
+                ring_buffer,
+            }
+        }
+    }
+
+    impl RingBufferCursorTest {
+        fn write(&self, value: i64) {
+            assert!(self
+                .ring_buffer
+                .write(MSG_TYPE_ID, &value.to_ne_bytes()[..], 0, 8));
+        }
+    }
+
+    fn values<R: RingBuffer>(cursor: &mut RingBufferCursor<R>) -> Vec<i64> {
+        let mut values = Vec::new();
+        cursor.poll(
+            |_, buffer, index, _| values.push(buffer.get_i64(index)),
+            u32::MAX,
+        );
+        values
+    }
+
+    #[test]
+    fn should_follow_records_without_consuming_them() {
+        let context = RingBufferCursorTest::default();
+        let mut cursor = RingBufferCursor::new(&context.ring_buffer);
+
+        context.write(1);
+        context.write(2);
+        assert_eq!(values(&mut cursor), vec![1, 2]);
+
+        context.write(3);
+        assert_eq!(values(&mut cursor), vec![3]);
+        assert_eq!(cursor.position(), context.ring_buffer.producer_position());
+        assert_eq!(context.ring_buffer.consumer_position(), 0);
+
+        let mut read = 0;
+        context.ring_buffer.read(|_, _, _, _| read += 1, u32::MAX);
+        assert_eq!(read, 3);
+        assert_eq!(cursor.lapped_count(), 0);
+    }
+
+    #[test]
+    fn should_peek_without_moving_consumer_position() {
+        let context = RingBufferCursorTest::default();
+        context.write(1);
+        context.write(2);
+
+        let mut peeked = Vec::new();
+        let count = context
+            .ring_buffer
+            .peek(|_, buffer, index, _| peeked.push(buffer.get_i64(index)), 1);
+
+        assert_eq!(count, 1);
+        assert_eq!(peeked, vec![1]);
+        assert_eq!(context.ring_buffer.consumer_position(), 0);
+        assert_eq!(context.ring_buffer.peek(|_, _, _, _| {}, u32::MAX), 2);
+    }
+
+    #[test]
+    fn should_follow_records_across_buffer_wrap() {
+        let context = RingBufferCursorTest::default();
+        let mut cursor = RingBufferCursor::new(&context.ring_buffer);
+
+        while context.ring_buffer.producer_position() < (CAPACITY - 16) as i64 {
+            context.write(0);
+            context.ring_buffer.read(|_, _, _, _| {}, u32::MAX);
+        }
+        cursor.poll(|_, _, _, _| {}, u32::MAX);
+        let lapped_count = cursor.lapped_count();
+
+        context.write(1);
+        context.write(2);
+        context.write(3);
+
+        assert_eq!(values(&mut cursor), vec![1, 2, 3]);
+        assert_eq!(cursor.lapped_count(), lapped_count);
+        assert!(cursor.position() > CAPACITY as i64);
+    }
+
+    #[test]
+    fn should_skip_to_consumer_position_when_lapped() {
+        let context = RingBufferCursorTest::default();
+        let mut cursor = RingBufferCursor::new(&context.ring_buffer);
+
+        context.write(1);
+        context.write(2);
+        context.ring_buffer.read(|_, _, _, _| {}, 1);
+        context.write(3);
+
+        assert_eq!(values(&mut cursor), vec![2, 3]);
+        assert_eq!(cursor.lapped_count(), 1);
+    }
+
+    #[test]
+    fn should_pass_copy_of_record_consumed_during_handler() {
+        let context = RingBufferCursorTest::default();
+        let mut cursor = RingBufferCursor::new(&context.ring_buffer);
+
+        context.write(1);
+        context.write(2);
+
+        let mut values = Vec::new();
+        let count = cursor.poll(
+            |_, buffer, index, _| {
+                context.ring_buffer.read(|_, _, _, _| {}, 1);
+                values.push(buffer.get_i64(index));
+            },
+            u32::MAX,
+        );
+
+        assert_eq!(count, 2);
+        assert_eq!(values, vec![1, 2]);
+        assert_eq!(cursor.lapped_count(), 0);
+        assert_eq!(context.ring_buffer.consumer_position(), cursor.position());
+    }
+
+    /// A ring buffer whose consumer reads a message straight after its position is checked
+    /// `laps_after` times, so it laps a cursor between checking a record and copying it.
+    struct LappingRingBuffer<'a> {
+        ring_buffer: &'a OneToOneRingBuffer,
+        laps_after: Cell<u32>,
+    }
+
+    impl RingBuffer for LappingRingBuffer<'_> {
+        fn capacity(&self) -> Index {
+            self.ring_buffer.capacity()
+        }
+
+        fn write<B: DirectBuffer + ?Sized>(
+            &self,
+            msg_type_id: i32,
+            src_buffer: &B,
+            src_index: Index,
+            length: Index,
+        ) -> bool {
+            self.ring_buffer
+                .write(msg_type_id, src_buffer, src_index, length)
+        }
+
+        fn write_batch(&self, messages: &[(i32, &[u8])]) -> bool {
+            self.ring_buffer.write_batch(messages)
+        }
+
+        fn try_claim(&self, msg_type_id: i32, length: Index) -> Index {
+            self.ring_buffer.try_claim(msg_type_id, length)
+        }
+
+        fn commit(&self, index: Index) {
+            self.ring_buffer.commit(index)
+        }
+
+        fn abort(&self, index: Index) {
+            self.ring_buffer.abort(index)
+        }
+
+        fn read<F>(&self, handler: F, message_count_limit: u32) -> u32
+        where
+            F: FnMut(i32, &ReadOnlyBuffer, Index, Index),
+        {
+            self.ring_buffer.read(handler, message_count_limit)
+        }
+
+        fn controlled_read<F>(&self, handler: F, message_count_limit: u32) -> u32
+        where
+            F: FnMut(i32, &ReadOnlyBuffer, Index, Index) -> ControlledAction,
+        {
+            self.ring_buffer.controlled_read(handler, message_count_limit)
+        }
+
+        fn buffer(&self) -> &AtomicBuffer {
+            self.ring_buffer.buffer()
+        }
+
+        fn max_msg_length(&self) -> Index {
+            self.ring_buffer.max_msg_length()
+        }
+
+        fn next_correlation_id(&self) -> i64 {
+            self.ring_buffer.next_correlation_id()
+        }
+
+        fn producer_position(&self) -> i64 {
+            self.ring_buffer.producer_position()
+        }
+
+        fn consumer_position(&self) -> i64 {
+            let position = self.ring_buffer.consumer_position();
+            let laps_after = self.laps_after.get();
+            self.laps_after.set(laps_after.wrapping_sub(1));
+            if laps_after == 1 {
+                self.ring_buffer.read(|_, _, _, _| {}, 1);
+            }
+            position
+        }
+
+        fn consumer_heartbeat_time(&self) -> i64 {
+            self.ring_buffer.consumer_heartbeat_time()
+        }
+
+        fn set_consumer_heartbeat_time(&self, time: i64) {
+            self.ring_buffer.set_consumer_heartbeat_time(time)
+        }
+
+        fn stats(&self) -> Option<RingBufferStats> {
+            self.ring_buffer.stats()
+        }
+
+        fn unblock(&self) -> bool {
+            self.ring_buffer.unblock()
+        }
+    }
+
+    #[test]
+    fn should_not_pass_record_consumed_while_it_is_copied() {
+        let context = RingBufferCursorTest::default();
+        let ring_buffer = LappingRingBuffer {
+            ring_buffer: &context.ring_buffer,
+            laps_after: Cell::new(0),
+        };
+        let mut cursor = RingBufferCursor::new(&ring_buffer);
+
+        context.write(1);
+        context.write(2);
+        // checked once on entering poll and again once the header of the first record is read
+        ring_buffer.laps_after.set(2);
+
+        assert_eq!(values(&mut cursor), vec![]);
+        assert_eq!(cursor.lapped_count(), 1);
+        assert_eq!(cursor.position(), context.ring_buffer.consumer_position());
+        assert_eq!(values(&mut cursor), vec![2]);
+    }
+
+    #[test]
+    #[should_panic(expected = "position is not aligned to a record: position=4")]
+    fn should_panic_for_misaligned_position() {
+        let context = RingBufferCursorTest::default();
+        RingBufferCursor::with_position(&context.ring_buffer, 4);
+    }
+}
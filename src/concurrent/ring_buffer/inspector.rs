@@ -0,0 +1,963 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::*;
+use std::{fmt, io};
+
+/// Snapshot of the counters held in the trailer of a ring buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RingBufferState {
+    pub capacity: Index,
+    pub head: i64,
+    pub head_cache: i64,
+    pub tail: i64,
+    /// Position the consumer has read up to, ahead of the head while a consumer using
+    /// [`ZeroFill::Batched`] holds back bytes it has read.
+    pub consumer_position: i64,
+    pub correlation_counter: i64,
+    pub consumer_heartbeat: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordKind {
+    /// A fully written message.
+    Message,
+    /// Padding inserted to skip to the start of the buffer on wrap.
+    Padding,
+    /// A message that has been claimed but not yet committed, the header holds a negative length.
+    InFlight,
+    /// Space has been claimed by a producer which has not yet written a header.
+    Unwritten,
+}
+
+/// Description of a record found while walking from head to tail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordInfo {
+    pub position: i64,
+    pub offset: Index,
+    pub length: Index,
+    pub msg_type_id: i32,
+    pub kind: RecordKind,
+}
+
+impl RecordInfo {
+    /// The length of the record rounded up to the record alignment, saturating for corrupt
+    /// headers whose length is close to `Index::MAX` or `Index::MIN`.
+    #[inline]
+    pub fn aligned_length(&self) -> Index {
+        let max_length = Index::MAX - (RecordDescriptor::ALIGNMENT - 1);
+        let length = self.length.unsigned_abs().min(max_length as u32) as Index;
+
+        bit_util::align(length, RecordDescriptor::ALIGNMENT)
+    }
+
+    #[inline]
+    pub fn encoded_msg_offset(&self) -> Index {
+        RecordDescriptor::encoded_msg_offset(self.offset)
+    }
+
+    #[inline]
+    pub fn encoded_msg_length(&self) -> Index {
+        self.length
+            .unsigned_abs()
+            .saturating_sub(RecordDescriptor::HEADER_LENGTH as u32) as Index
+    }
+}
+
+/// Problem found by [`RingBufferInspector::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Issue {
+    /// A trailer position is negative or not a multiple of the record alignment.
+    MisalignedPosition {
+        name: &'static str,
+        position: i64,
+    },
+    /// The head cache is ahead of the head, producers will overestimate the available capacity.
+    HeadCacheAheadOfHead {
+        head_cache: i64,
+        head: i64,
+    },
+    HeadAheadOfTail {
+        head: i64,
+        tail: i64,
+    },
+    /// More bytes are pending between head and tail than the buffer can hold.
+    PendingExceedsCapacity {
+        head: i64,
+        tail: i64,
+    },
+    /// A producer claimed space but has not written a header, the consumer is blocked at this record.
+    UnwrittenRecord {
+        position: i64,
+        offset: Index,
+    },
+    /// A producer wrote a header with a negative length but has not committed the record.
+    InFlightRecord {
+        position: i64,
+        offset: Index,
+        length: Index,
+    },
+    /// The record length is too short to hold a header.
+    InvalidLength {
+        position: i64,
+        offset: Index,
+        length: Index,
+    },
+    /// The record runs past the end of the buffer instead of being preceded by padding.
+    RecordCrossesBufferEnd {
+        position: i64,
+        offset: Index,
+        length: Index,
+    },
+    /// The record runs past the tail position.
+    RecordBeyondTail {
+        position: i64,
+        offset: Index,
+        length: Index,
+        tail: i64,
+    },
+    /// A padding record whose length is not a multiple of the record alignment.
+    InvalidPadding {
+        position: i64,
+        offset: Index,
+        length: Index,
+    },
+    InvalidMsgTypeId {
+        position: i64,
+        offset: Index,
+        msg_type_id: i32,
+    },
+    /// Space between tail and head which should have been zeroed by the consumer is not.
+    NonZeroFreeSpace {
+        offset: Index,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Issue::MisalignedPosition { name, position } => {
+                write!(
+                    f,
+                    "{} position {} is not aligned to {}",
+                    name,
+                    position,
+                    RecordDescriptor::ALIGNMENT
+                )
+            }
+            Issue::HeadCacheAheadOfHead { head_cache, head } => {
+                write!(f, "head cache {} is ahead of head {}", head_cache, head)
+            }
+            Issue::HeadAheadOfTail { head, tail } => {
+                write!(f, "head {} is ahead of tail {}", head, tail)
+            }
+            Issue::PendingExceedsCapacity { head, tail } => {
+                write!(
+                    f,
+                    "{} bytes pending between head {} and tail {} exceeds capacity",
+                    tail.saturating_sub(head),
+                    head,
+                    tail
+                )
+            }
+            Issue::UnwrittenRecord { position, offset } => write!(
+                f,
+                "record at position={} offset={} has been claimed but no header written",
+                position, offset
+            ),
+            Issue::InFlightRecord {
+                position,
+                offset,
+                length,
+            } => write!(
+                f,
+                "record at position={} offset={} is in flight with length={}",
+                position, offset, length
+            ),
+            Issue::InvalidLength {
+                position,
+                offset,
+                length,
+            } => write!(
+                f,
+                "record at position={} offset={} has invalid length={}",
+                position, offset, length
+            ),
+            Issue::RecordCrossesBufferEnd {
+                position,
+                offset,
+                length,
+            } => write!(
+                f,
+                "record at position={} offset={} length={} crosses the end of the buffer",
+                position, offset, length
+            ),
+            Issue::RecordBeyondTail {
+                position,
+                offset,
+                length,
+                tail,
+            } => write!(
+                f,
+                "record at position={} offset={} length={} runs past tail {}",
+                position, offset, length, tail
+            ),
+            Issue::InvalidPadding {
+                position,
+                offset,
+                length,
+            } => write!(
+                f,
+                "padding at position={} offset={} has unaligned length={}",
+                position, offset, length
+            ),
+            Issue::InvalidMsgTypeId {
+                position,
+                offset,
+                msg_type_id,
+            } => write!(
+                f,
+                "record at position={} offset={} has invalid msg_type_id={}",
+                position, offset, msg_type_id
+            ),
+            Issue::NonZeroFreeSpace { offset } => {
+                write!(f, "free space at offset={} has not been zeroed", offset)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub state: RingBufferState,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Change applied by [`RingBufferInspector::repair`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    HeadCacheReset {
+        from: i64,
+        to: i64,
+    },
+    /// The record at the consumer position was blocked and has been turned into padding.
+    Unblocked {
+        position: i64,
+    },
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Repair::HeadCacheReset { from, to } => {
+                write!(f, "reset head cache from {} to {}", from, to)
+            }
+            Repair::Unblocked { position } => {
+                write!(f, "unblocked record at position={}", position)
+            }
+        }
+    }
+}
+
+/// View over the records and trailer of a ring buffer, intended for diagnostics.
+///
+/// Apart from [`repair`](RingBufferInspector::repair) the inspector never writes to the buffer, so
+/// it is safe to point at a buffer that is in use by producers and a consumer. Records are read
+/// with volatile loads and may change while walking.
+pub struct RingBufferInspector {
+    buffer: AtomicBuffer,
+    capacity: Index,
+    zero_fill: ZeroFill,
+}
+
+impl RingBufferInspector {
+    /// Returns an error of kind `InvalidData` rather than panicking when the buffer does not hold
+    /// a ring buffer, since the inspector is pointed at files which may be truncated or corrupt.
+    pub fn new(buffer: AtomicBuffer) -> io::Result<RingBufferInspector> {
+        Self::with_zero_fill(buffer, ZeroFill::default())
+    }
+
+    /// An inspector for a ring buffer whose consumer uses `zero_fill`, which determines how much
+    /// of the free space [`validate`](RingBufferInspector::validate) expects to be zeroed.
+    pub fn with_zero_fill(
+        buffer: AtomicBuffer,
+        zero_fill: ZeroFill,
+    ) -> io::Result<RingBufferInspector> {
+        let capacity = buffer.capacity() - RingBufferDescriptor::TRAILER_LENGTH;
+
+        if !bit_util::is_power_of_two(capacity) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Capacity must be a positive power of 2 + TRAILER_LENGTH: capacity={}",
+                    capacity
+                ),
+            ));
+        }
+
+        Ok(RingBufferInspector {
+            buffer,
+            capacity,
+            zero_fill,
+        })
+    }
+
+    pub fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
+    pub fn capacity(&self) -> Index {
+        self.capacity
+    }
+
+    pub fn state(&self) -> RingBufferState {
+        let head = self.trailer_i64(RingBufferDescriptor::HEAD_POSITION_OFFSET);
+
+        RingBufferState {
+            capacity: self.capacity,
+            head,
+            head_cache: self.trailer_i64(RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET),
+            tail: self.trailer_i64(RingBufferDescriptor::TAIL_POSITION_OFFSET),
+            consumer_position: head
+                .max(self.trailer_i64(RingBufferDescriptor::CONSUMER_POSITION_OFFSET)),
+            correlation_counter: self.trailer_i64(RingBufferDescriptor::CORRELATION_COUNTER_OFFSET),
+            consumer_heartbeat: self.trailer_i64(RingBufferDescriptor::CONSUMER_HEARTBEAT_OFFSET),
+        }
+    }
+
+    /// Walk the records between the current head and tail positions.
+    ///
+    /// The walk stops at the tail, at a record whose length cannot be determined, or after a full
+    /// capacity worth of bytes so a corrupt trailer cannot cause it to spin.
+    pub fn records(&self) -> Records<'_> {
+        let state = self.state();
+
+        Records {
+            inspector: self,
+            position: state.head,
+            limit: state
+                .tail
+                .min(state.head.saturating_add(self.capacity as i64)),
+            done: false,
+        }
+    }
+
+    /// Check the trailer positions and the chain of records between head and tail.
+    ///
+    /// The invariants checked are `head_cache <= head <= tail`, `tail - head <= capacity`, aligned
+    /// positions, record lengths within bounds, aligned padding and zeroed free space. With
+    /// [`ZeroFill::HeadersOnly`] only the header following the tail is expected to be zeroed. A
+    /// buffer in active use may report transient in-flight or unwritten records.
+    ///
+    /// The head cache is expected to trail the head rather than lie between head and tail: as in
+    /// Agrona, producers only refresh it from the head when the cached value shows too little
+    /// capacity, so any value up to the head is valid while one ahead of it is not.
+    pub fn validate(&self) -> ValidationReport {
+        let state = self.state();
+        let mut issues = vec![];
+
+        self.validate_positions(&state, &mut issues);
+        if issues.is_empty() {
+            self.validate_records(&state, &mut issues);
+            self.validate_free_space(&state, &mut issues);
+        }
+
+        ValidationReport { state, issues }
+    }
+
+    /// Fix what can be fixed without the cooperation of producers or the consumer.
+    ///
+    /// A head cache that is ahead of the head is reset, and a record blocking the consumer is
+    /// turned into padding using the same logic as [`ManyToOneRingBuffer::unblock`]. Only the
+    /// record at the [`consumer_position`](RingBufferState::consumer_position) is unblocked, later
+    /// records are handled once the consumer has moved past it.
+    pub fn repair(&self) -> Vec<Repair> {
+        let mut repairs = vec![];
+        let state = self.state();
+
+        if state.head_cache > state.head {
+            self.buffer.put_i64_ordered(
+                self.capacity + RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET,
+                state.head,
+            );
+            repairs.push(Repair::HeadCacheReset {
+                from: state.head_cache,
+                to: state.head,
+            });
+        }
+
+        if ManyToOneRingBuffer::new(self.buffer).unblock() {
+            repairs.push(Repair::Unblocked {
+                position: state.consumer_position,
+            });
+        }
+
+        repairs
+    }
+
+    fn validate_positions(&self, state: &RingBufferState, issues: &mut Vec<Issue>) {
+        let alignment = RecordDescriptor::ALIGNMENT as i64;
+
+        for &(name, position) in &[
+            ("head", state.head),
+            ("head cache", state.head_cache),
+            ("tail", state.tail),
+        ] {
+            if position < 0 || position % alignment != 0 {
+                issues.push(Issue::MisalignedPosition { name, position });
+            }
+        }
+
+        if state.head_cache > state.head {
+            issues.push(Issue::HeadCacheAheadOfHead {
+                head_cache: state.head_cache,
+                head: state.head,
+            });
+        }
+
+        if state.head > state.tail {
+            issues.push(Issue::HeadAheadOfTail {
+                head: state.head,
+                tail: state.tail,
+            });
+        } else if state.tail.saturating_sub(state.head) > self.capacity as i64 {
+            issues.push(Issue::PendingExceedsCapacity {
+                head: state.head,
+                tail: state.tail,
+            });
+        }
+    }
+
+    fn validate_records(&self, state: &RingBufferState, issues: &mut Vec<Issue>) {
+        let mask = (self.capacity - 1) as i64;
+        let mut position = state.head;
+
+        while position < state.tail {
+            let offset = (position & mask) as Index;
+            let header = self.buffer.get_i64_volatile(offset);
+            let length = RecordDescriptor::record_length(header);
+            let msg_type_id = RecordDescriptor::message_type_id(header);
+
+            if 0 == length {
+                issues.push(Issue::UnwrittenRecord { position, offset });
+                break;
+            }
+
+            let abs_length = length.unsigned_abs();
+            if abs_length < RecordDescriptor::HEADER_LENGTH as u32 {
+                issues.push(Issue::InvalidLength {
+                    position,
+                    offset,
+                    length,
+                });
+                break;
+            }
+
+            if abs_length > (self.capacity - offset) as u32 {
+                issues.push(Issue::RecordCrossesBufferEnd {
+                    position,
+                    offset,
+                    length,
+                });
+                break;
+            }
+
+            let aligned_length = bit_util::align(abs_length as Index, RecordDescriptor::ALIGNMENT);
+            if position + aligned_length as i64 > state.tail {
+                issues.push(Issue::RecordBeyondTail {
+                    position,
+                    offset,
+                    length,
+                    tail: state.tail,
+                });
+                break;
+            }
+
+            if length < 0 {
+                issues.push(Issue::InFlightRecord {
+                    position,
+                    offset,
+                    length,
+                });
+            } else if RecordDescriptor::PADDING_MSG_TYPE_ID == msg_type_id {
+                if length != aligned_length {
+                    issues.push(Issue::InvalidPadding {
+                        position,
+                        offset,
+                        length,
+                    });
+                }
+            } else if msg_type_id < 1 {
+                issues.push(Issue::InvalidMsgTypeId {
+                    position,
+                    offset,
+                    msg_type_id,
+                });
+            }
+
+            position += aligned_length as i64;
+        }
+    }
+
+    fn validate_free_space(&self, state: &RingBufferState, issues: &mut Vec<Issue>) {
+        let mask = (self.capacity - 1) as i64;
+        let mut free = self.capacity as i64 - (state.tail - state.head);
+
+        // Only the header following the last record is zeroed when the consumer leaves message
+        // bytes behind.
+        if ZeroFill::HeadersOnly == self.zero_fill {
+            free = free.min(RecordDescriptor::HEADER_LENGTH as i64);
+        }
+
+        for i in (0..free).step_by(size_of::<i64>()) {
+            let offset = ((state.tail + i) & mask) as Index;
+            if 0 != self.buffer.get_i64_volatile(offset) {
+                issues.push(Issue::NonZeroFreeSpace { offset });
+                break;
+            }
+        }
+    }
+
+    #[inline]
+    fn trailer_i64(&self, offset: Index) -> i64 {
+        self.buffer.get_i64_volatile(self.capacity + offset)
+    }
+}
+
+pub struct Records<'a> {
+    inspector: &'a RingBufferInspector,
+    position: i64,
+    limit: i64,
+    done: bool,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = RecordInfo;
+
+    fn next(&mut self) -> Option<RecordInfo> {
+        if self.done || self.position >= self.limit {
+            return None;
+        }
+
+        let capacity = self.inspector.capacity;
+        let offset = (self.position & (capacity - 1) as i64) as Index;
+        let header = self.inspector.buffer.get_i64_volatile(offset);
+        let length = RecordDescriptor::record_length(header);
+        let msg_type_id = RecordDescriptor::message_type_id(header);
+
+        let kind = if length == 0 {
+            RecordKind::Unwritten
+        } else if length < 0 {
+            RecordKind::InFlight
+        } else if RecordDescriptor::PADDING_MSG_TYPE_ID == msg_type_id {
+            RecordKind::Padding
+        } else {
+            RecordKind::Message
+        };
+
+        let record = RecordInfo {
+            position: self.position,
+            offset,
+            length,
+            msg_type_id,
+            kind,
+        };
+
+        let aligned_length = record.aligned_length();
+        if RecordKind::Unwritten == kind || aligned_length > capacity - offset {
+            self.done = true;
+        } else {
+            self.position = self.position.saturating_add(aligned_length as i64);
+        }
+
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::AlignedBox;
+
+    const CAPACITY: i32 = 1024;
+    const BUFFER_SZ: usize = (CAPACITY + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    const MSG_TYPE_ID: i32 = 101;
+
+    struct RingBufferInspectorTest {
+        _buffer: AlignedBox<[u8]>,
+        _src_buffer: AlignedBox<[u8]>,
+        src_ab: AtomicBuffer,
+        ring_buffer: ManyToOneRingBuffer,
+        inspector: RingBufferInspector,
+    }
+
+    impl Default for RingBufferInspectorTest {
+        fn default() -> Self {
+            let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let mut src_buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let ab = AtomicBuffer::wrap(&mut buffer);
+            let src_ab = AtomicBuffer::wrap(&mut src_buffer);
+
+            RingBufferInspectorTest {
+                _buffer: buffer,
+                _src_buffer: src_buffer,
+                src_ab,
+                ring_buffer: ManyToOneRingBuffer::new(ab),
+                inspector: RingBufferInspector::new(ab).unwrap(),
+            }
+        }
+    }
+
+    #[test]
+    fn should_report_trailer_state() {
+        let context = RingBufferInspectorTest::default();
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        context.ring_buffer.next_correlation_id();
+
+        let state = context.inspector.state();
+        assert_eq!(state.capacity, CAPACITY);
+        assert_eq!(state.head, 0);
+        assert_eq!(state.tail, 16);
+        assert_eq!(state.correlation_counter, 1);
+    }
+
+    #[test]
+    fn should_walk_messages_between_head_and_tail() {
+        let context = RingBufferInspectorTest::default();
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID + 1, &context.src_ab, 0, 20));
+
+        let records: Vec<RecordInfo> = context.inspector.records().collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].offset, 0);
+        assert_eq!(records[0].length, 16);
+        assert_eq!(records[0].msg_type_id, MSG_TYPE_ID);
+        assert_eq!(records[0].kind, RecordKind::Message);
+        assert_eq!(records[1].position, 16);
+        assert_eq!(records[1].aligned_length(), 32);
+        assert_eq!(records[1].encoded_msg_length(), 20);
+        assert_eq!(records[1].msg_type_id, MSG_TYPE_ID + 1);
+    }
+
+    #[test]
+    fn should_report_in_flight_and_unwritten_records() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 48);
+
+        let records: Vec<RecordInfo> = context.inspector.records().collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, RecordKind::InFlight);
+        assert_eq!(records[0].aligned_length(), 16);
+        assert_eq!(records[1].kind, RecordKind::Unwritten);
+        assert_eq!(records[1].offset, 16);
+    }
+
+    #[test]
+    fn should_saturate_lengths_of_corrupt_header() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(i32::MIN, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 16);
+
+        let records: Vec<RecordInfo> = context.inspector.records().collect();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].kind, RecordKind::InFlight);
+        assert_eq!(records[0].aligned_length(), i32::MAX - 7);
+        assert_eq!(records[0].encoded_msg_length(), i32::MAX - 7);
+    }
+
+    #[test]
+    fn should_return_error_for_buffer_which_is_not_a_ring_buffer() {
+        let mut bytes = AlignedBox::<[u8]>::zeroed(1000, 64);
+
+        let error = RingBufferInspector::new(AtomicBuffer::wrap(&mut bytes))
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            "Capacity must be a positive power of 2 + TRAILER_LENGTH: capacity=232"
+        );
+    }
+
+    #[test]
+    fn should_report_padding_on_wrap() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+        let tail = (CAPACITY - RecordDescriptor::ALIGNMENT) as i64;
+
+        ab.put_i64(CAPACITY + RingBufferDescriptor::HEAD_POSITION_OFFSET, tail);
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, tail);
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+
+        let records: Vec<RecordInfo> = context.inspector.records().collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].kind, RecordKind::Padding);
+        assert_eq!(records[0].offset, CAPACITY - RecordDescriptor::ALIGNMENT);
+        assert_eq!(records[1].kind, RecordKind::Message);
+        assert_eq!(records[1].offset, 0);
+        assert_eq!(records[1].position, CAPACITY as i64);
+    }
+
+    #[test]
+    fn should_validate_buffer_in_use() {
+        let context = RingBufferInspectorTest::default();
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 20));
+        assert_eq!(context.ring_buffer.read(|_, _, _, _| {}, 1), 1);
+
+        let report = context.inspector.validate();
+
+        assert!(report.is_valid(), "unexpected issues {:?}", report.issues);
+        assert_eq!(report.state.head, 16);
+        assert_eq!(report.state.tail, 48);
+    }
+
+    #[test]
+    fn should_report_head_cache_ahead_of_head() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(
+            CAPACITY + RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET,
+            64,
+        );
+
+        let report = context.inspector.validate();
+
+        assert_eq!(
+            report.issues,
+            vec![Issue::HeadCacheAheadOfHead {
+                head_cache: 64,
+                head: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn should_report_blocked_record_and_stale_free_space() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 16);
+        ab.put_i64(512, 1);
+
+        let report = context.inspector.validate();
+
+        assert_eq!(
+            report.issues,
+            vec![
+                Issue::InFlightRecord {
+                    position: 0,
+                    offset: 0,
+                    length: -16
+                },
+                Issue::NonZeroFreeSpace { offset: 512 },
+            ]
+        );
+    }
+
+    #[test]
+    fn should_report_record_beyond_tail() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(64, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 32);
+
+        let report = context.inspector.validate();
+
+        assert_eq!(
+            report.issues,
+            vec![Issue::RecordBeyondTail {
+                position: 0,
+                offset: 0,
+                length: 64,
+                tail: 32
+            }]
+        );
+    }
+
+    #[test]
+    fn should_report_corrupt_header_with_minimum_length() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(i32::MIN, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 16);
+
+        let report = context.inspector.validate();
+
+        assert_eq!(
+            report.issues,
+            vec![Issue::RecordCrossesBufferEnd {
+                position: 0,
+                offset: 0,
+                length: i32::MIN
+            }]
+        );
+    }
+
+    #[test]
+    fn should_validate_buffer_with_batched_consumer() {
+        let context = RingBufferInspectorTest::default();
+        let consumer = ManyToOneRingBuffer::with_zero_fill(
+            *context.inspector.buffer(),
+            ZeroFill::Batched { chunk_length: 256 },
+        );
+
+        for _ in 0..3 {
+            assert!(context
+                .ring_buffer
+                .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        }
+        assert_eq!(consumer.read(|_, _, _, _| {}, 2), 2);
+
+        let report = context.inspector.validate();
+
+        assert!(report.is_valid(), "unexpected issues {:?}", report.issues);
+        assert_eq!(report.state.head, 0);
+        assert_eq!(report.state.consumer_position, 32);
+    }
+
+    #[test]
+    fn should_validate_free_space_of_headers_only_consumer() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+        let ring_buffer = OneToOneRingBuffer::with_zero_fill(ab, ZeroFill::HeadersOnly);
+        context.src_ab.put_i64(0, -1);
+
+        assert!(ring_buffer.write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        assert!(ring_buffer.write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 2);
+
+        assert_eq!(
+            context.inspector.validate().issues,
+            vec![Issue::NonZeroFreeSpace { offset: 8 }]
+        );
+
+        let inspector = RingBufferInspector::with_zero_fill(ab, ZeroFill::HeadersOnly).unwrap();
+        let report = inspector.validate();
+        assert!(report.is_valid(), "unexpected issues {:?}", report.issues);
+
+        ab.put_i64(32, 1);
+        assert_eq!(
+            inspector.validate().issues,
+            vec![Issue::NonZeroFreeSpace { offset: 32 }]
+        );
+    }
+
+    #[test]
+    fn should_repair_blocked_consumer() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 16);
+        ab.put_i64(
+            CAPACITY + RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET,
+            32,
+        );
+        ab.put_i64(0, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+
+        let repairs = context.inspector.repair();
+
+        assert_eq!(
+            repairs,
+            vec![
+                Repair::HeadCacheReset { from: 32, to: 0 },
+                Repair::Unblocked { position: 0 }
+            ]
+        );
+        assert!(context.inspector.validate().is_valid());
+        assert_eq!(context.ring_buffer.read(|_, _, _, _| {}, u32::MAX), 0);
+        assert_eq!(context.inspector.state().head, 16);
+    }
+
+    #[test]
+    fn should_accept_head_cache_trailing_head() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(16, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 16);
+        assert_eq!(context.ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+
+        let report = context.inspector.validate();
+
+        assert!(report.is_valid(), "unexpected issues {:?}", report.issues);
+        assert_eq!(report.state.head_cache, 0);
+        assert_eq!(report.state.head, 16);
+    }
+
+    #[test]
+    fn should_report_consumer_position_when_repairing_behind_batched_consumer() {
+        let context = RingBufferInspectorTest::default();
+        let ab = *context.inspector.buffer();
+
+        ab.put_i64(0, RecordDescriptor::make_header(16, MSG_TYPE_ID));
+        ab.put_i64(16, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+        ab.put_i64(CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET, 32);
+        ab.put_i64(
+            CAPACITY + RingBufferDescriptor::CONSUMER_POSITION_OFFSET,
+            16,
+        );
+
+        assert_eq!(
+            context.inspector.repair(),
+            vec![Repair::Unblocked { position: 16 }]
+        );
+        assert_eq!(
+            RecordDescriptor::message_type_id(ab.get_i64(16)),
+            RecordDescriptor::PADDING_MSG_TYPE_ID
+        );
+        assert_eq!(
+            ab.get_i64(0),
+            RecordDescriptor::make_header(16, MSG_TYPE_ID)
+        );
+    }
+}
@@ -0,0 +1,598 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Record and replay of ring buffer traffic.
+//!
+//! A [`Recorder`] taps the handler passed to [`RingBuffer::read`] and appends every message to a
+//! journal, and a [`Replayer`] writes a journal back into any [`RingBuffer`].
+//!
+//! The journal is a stream of little endian fields, without alignment padding:
+//!
+//! <pre>
+//!   0                   1                   2                   3
+//!   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//!  +---------------------------------------------------------------+
+//!  |                       Magic 'C' 'R' 'B' 'J'                   |
+//!  +---------------------------------------------------------------+
+//!  |                            Version                            |
+//!  +---------------------------------------------------------------+
+//!  |                          Header Length                        |
+//!  +---------------------------------------------------------------+
+//!  |                            Reserved                           |
+//!  +---------------------------------------------------------------+
+//!  |                  Entries up to the end of the stream         ...
+//! ...                                                              |
+//!  +---------------------------------------------------------------+
+//! </pre>
+//!
+//! Each entry is a 16 byte header followed by the encoded message:
+//!
+//! <pre>
+//!   0                   1                   2                   3
+//!   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//!  +---------------------------------------------------------------+
+//!  |                   Timestamp in Nanoseconds                    |
+//!  |                                                               |
+//!  +---------------------------------------------------------------+
+//!  |                          Msg Type Id                          |
+//!  +---------------------------------------------------------------+
+//!  |                            Length                             |
+//!  +---------------------------------------------------------------+
+//!  |                       Encoded Message                        ...
+//! ...                                                              |
+//!  +---------------------------------------------------------------+
+//! </pre>
+//!
+//! The timestamp is nanoseconds since the UNIX epoch at the time the message was read. Readers
+//! reject a journal with a different magic or a version they do not support, and skip any header
+//! bytes beyond those they know so that later versions can extend the header.
+
+use super::RingBuffer;
+use crate::buffer::{DirectBuffer, ReadOnlyBuffer};
+use crate::util::Index;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub struct JournalDescriptor;
+
+impl JournalDescriptor {
+    pub const MAGIC: [u8; 4] = *b"CRBJ";
+    pub const VERSION: i32 = 1;
+    pub const HEADER_LENGTH: i32 = 16;
+    pub const ENTRY_HEADER_LENGTH: usize = 16;
+}
+
+/// Message read back from a journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub timestamp_ns: i64,
+    pub msg_type_id: i32,
+    pub payload: Vec<u8>,
+}
+
+/// Appends messages to a journal.
+///
+/// When used through [`tap`](Recorder::tap) an IO error cannot be returned from the handler, so the
+/// first error is kept, further messages are not recorded, and the error is returned by
+/// [`flush`](Recorder::flush) or [`into_inner`](Recorder::into_inner).
+pub struct Recorder<W: Write> {
+    writer: W,
+    scratch: Vec<u8>,
+    entries_recorded: u64,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut writer: W) -> io::Result<Recorder<W>> {
+        writer.write_all(&JournalDescriptor::MAGIC)?;
+        writer.write_all(&JournalDescriptor::VERSION.to_le_bytes())?;
+        writer.write_all(&JournalDescriptor::HEADER_LENGTH.to_le_bytes())?;
+        writer.write_all(&0_i32.to_le_bytes())?;
+
+        Ok(Recorder {
+            writer,
+            scratch: Vec::new(),
+            entries_recorded: 0,
+            error: None,
+        })
+    }
+
+    pub fn record<B: DirectBuffer + ?Sized>(
+        &mut self,
+        timestamp_ns: i64,
+        msg_type_id: i32,
+        buffer: &B,
+        index: Index,
+        length: Index,
+    ) -> io::Result<()> {
+        self.scratch.clear();
+        self.scratch.extend_from_slice(&timestamp_ns.to_le_bytes());
+        self.scratch.extend_from_slice(&msg_type_id.to_le_bytes());
+        self.scratch.extend_from_slice(&length.to_le_bytes());
+
+        let payload_start = self.scratch.len();
+        self.scratch.resize(payload_start + length as usize, 0);
+        buffer.get_bytes(index, &mut self.scratch[payload_start..]);
+
+        self.writer.write_all(&self.scratch)?;
+        self.entries_recorded += 1;
+
+        Ok(())
+    }
+
+    /// Wrap a handler for [`RingBuffer::read`] so each message is recorded before being passed on.
+    pub fn tap<'r, F>(
+        &'r mut self,
+        mut handler: F,
+    ) -> impl FnMut(i32, &ReadOnlyBuffer, Index, Index) + 'r
+    where
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index) + 'r,
+    {
+        move |msg_type_id, buffer, index, length| {
+            if self.error.is_none() {
+                if let Err(e) = self.record(epoch_nanos(), msg_type_id, buffer, index, length) {
+                    self.error = Some(e);
+                }
+            }
+
+            handler(msg_type_id, buffer, index, length);
+        }
+    }
+
+    pub fn entries_recorded(&self) -> u64 {
+        self.entries_recorded
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        self.writer.flush()
+    }
+
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Rate at which a [`Replayer`] writes entries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pace {
+    /// Write entries as quickly as the ring buffer accepts them.
+    AsFastAsPossible,
+    /// Keep the original gaps between entries.
+    Original,
+    /// Keep the original gaps between entries divided by the given factor.
+    Accelerated(f64),
+}
+
+/// Reads a journal and writes its entries into a ring buffer.
+pub struct Replayer<R: Read> {
+    reader: R,
+    version: i32,
+}
+
+impl<R: Read> Replayer<R> {
+    pub fn new(mut reader: R) -> io::Result<Replayer<R>> {
+        let mut header = [0_u8; JournalDescriptor::HEADER_LENGTH as usize];
+        reader.read_exact(&mut header)?;
+
+        if header[0..4] != JournalDescriptor::MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a ring buffer journal",
+            ));
+        }
+
+        let version = i32_at(&header, 4);
+        if version != JournalDescriptor::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported journal version={}", version),
+            ));
+        }
+
+        let header_length = i32_at(&header, 8);
+        if header_length < JournalDescriptor::HEADER_LENGTH {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid journal header length={}", header_length),
+            ));
+        }
+        io::copy(
+            &mut (&mut reader).take((header_length - JournalDescriptor::HEADER_LENGTH) as u64),
+            &mut io::sink(),
+        )?;
+
+        Ok(Replayer { reader, version })
+    }
+
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    /// Read the next entry, returning `None` at the end of the journal.
+    pub fn next_entry(&mut self) -> io::Result<Option<JournalEntry>> {
+        let mut header = [0_u8; JournalDescriptor::ENTRY_HEADER_LENGTH];
+
+        match self.reader.read_exact(&mut header[0..1]) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        self.reader.read_exact(&mut header[1..])?;
+
+        let mut timestamp = [0_u8; 8];
+        timestamp.copy_from_slice(&header[0..8]);
+        let length = i32_at(&header, 12);
+        if length < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid journal entry length={}", length),
+            ));
+        }
+
+        // read rather than allocate up front, so a corrupt length cannot exhaust memory
+        let mut payload = Vec::new();
+        (&mut self.reader)
+            .take(length as u64)
+            .read_to_end(&mut payload)?;
+        if payload.len() != length as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "truncated journal entry length={} read={}",
+                    length,
+                    payload.len()
+                ),
+            ));
+        }
+
+        Ok(Some(JournalEntry {
+            timestamp_ns: i64::from_le_bytes(timestamp),
+            msg_type_id: i32_at(&header, 8),
+            payload,
+        }))
+    }
+
+    /// Write all remaining entries into the ring buffer, returning the number written.
+    ///
+    /// When the ring buffer is full the write is retried after yielding the thread. An entry longer
+    /// than the max message length of the ring buffer fails the replay with an error of kind
+    /// `InvalidInput`, and an entry with a msg type id which is not positive with `InvalidData`,
+    /// leaving the entries before it written. An `Accelerated` factor must be positive.
+    pub fn replay<B: RingBuffer>(&mut self, ring_buffer: &B, pace: Pace) -> io::Result<u64> {
+        let speed = match pace {
+            Pace::AsFastAsPossible => None,
+            Pace::Original => Some(1.0),
+            Pace::Accelerated(factor) if factor.is_nan() || factor <= 0.0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("pace factor must be positive: factor={}", factor),
+                ))
+            }
+            Pace::Accelerated(factor) => Some(factor),
+        };
+        let mut origin: Option<(i64, Instant)> = None;
+        let mut entries_replayed = 0;
+
+        while let Some(entry) = self.next_entry()? {
+            let length = entry.payload.len() as Index;
+            if entry.msg_type_id < 1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid journal entry msg_type_id={}", entry.msg_type_id),
+                ));
+            }
+            if length > ring_buffer.max_msg_length() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "journal entry exceeds maxMsgLength of {} length={}",
+                        ring_buffer.max_msg_length(),
+                        length
+                    ),
+                ));
+            }
+
+            if let Some(speed) = speed {
+                let (first_timestamp_ns, start) =
+                    *origin.get_or_insert((entry.timestamp_ns, Instant::now()));
+                let offset_ns =
+                    entry.timestamp_ns.saturating_sub(first_timestamp_ns).max(0) as f64 / speed;
+                let due = start.checked_add(Duration::from_nanos(offset_ns as u64));
+                let now = Instant::now();
+
+                match due {
+                    Some(due) if due > now => thread::sleep(due - now),
+                    Some(_) => {}
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "journal entry timestamp_ns={} is too far after the first",
+                                entry.timestamp_ns
+                            ),
+                        ))
+                    }
+                }
+            }
+
+            while !ring_buffer.write(entry.msg_type_id, &entry.payload[..], 0, length) {
+                thread::yield_now();
+            }
+
+            entries_replayed += 1;
+        }
+
+        Ok(entries_replayed)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[inline]
+fn i32_at(bytes: &[u8], offset: usize) -> i32 {
+    let mut value = [0_u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    i32::from_le_bytes(value)
+}
+
+#[inline]
+fn epoch_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{OneToOneRingBuffer, RingBufferDescriptor};
+    use crate::concurrent::AtomicBuffer;
+    use crate::mem::AlignedBox;
+
+    const CAPACITY: i32 = 1024;
+    const BUFFER_SZ: usize = (CAPACITY + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    const MSG_TYPE_ID: i32 = 101;
+
+    fn journal_of(entries: &[(i64, i32, &[u8])]) -> Vec<u8> {
+        let mut src_buffer = AlignedBox::<[u8]>::zeroed(256, 64);
+        let src_ab = AtomicBuffer::wrap(&mut src_buffer);
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+
+        for &(timestamp_ns, msg_type_id, payload) in entries {
+            for (i, b) in payload.iter().enumerate() {
+                src_ab.put::<u8>(i as Index, *b);
+            }
+            recorder
+                .record(
+                    timestamp_ns,
+                    msg_type_id,
+                    &src_ab,
+                    0,
+                    payload.len() as Index,
+                )
+                .unwrap();
+        }
+
+        recorder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn should_write_versioned_header() {
+        let journal = journal_of(&[(7, MSG_TYPE_ID, b"abc")]);
+
+        assert_eq!(&journal[0..4], b"CRBJ");
+        assert_eq!(i32_at(&journal, 4), JournalDescriptor::VERSION);
+        assert_eq!(i32_at(&journal, 8), JournalDescriptor::HEADER_LENGTH);
+        assert_eq!(journal.len(), 16 + 16 + 3);
+        assert_eq!(&journal[16..24], &7_i64.to_le_bytes());
+        assert_eq!(i32_at(&journal, 24), MSG_TYPE_ID);
+        assert_eq!(i32_at(&journal, 28), 3);
+        assert_eq!(&journal[32..], b"abc");
+    }
+
+    #[test]
+    fn should_read_back_entries() {
+        let journal = journal_of(&[(1, MSG_TYPE_ID, b"first"), (2, MSG_TYPE_ID + 1, b"")]);
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+
+        assert_eq!(
+            replayer.next_entry().unwrap(),
+            Some(JournalEntry {
+                timestamp_ns: 1,
+                msg_type_id: MSG_TYPE_ID,
+                payload: b"first".to_vec()
+            })
+        );
+        assert_eq!(
+            replayer.next_entry().unwrap(),
+            Some(JournalEntry {
+                timestamp_ns: 2,
+                msg_type_id: MSG_TYPE_ID + 1,
+                payload: vec![]
+            })
+        );
+        assert_eq!(replayer.next_entry().unwrap(), None);
+    }
+
+    #[test]
+    fn should_reject_unknown_magic_and_version() {
+        let mut journal = journal_of(&[]);
+
+        journal[4] = 2;
+        assert_eq!(
+            Replayer::new(&journal[..]).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        journal[0] = b'X';
+        assert_eq!(
+            Replayer::new(&journal[..]).err().unwrap().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn should_report_truncated_entry() {
+        let journal = journal_of(&[(1, MSG_TYPE_ID, b"truncated")]);
+        let mut replayer = Replayer::new(&journal[..journal.len() - 1]).unwrap();
+
+        assert_eq!(
+            replayer.next_entry().err().unwrap().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn should_report_truncated_entry_with_corrupt_length() {
+        let mut journal = journal_of(&[(1, MSG_TYPE_ID, b"corrupt")]);
+        journal[28..32].copy_from_slice(&i32::MAX.to_le_bytes());
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+
+        assert_eq!(
+            replayer.next_entry().err().unwrap().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn should_record_read_and_replay_into_ring_buffer() {
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let mut src_buffer = AlignedBox::<[u8]>::zeroed(128, 64);
+        let ab = AtomicBuffer::wrap(&mut buffer);
+        let src_ab = AtomicBuffer::wrap(&mut src_buffer);
+        let ring_buffer = OneToOneRingBuffer::new(ab);
+
+        for m in 0..10 {
+            src_ab.put::<i32>(0, m);
+            assert!(ring_buffer.write(MSG_TYPE_ID + m, &src_ab, 0, 4));
+        }
+
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        let mut times_called = 0;
+        let messages_read =
+            ring_buffer.read(recorder.tap(|_, _, _, _| times_called += 1), u32::MAX);
+
+        assert_eq!(messages_read, 10);
+        assert_eq!(times_called, 10);
+        assert_eq!(recorder.entries_recorded(), 10);
+
+        let journal = recorder.into_inner().unwrap();
+        let mut replay_buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let replay_ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut replay_buffer));
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+
+        assert_eq!(
+            replayer
+                .replay(&replay_ring_buffer, Pace::AsFastAsPossible)
+                .unwrap(),
+            10
+        );
+
+        let mut received = vec![];
+        replay_ring_buffer.read(
+            |msg_type_id, buffer, index, length| {
+                assert_eq!(length, 4);
+                received.push((msg_type_id, buffer.get::<i32>(index)));
+            },
+            u32::MAX,
+        );
+
+        let expected: Vec<(i32, i32)> = (0..10).map(|m| (MSG_TYPE_ID + m, m)).collect();
+        assert_eq!(received, expected);
+    }
+
+    #[test]
+    fn should_reject_pace_factor_which_is_not_positive() {
+        let journal = journal_of(&[(0, MSG_TYPE_ID, b"a")]);
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer));
+
+        for factor in [0.0, -2.0, f64::NAN] {
+            let mut replayer = Replayer::new(&journal[..]).unwrap();
+            let error = replayer
+                .replay(&ring_buffer, Pace::Accelerated(factor))
+                .err()
+                .unwrap();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert_eq!(ring_buffer.producer_position(), 0);
+    }
+
+    #[test]
+    fn should_reject_entry_exceeding_max_msg_length() {
+        let too_long = [1_u8; (CAPACITY / 8 + 1) as usize];
+        let journal = journal_of(&[(0, MSG_TYPE_ID, b"fits"), (0, MSG_TYPE_ID, &too_long)]);
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer));
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+
+        let error = replayer
+            .replay(&ring_buffer, Pace::AsFastAsPossible)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(
+            error.to_string(),
+            "journal entry exceeds maxMsgLength of 128 length=129"
+        );
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+    }
+
+    #[test]
+    fn should_reject_entry_with_invalid_msg_type_id() {
+        let journal = journal_of(&[(0, 0, b"a")]);
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer));
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+
+        let error = replayer
+            .replay(&ring_buffer, Pace::AsFastAsPossible)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(ring_buffer.producer_position(), 0);
+    }
+
+    #[test]
+    fn should_keep_accelerated_gaps_between_entries() {
+        let journal = journal_of(&[(0, MSG_TYPE_ID, b"a"), (40_000_000, MSG_TYPE_ID, b"b")]);
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer));
+        let mut replayer = Replayer::new(&journal[..]).unwrap();
+        let start = Instant::now();
+
+        assert_eq!(
+            replayer
+                .replay(&ring_buffer, Pace::Accelerated(2.0))
+                .unwrap(),
+            2
+        );
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+}
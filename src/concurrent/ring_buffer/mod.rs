@@ -17,18 +17,34 @@
  * limitations under the License.
  */
 
+use std::fmt;
 use std::mem::size_of;
+use std::time::{Duration, Instant};
 
-use crate::concurrent::AtomicBuffer;
+use crate::buffer::{DirectBuffer, ReadOnlyBuffer};
+use crate::concurrent::{AtomicBuffer, IdleStrategy};
 use crate::util::bit_util;
 use crate::util::bit_util::CACHE_LINE_LENGTH;
 use crate::util::Index;
 
+pub use self::cursor::RingBufferCursor;
+pub use self::inspector::RingBufferInspector;
 pub use self::many_to_one_ring_buffer::ManyToOneRingBuffer;
 pub use self::one_to_one_ring_buffer::OneToOneRingBuffer;
+pub use self::stats::{RingBufferCounters, RingBufferStats};
+pub use self::watchdog::UnblockWatchdog;
+pub use self::zero_fill::ZeroFill;
 
+use self::zero_fill::ZeroFiller;
+
+pub mod cursor;
+pub mod inspector;
+pub mod journal;
 pub mod many_to_one_ring_buffer;
 pub mod one_to_one_ring_buffer;
+pub mod stats;
+pub mod watchdog;
+pub mod zero_fill;
 
 pub struct RingBufferDescriptor;
 
@@ -39,6 +55,11 @@ impl RingBufferDescriptor {
     pub const CORRELATION_COUNTER_OFFSET: Index = CACHE_LINE_LENGTH * 8;
     pub const CONSUMER_HEARTBEAT_OFFSET: Index = CACHE_LINE_LENGTH * 10;
 
+    /// Position read up to by a [`ZeroFill::Batched`] or [`ZeroFill::HeadersOnly`] consumer,
+    /// published before it holds back or zeroes bytes, in the padding following the head position
+    /// which Agrona leaves unused. A [`ZeroFill::Eager`] consumer does not write it.
+    pub const CONSUMER_POSITION_OFFSET: Index = Self::HEAD_POSITION_OFFSET + 8;
+
     /* Total length of the trailer in bytes. */
     pub const TRAILER_LENGTH: Index = CACHE_LINE_LENGTH * 12;
 
@@ -118,28 +139,340 @@ impl RecordDescriptor {
     }
 }
 
+/// Action to be taken by a ring buffer after a handler passed to
+/// [`controlled_read`](RingBuffer::controlled_read) has processed a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlledAction {
+    /// Abort the current read and do not consume the message, it will be delivered again.
+    Abort,
+    /// Stop reading after this message and consume up to and including it.
+    Break,
+    /// Consume the messages read so far and continue reading.
+    Commit,
+    /// Continue reading, consuming the messages when the read completes.
+    Continue,
+}
+
+/// How long [`write_blocking`](RingBuffer::write_blocking) waits for capacity, converted from a
+/// [`Duration`] when there is no need to unblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteTimeout {
+    /// Give up once the write has waited this long.
+    pub timeout: Duration,
+    /// Call [`unblock`](RingBuffer::unblock) when the consumer position has not moved for this
+    /// long while behind the producer, in case a producer died between claiming and committing a
+    /// record. As for [`UnblockWatchdog`], this should exceed the longest time a live producer
+    /// takes to write a record.
+    pub unblock_after: Option<Duration>,
+}
+
+impl From<Duration> for WriteTimeout {
+    fn from(timeout: Duration) -> Self {
+        WriteTimeout {
+            timeout,
+            unblock_after: None,
+        }
+    }
+}
+
+/// Returned by [`write_blocking`](RingBuffer::write_blocking) when there was not enough capacity
+/// to write the message before the timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError {
+    pub timeout: Duration,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out writing to ring buffer after {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
 pub trait RingBuffer {
+    /// Returned by [`try_claim`](RingBuffer::try_claim) when there is not enough capacity.
+    const INSUFFICIENT_CAPACITY: Index = -2;
+
     fn capacity(&self) -> Index;
 
-    fn write(
+    /// Copy `length` bytes of `src_buffer` from `src_index` into the ring as a message, returning
+    /// false if there is not enough capacity.
+    fn write<B: DirectBuffer + ?Sized>(
         &self,
         msg_type_id: i32,
-        src_buffer: &AtomicBuffer,
+        src_buffer: &B,
         src_index: Index,
         length: Index,
     ) -> bool;
 
-    fn read<'a, F>(&'a self, handler: F, message_count_limit: u32) -> u32
+    /// [`write`](RingBuffer::write), retrying with `idle` while there is not enough capacity until
+    /// the message is written or the timeout expires.
+    fn write_blocking<B, I>(
+        &self,
+        msg_type_id: i32,
+        src_buffer: &B,
+        src_index: Index,
+        length: Index,
+        idle: &I,
+        timeout: impl Into<WriteTimeout>,
+    ) -> Result<(), TimeoutError>
+    where
+        B: DirectBuffer + ?Sized,
+        I: IdleStrategy + ?Sized,
+    {
+        if self.write(msg_type_id, src_buffer, src_index, length) {
+            return Ok(());
+        }
+
+        let timeout = timeout.into();
+        let start = Instant::now();
+        let mut consumer_position = self.consumer_position();
+        let mut time_of_last_position_change = start;
+        idle.reset();
+
+        loop {
+            idle.idle();
+            if self.write(msg_type_id, src_buffer, src_index, length) {
+                return Ok(());
+            }
+
+            let now = Instant::now();
+            if now.duration_since(start) >= timeout.timeout {
+                return Err(TimeoutError {
+                    timeout: timeout.timeout,
+                });
+            }
+
+            if let Some(unblock_after) = timeout.unblock_after {
+                let position = self.consumer_position();
+                if position != consumer_position {
+                    consumer_position = position;
+                    time_of_last_position_change = now;
+                } else if now.duration_since(time_of_last_position_change) > unblock_after
+                    && self.producer_position() > position
+                    && self.unblock()
+                {
+                    time_of_last_position_change = now;
+                }
+            }
+        }
+    }
+
+    /// Write `messages` as consecutive records, claiming space for all of them with a single
+    /// update of the tail and then publishing each record in order.
+    ///
+    /// The batch is all or nothing: if there is not enough capacity for every record, nothing is
+    /// written and false is returned, so the caller can retry the whole batch. Each message is
+    /// checked as for [`write`](RingBuffer::write), and a batch whose records are longer than
+    /// [`max_batch_length`](RingBuffer::max_batch_length) panics.
+    fn write_batch(&self, messages: &[(i32, &[u8])]) -> bool;
+
+    /// Claim space for a message of `length` bytes to be encoded directly into the ring buffer.
+    ///
+    /// Returns the index at which the message should be encoded in [`buffer`](RingBuffer::buffer),
+    /// or [`INSUFFICIENT_CAPACITY`](RingBuffer::INSUFFICIENT_CAPACITY). The claim must be followed
+    /// by [`commit`](RingBuffer::commit) or [`abort`](RingBuffer::abort) of the returned index.
+    fn try_claim(&self, msg_type_id: i32, length: Index) -> Index;
+
+    /// Make a message claimed by [`try_claim`](RingBuffer::try_claim) available to the consumer.
+    fn commit(&self, index: Index);
+
+    /// Release a message claimed by [`try_claim`](RingBuffer::try_claim) as padding.
+    fn abort(&self, index: Index);
+
+    /// Read messages, passing the handler the type id, a read-only view over just the message,
+    /// and the index and length of the message in the view. The index is always 0, so handlers can
+    /// not reach other records or the trailer through the buffer.
+    fn read<F>(&self, handler: F, message_count_limit: u32) -> u32
+    where
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index);
+
+    /// Read messages, letting the handler decide after each message whether to continue, commit
+    /// progress so far, or abort.
+    fn controlled_read<F>(&self, handler: F, message_count_limit: u32) -> u32
+    where
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index) -> ControlledAction;
+
+    /// Pass the handler the messages available to read, as [`read`](RingBuffer::read) would,
+    /// without advancing the consumer position or zeroing them. Should only be called by the
+    /// consumer, other observers can follow the records with a [`RingBufferCursor`].
+    ///
+    /// Each call builds a [`RingBufferCursor`], which allocates a copy buffer of
+    /// [`max_msg_length`](RingBuffer::max_msg_length) bytes. A caller peeking repeatedly to follow
+    /// the records should keep a cursor and [`poll`](RingBufferCursor::poll) it instead.
+    fn peek<F>(&self, handler: F, message_count_limit: u32) -> u32
+    where
+        Self: Sized,
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index),
+    {
+        RingBufferCursor::new(self).poll(handler, message_count_limit)
+    }
+
+    /// [`read`](RingBuffer::read) into a [`MessageHandler`], which keeps its state between reads.
+    fn read_with<H>(&self, handler: &mut H, message_count_limit: u32) -> u32
+    where
+        H: MessageHandler + ?Sized,
+    {
+        self.read(
+            |msg_type_id, buffer, index, length| handler.on_message(msg_type_id, buffer, index, length),
+            message_count_limit,
+        )
+    }
+
+    /// [`controlled_read`](RingBuffer::controlled_read) into a [`ControlledMessageHandler`].
+    fn controlled_read_with<H>(&self, handler: &mut H, message_count_limit: u32) -> u32
     where
-        F: FnMut(i32, &'a AtomicBuffer, Index, Index);
+        H: ControlledMessageHandler + ?Sized,
+    {
+        self.controlled_read(
+            |msg_type_id, buffer, index, length| handler.on_message(msg_type_id, buffer, index, length),
+            message_count_limit,
+        )
+    }
+
+    /// The underlying buffer, including the trailer.
+    fn buffer(&self) -> &AtomicBuffer;
 
     fn max_msg_length(&self) -> Index;
 
+    /// The most a batch passed to [`write_batch`](RingBuffer::write_batch) can take up in records,
+    /// with their headers, which is the record of a message of
+    /// [`max_msg_length`](RingBuffer::max_msg_length). A batch of this length fits once the
+    /// consumer has caught up wherever the tail is, so retrying it can not livelock.
+    fn max_batch_length(&self) -> Index {
+        bit_util::align(
+            self.max_msg_length() + RecordDescriptor::HEADER_LENGTH,
+            RecordDescriptor::ALIGNMENT,
+        )
+    }
+
     fn next_correlation_id(&self) -> i64;
 
+    /// The position in bytes from start up to which producers have claimed space.
+    fn producer_position(&self) -> i64;
+
+    /// The position in bytes from start up to which the consumer has read.
+    fn consumer_position(&self) -> i64;
+
+    /// The time of the last consumer heartbeat, in the units chosen by the consumer.
+    fn consumer_heartbeat_time(&self) -> i64;
+
+    fn set_consumer_heartbeat_time(&self, time: i64);
+
+    /// A snapshot of the counters if the ring buffer is instrumented with [`RingBufferCounters`].
+    fn stats(&self) -> Option<RingBufferStats>;
+
     fn unblock(&self) -> bool;
 }
 
+#[inline]
+fn compute_record_index(index: Index, capacity: Index) -> Index {
+    let record_index = index - RecordDescriptor::HEADER_LENGTH;
+    if record_index < 0 || record_index > capacity - RecordDescriptor::HEADER_LENGTH {
+        panic!("invalid message index {}", index)
+    }
+
+    record_index
+}
+
+/// The aligned length of the records for a batch of `messages`, checking each message with
+/// `check_msg_length` and the total against `max_batch_length`.
+fn compute_batch_length(
+    messages: &[(i32, &[u8])],
+    max_batch_length: Index,
+    check_msg_length: impl Fn(Index),
+) -> Index {
+    let mut batch_length: i64 = 0;
+    for (msg_type_id, msg) in messages {
+        RecordDescriptor::check_msg_type_id(*msg_type_id);
+        check_msg_length(msg.len() as Index);
+        batch_length += bit_util::align(
+            msg.len() as Index + RecordDescriptor::HEADER_LENGTH,
+            RecordDescriptor::ALIGNMENT,
+        ) as i64;
+    }
+
+    if batch_length > max_batch_length as i64 {
+        panic!(
+            "batch exceeds maxBatchLength of {} length={}",
+            max_batch_length, batch_length
+        )
+    }
+
+    batch_length as Index
+}
+
+/// The message bytes in a batch, excluding headers.
+#[inline]
+fn batch_bytes(messages: &[(i32, &[u8])]) -> i64 {
+    messages.iter().map(|(_, msg)| msg.len() as i64).sum()
+}
+
+/// A read-only view over the message of the record at `record_index`, as passed to read handlers.
+#[inline]
+fn message_view(buffer: &AtomicBuffer, record_index: Index, record_length: Index) -> ReadOnlyBuffer {
+    ReadOnlyBuffer::new(buffer.view(
+        RecordDescriptor::encoded_msg_offset(record_index),
+        record_length - RecordDescriptor::HEADER_LENGTH,
+    ))
+}
+
+#[inline]
+fn verify_claimed_space_not_released(buffer: &AtomicBuffer, record_index: Index) -> Index {
+    let record_length = buffer.get::<i32>(RecordDescriptor::length_offset(record_index));
+    if record_length < 0 {
+        return record_length;
+    }
+
+    if RecordDescriptor::PADDING_MSG_TYPE_ID
+        == buffer.get::<i32>(RecordDescriptor::type_offset(record_index))
+    {
+        panic!("claimed space previously aborted")
+    } else {
+        panic!("claimed space previously committed")
+    }
+}
+
+/// Handler for messages passed to [`read_with`](RingBuffer::read_with), implemented by closures
+/// and by stateful handlers such as assemblers and dispatchers.
 pub trait MessageHandler {
-    fn on_message(&self, msg_type_id: i32, buffer: &AtomicBuffer, index: Index, length: Index);
+    fn on_message(&mut self, msg_type_id: i32, buffer: &ReadOnlyBuffer, index: Index, length: Index);
+}
+
+impl<F> MessageHandler for F
+where
+    F: FnMut(i32, &ReadOnlyBuffer, Index, Index),
+{
+    #[inline]
+    fn on_message(&mut self, msg_type_id: i32, buffer: &ReadOnlyBuffer, index: Index, length: Index) {
+        self(msg_type_id, buffer, index, length)
+    }
+}
+
+/// Handler for messages passed to [`controlled_read_with`](RingBuffer::controlled_read_with).
+pub trait ControlledMessageHandler {
+    fn on_message(
+        &mut self,
+        msg_type_id: i32,
+        buffer: &ReadOnlyBuffer,
+        index: Index,
+        length: Index,
+    ) -> ControlledAction;
+}
+
+impl<F> ControlledMessageHandler for F
+where
+    F: FnMut(i32, &ReadOnlyBuffer, Index, Index) -> ControlledAction,
+{
+    #[inline]
+    fn on_message(
+        &mut self,
+        msg_type_id: i32,
+        buffer: &ReadOnlyBuffer,
+        index: Index,
+        length: Index,
+    ) -> ControlledAction {
+        self(msg_type_id, buffer, index, length)
+    }
 }
@@ -28,7 +28,9 @@ pub struct OneToOneRingBuffer {
     head_cache_position_index: Index,
     tail_position_index: Index,
     correlation_id_counter_index: Index,
-    _consumer_heartbeat_index: Index,
+    consumer_heartbeat_index: Index,
+    zero_fill: ZeroFiller,
+    counters: Option<RingBufferCounters>,
 }
 
 unsafe impl Send for OneToOneRingBuffer {}
@@ -38,6 +40,9 @@ unsafe impl Sync for OneToOneRingBuffer {}
 impl OneToOneRingBuffer {
     #[inline]
     fn check_msg_length(&self, length: Index) {
+        if length < 0 {
+            panic!("invalid message length={}", length)
+        }
         if length > self.max_msg_length {
             panic!(
                 "encoded message exceeds maxMsgLength of {} length={}",
@@ -47,6 +52,11 @@ impl OneToOneRingBuffer {
     }
 
     pub fn new(buffer: AtomicBuffer) -> OneToOneRingBuffer {
+        Self::with_zero_fill(buffer, ZeroFill::default())
+    }
+
+    /// A ring buffer whose consumer zeroes the records it reads according to `zero_fill`.
+    pub fn with_zero_fill(buffer: AtomicBuffer, zero_fill: ZeroFill) -> OneToOneRingBuffer {
         let capacity = buffer.capacity() - RingBufferDescriptor::TRAILER_LENGTH;
 
         RingBufferDescriptor::check_capacity(capacity);
@@ -60,42 +70,50 @@ impl OneToOneRingBuffer {
             head_position_index: capacity + RingBufferDescriptor::HEAD_POSITION_OFFSET,
             correlation_id_counter_index: capacity
                 + RingBufferDescriptor::CORRELATION_COUNTER_OFFSET,
-            _consumer_heartbeat_index: capacity + RingBufferDescriptor::CONSUMER_HEARTBEAT_OFFSET,
+            consumer_heartbeat_index: capacity + RingBufferDescriptor::CONSUMER_HEARTBEAT_OFFSET,
+            zero_fill: ZeroFiller::new(zero_fill, capacity),
+            counters: None,
         }
     }
-}
 
-impl RingBuffer for OneToOneRingBuffer {
-    fn capacity(&self) -> Index {
-        self.capacity
+    /// Count writes, reads and unblocks in `counters`, which [`stats`](RingBuffer::stats) reports.
+    pub fn set_counters(&mut self, counters: RingBufferCounters) {
+        self.counters = Some(counters);
     }
 
-    fn write(
-        &self,
-        msg_type_id: i32,
-        src_buffer: &AtomicBuffer,
-        src_index: i32,
-        length: i32,
-    ) -> bool {
-        RecordDescriptor::check_msg_type_id(msg_type_id);
-        self.check_msg_length(length);
+    #[inline]
+    fn count(&self, offset: Index, delta: i64) {
+        if let Some(counters) = &self.counters {
+            counters.add(offset, delta);
+        }
+    }
 
-        let record_length: Index = length + RecordDescriptor::HEADER_LENGTH;
+    #[inline]
+    fn count_write(&self, messages: i64, bytes: i64) {
+        self.count(RingBufferCounters::WRITES_OFFSET, messages);
+        self.count(RingBufferCounters::BYTES_WRITTEN_OFFSET, bytes);
+    }
+
+    /// Claims space for a record of `record_length`, which must not be negative. The trailer
+    /// indexes are in bounds as the capacity was checked on construction, and the claimed record
+    /// and the header after it lie within the capacity whatever the head and tail read, so the
+    /// `unchecked` feature can skip the bounds checks.
+    fn claim_capacity(&self, record_length: Index) -> Index {
         let aligned_record_length: Index = bit_util::align(record_length, RecordDescriptor::ALIGNMENT);
         let required_capacity: Index = aligned_record_length + RecordDescriptor::HEADER_LENGTH;
         let mask: i64 = self.capacity as i64 - 1;
 
-        let mut head = self.buffer.get_i64(self.head_cache_position_index);
-        let tail: i64 = self.buffer.get_i64(self.tail_position_index);
+        let mut head = self.buffer.get_hot::<i64>(self.head_cache_position_index);
+        let tail: i64 = self.buffer.get_hot::<i64>(self.tail_position_index);
         let available_capacity: Index = self.capacity - (tail - head) as Index;
 
         if required_capacity > available_capacity {
-            head = self.buffer.get_i64_volatile(self.head_position_index);
+            head = self.buffer.get_volatile_hot::<i64>(self.head_position_index);
 
             if required_capacity > (self.capacity - (tail - head) as Index) {
-                return false;
+                return Self::INSUFFICIENT_CAPACITY;
             }
-            self.buffer.put_i64(self.head_cache_position_index, head);
+            self.buffer.put_hot(self.head_cache_position_index, head);
         }
 
         let mut padding: Index = 0;
@@ -106,33 +124,63 @@ impl RingBuffer for OneToOneRingBuffer {
             let mut head_index: Index = (head & mask) as Index;
 
             if required_capacity > head_index {
-                head = self.buffer.get_i64_volatile(self.head_position_index);
+                head = self.buffer.get_volatile_hot::<i64>(self.head_position_index);
                 head_index = (head & mask) as Index;
 
                 if required_capacity > head_index {
-                    return false;
+                    return Self::INSUFFICIENT_CAPACITY;
                 }
 
-                self.buffer
-                    .put_i64_ordered(self.head_cache_position_index, head);
+                self.buffer.put_ordered_hot(self.head_cache_position_index, head);
             }
 
             padding = to_buffer_end_length;
         }
 
-        self.buffer.put_i64_ordered(self.tail_position_index, tail + (aligned_record_length + padding) as i64);
+        self.buffer.put_ordered_hot(
+            self.tail_position_index,
+            tail + (aligned_record_length + padding) as i64,
+        );
 
         if 0 != padding {
-            self.buffer.put_i64(0, 0);
-
-            self.buffer.put_i64_ordered(
+            self.count(RingBufferCounters::PADDING_RECORDS_OFFSET, 1);
+            self.buffer.put_hot::<i64>(0, 0);
+            self.buffer.put_ordered_hot(
                 record_index,
                 RecordDescriptor::make_header(padding, RecordDescriptor::PADDING_MSG_TYPE_ID),
             );
             record_index = 0;
         }
 
-        self.buffer.put_i64(record_index + aligned_record_length, 0); // pre-zero next message header
+        // pre-zero next message header
+        self.buffer.put_hot::<i64>(record_index + aligned_record_length, 0);
+
+        record_index
+    }
+}
+
+impl RingBuffer for OneToOneRingBuffer {
+    fn capacity(&self) -> Index {
+        self.capacity
+    }
+
+    fn write<B: DirectBuffer + ?Sized>(
+        &self,
+        msg_type_id: i32,
+        src_buffer: &B,
+        src_index: i32,
+        length: i32,
+    ) -> bool {
+        RecordDescriptor::check_msg_type_id(msg_type_id);
+        self.check_msg_length(length);
+
+        let record_length: Index = length + RecordDescriptor::HEADER_LENGTH;
+        let record_index: Index = self.claim_capacity(record_length);
+
+        if Self::INSUFFICIENT_CAPACITY == record_index {
+            self.count(RingBufferCounters::FAILED_WRITES_OFFSET, 1);
+            return false;
+        }
 
         self.buffer.put_bytes(
             RecordDescriptor::encoded_msg_offset(record_index),
@@ -140,37 +188,121 @@ impl RingBuffer for OneToOneRingBuffer {
             src_index,
             length,
         );
-        self.buffer.put_i64_ordered(
+        self.buffer.put_ordered_hot(
             record_index,
             RecordDescriptor::make_header(record_length, msg_type_id),
         );
+        self.count_write(1, length as i64);
+
+        true
+    }
+
+    fn write_batch(&self, messages: &[(i32, &[u8])]) -> bool {
+        let batch_length = compute_batch_length(messages, self.max_batch_length(), |length| {
+            self.check_msg_length(length)
+        });
+        if messages.is_empty() {
+            return true;
+        }
+
+        let batch_index: Index = self.claim_capacity(batch_length);
+
+        if Self::INSUFFICIENT_CAPACITY == batch_index {
+            self.count(RingBufferCounters::FAILED_WRITES_OFFSET, 1);
+            return false;
+        }
+
+        // zero the headers within the batch, which only the first is pre-zeroed for, so the
+        // consumer stops at each record until it is published
+        let mut record_index = batch_index;
+        for (_, msg) in messages {
+            self.buffer.put_i64(record_index, 0);
+            self.buffer
+                .put_slice(RecordDescriptor::encoded_msg_offset(record_index), msg);
+            record_index += bit_util::align(
+                msg.len() as Index + RecordDescriptor::HEADER_LENGTH,
+                RecordDescriptor::ALIGNMENT,
+            );
+        }
+
+        record_index = batch_index;
+        for (msg_type_id, msg) in messages {
+            let record_length: Index = msg.len() as Index + RecordDescriptor::HEADER_LENGTH;
+
+            self.buffer.put_i64_ordered(
+                record_index,
+                RecordDescriptor::make_header(record_length, *msg_type_id),
+            );
+
+            record_index += bit_util::align(record_length, RecordDescriptor::ALIGNMENT);
+        }
+
+        self.count_write(messages.len() as i64, batch_bytes(messages));
+
+        true
+    }
+
+    fn try_claim(&self, msg_type_id: i32, length: Index) -> Index {
+        RecordDescriptor::check_msg_type_id(msg_type_id);
+        self.check_msg_length(length);
+
+        let record_length: Index = length + RecordDescriptor::HEADER_LENGTH;
+        let record_index: Index = self.claim_capacity(record_length);
+
+        if Self::INSUFFICIENT_CAPACITY == record_index {
+            self.count(RingBufferCounters::FAILED_WRITES_OFFSET, 1);
+            return record_index;
+        }
+
+        self.buffer.put_i64_ordered(
+            record_index,
+            RecordDescriptor::make_header(-record_length, msg_type_id),
+        );
 
-        return true;
+        RecordDescriptor::encoded_msg_offset(record_index)
     }
 
-    fn read<'a, F>(&'a self, mut handler: F, message_count_limit: u32) -> u32
+    fn commit(&self, index: Index) {
+        let record_index = compute_record_index(index, self.capacity);
+        let record_length = verify_claimed_space_not_released(&self.buffer, record_index);
+
+        self.count_write(1, (-record_length - RecordDescriptor::HEADER_LENGTH) as i64);
+        self.buffer
+            .put_ordered(RecordDescriptor::length_offset(record_index), -record_length);
+    }
+
+    fn abort(&self, index: Index) {
+        let record_index = compute_record_index(index, self.capacity);
+        let record_length = verify_claimed_space_not_released(&self.buffer, record_index);
+
+        self.buffer.put(
+            RecordDescriptor::type_offset(record_index),
+            RecordDescriptor::PADDING_MSG_TYPE_ID,
+        );
+        self.buffer
+            .put_ordered(RecordDescriptor::length_offset(record_index), -record_length);
+    }
+
+    fn read<F>(&self, mut handler: F, message_count_limit: u32) -> u32
     where
-        F: FnMut(i32, &'a AtomicBuffer, Index, Index),
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index),
     {
         let head: i64 = self.buffer.get_i64(self.head_position_index);
-        let head_index = (head & (self.capacity - 1) as i64) as Index;
+        let position = self.zero_fill.read_position(&self.buffer, head);
+        let head_index = (position & (self.capacity - 1) as i64) as Index;
         let contiguous_block_length: Index = self.capacity - head_index;
         let mut messages_read = 0;
         let bytes_read = Cell::new(0);
 
         defer! {
-            let read = bytes_read.get();
-            if read != 0
-            {
-                self.buffer.set_memory(head_index, read, 0);
-                self.buffer.put_i64_ordered(self.head_position_index, head + read as i64);
-            }
+            self.zero_fill.release(&self.buffer, head, position, bytes_read.get());
         }
 
         while (bytes_read.get() < contiguous_block_length) && (messages_read < message_count_limit)
         {
             let record_index: Index = head_index + bytes_read.get();
-            let header: i64 = self.buffer.get_i64_volatile(record_index);
+            // record_index is below the capacity, so the header is before the end of the trailer
+            let header: i64 = self.buffer.get_volatile_hot(record_index);
             let record_length: Index = RecordDescriptor::record_length(header);
 
             if record_length <= 0 {
@@ -189,23 +321,122 @@ impl RingBuffer for OneToOneRingBuffer {
             messages_read += 1;
             handler(
                 msg_type_id,
-                &self.buffer,
-                RecordDescriptor::encoded_msg_offset(record_index),
+                &message_view(&self.buffer, record_index, record_length),
+                0,
+                record_length - RecordDescriptor::HEADER_LENGTH,
+            );
+        }
+
+        if let Some(counters) = &self.counters {
+            counters.record_read(messages_read);
+        }
+
+        messages_read
+    }
+
+    fn controlled_read<F>(&self, mut handler: F, message_count_limit: u32) -> u32
+    where
+        F: FnMut(i32, &ReadOnlyBuffer, Index, Index) -> ControlledAction,
+    {
+        let head = Cell::new(self.buffer.get_i64(self.head_position_index));
+        let position = Cell::new(self.zero_fill.read_position(&self.buffer, head.get()));
+        let head_index = Cell::new((position.get() & (self.capacity - 1) as i64) as Index);
+        let mut messages_read = 0;
+        let bytes_read = Cell::new(0);
+
+        defer! {
+            self.zero_fill.release(&self.buffer, head.get(), position.get(), bytes_read.get());
+        }
+
+        while (head_index.get() + bytes_read.get() < self.capacity)
+            && (messages_read < message_count_limit)
+        {
+            let record_index: Index = head_index.get() + bytes_read.get();
+            let header: i64 = self.buffer.get_volatile_hot(record_index);
+            let record_length: Index = RecordDescriptor::record_length(header);
+
+            if record_length <= 0 {
+                break;
+            }
+
+            let aligned_length = bit_util::align(record_length, RecordDescriptor::ALIGNMENT);
+            bytes_read.set(bytes_read.get() + aligned_length);
+
+            let msg_type_id: Index = RecordDescriptor::message_type_id(header);
+            if RecordDescriptor::PADDING_MSG_TYPE_ID == msg_type_id {
+                continue;
+            }
+
+            let action = handler(
+                msg_type_id,
+                &message_view(&self.buffer, record_index, record_length),
+                0,
                 record_length - RecordDescriptor::HEADER_LENGTH,
             );
+
+            if ControlledAction::Abort == action {
+                bytes_read.set(bytes_read.get() - aligned_length);
+                break;
+            }
+
+            messages_read += 1;
+
+            if ControlledAction::Break == action {
+                break;
+            }
+
+            if ControlledAction::Commit == action {
+                let read = bytes_read.get();
+                head.set(
+                    self.zero_fill
+                        .release(&self.buffer, head.get(), position.get(), read),
+                );
+                head_index.set(head_index.get() + read);
+                position.set(position.get() + read as i64);
+                bytes_read.set(0);
+            }
+        }
+
+        if let Some(counters) = &self.counters {
+            counters.record_read(messages_read);
         }
 
         messages_read
     }
 
+    fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
     fn max_msg_length(&self) -> i32 {
         self.max_msg_length
     }
 
     fn next_correlation_id(&self) -> i64 {
-        return self
+        self
             .buffer
-            .get_and_add_i64(self.correlation_id_counter_index, 1);
+            .get_and_add_i64(self.correlation_id_counter_index, 1)
+    }
+
+    fn producer_position(&self) -> i64 {
+        self.buffer.get_i64_volatile(self.tail_position_index)
+    }
+
+    fn consumer_position(&self) -> i64 {
+        let head = self.buffer.get_i64_volatile(self.head_position_index);
+        self.zero_fill.consumer_position(&self.buffer, head)
+    }
+
+    fn consumer_heartbeat_time(&self) -> i64 {
+        self.buffer.get_i64_volatile(self.consumer_heartbeat_index)
+    }
+
+    fn set_consumer_heartbeat_time(&self, time: i64) {
+        self.buffer.put_i64_ordered(self.consumer_heartbeat_index, time);
+    }
+
+    fn stats(&self) -> Option<RingBufferStats> {
+        self.counters.as_ref().map(RingBufferCounters::snapshot)
     }
 
     fn unblock(&self) -> bool {
@@ -216,10 +447,13 @@ impl RingBuffer for OneToOneRingBuffer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::mem::Align16;
+    use crate::buffer::{ExpandableArrayBuffer, MutableDirectBuffer};
+    use crate::concurrent::{BusySpinIdleStrategy, YieldingIdleStrategy};
+    use crate::mem::{Align16, AlignedBox};
     use std::panic::AssertUnwindSafe;
     use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
+    use std::time::{Duration, Instant};
     use std::{panic, thread};
 
     const CAPACITY: i32 = 1024;
@@ -232,8 +466,8 @@ mod tests {
     const TAIL_COUNTER_INDEX: Index = 1024 + RingBufferDescriptor::TAIL_POSITION_OFFSET;
 
     struct OneToOneRingBufferTest {
-        _buffer: Align16<Vec<u8>>,
-        _src_buffer: Align16<Vec<u8>>,
+        _buffer: AlignedBox<[u8]>,
+        _src_buffer: AlignedBox<[u8]>,
         ab: AtomicBuffer,
         src_ab: AtomicBuffer,
         ring_buffer: OneToOneRingBuffer,
@@ -241,8 +475,8 @@ mod tests {
 
     impl OneToOneRingBufferTest {
         fn new(buffer_size: usize) -> OneToOneRingBufferTest {
-            let mut buffer = Align16::new(vec![0 as u8; buffer_size]);
-            let mut src_buffer = Align16::new(vec![0 as u8; buffer_size]);
+            let mut buffer = AlignedBox::<[u8]>::zeroed(buffer_size, 64);
+            let mut src_buffer = AlignedBox::<[u8]>::zeroed(buffer_size, 64);
             let ab = AtomicBuffer::wrap(&mut buffer);
             let src_ab = AtomicBuffer::wrap(&mut src_buffer);
             let ring_buffer = OneToOneRingBuffer::new(ab);
@@ -278,7 +512,7 @@ mod tests {
         expected = "Capacity must be a positive power of 2 + TRAILER_LENGTH: capacity=1023"
     )]
     fn should_panic_for_capacity_not_power_of_two() {
-        let mut test_buffer = Align16::new([0 as u8; ODD_BUFFER_SZ]);
+        let mut test_buffer = Align16::new([0_u8; ODD_BUFFER_SZ]);
         let ab = AtomicBuffer::wrap(&mut *test_buffer);
         let _ring_buffer = OneToOneRingBuffer::new(ab);
     }
@@ -295,6 +529,121 @@ mod tests {
         );
     }
 
+    #[test]
+    #[should_panic(expected = "invalid message length=-1")]
+    fn should_panic_when_claiming_negative_message_length() {
+        let context = OneToOneRingBufferTest::default();
+        context.ring_buffer.try_claim(MSG_TYPE_ID, -1);
+    }
+
+    #[test]
+    fn should_write_from_expandable_buffer() {
+        let context = OneToOneRingBufferTest::default();
+        let mut src_buffer = ExpandableArrayBuffer::with_capacity(4);
+        src_buffer.put_slice(0, b"variable size message");
+        let length = 21;
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &src_buffer, 0, length));
+
+        let mut received = vec![];
+        context.ring_buffer.read(
+            |_, buffer, index, length| {
+                let mut bytes = vec![0_u8; length as usize];
+                buffer.get_bytes(index, &mut bytes);
+                received = bytes;
+            },
+            1,
+        );
+
+        assert_eq!(received, b"variable size message");
+    }
+
+    struct MessageAssembler {
+        messages: Vec<(i32, Vec<u8>)>,
+    }
+
+    impl MessageHandler for MessageAssembler {
+        fn on_message(
+            &mut self,
+            msg_type_id: i32,
+            buffer: &ReadOnlyBuffer,
+            index: Index,
+            length: Index,
+        ) {
+            let mut bytes = vec![0_u8; length as usize];
+            buffer.get_bytes(index, &mut bytes);
+            self.messages.push((msg_type_id, bytes));
+        }
+    }
+
+    struct StopAfter {
+        remaining: u32,
+    }
+
+    impl ControlledMessageHandler for StopAfter {
+        fn on_message(&mut self, _: i32, _: &ReadOnlyBuffer, _: Index, _: Index) -> ControlledAction {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                ControlledAction::Break
+            } else {
+                ControlledAction::Continue
+            }
+        }
+    }
+
+    #[test]
+    fn should_keep_handler_state_between_reads() {
+        let context = OneToOneRingBufferTest::default();
+        let mut assembler = MessageAssembler { messages: vec![] };
+
+        for i in 0..3_u8 {
+            let src = [i; 8];
+            assert!(context
+                .ring_buffer
+                .write(MSG_TYPE_ID, &src[..], 0, 8));
+            assert_eq!(context.ring_buffer.read_with(&mut assembler, u32::MAX), 1);
+        }
+
+        let mut times_called = 0;
+        let handler: &mut dyn MessageHandler =
+            &mut |_: i32, _: &ReadOnlyBuffer, _: Index, _: Index| times_called += 1;
+        assert_eq!(context.ring_buffer.read_with(handler, u32::MAX), 0);
+
+        assert_eq!(
+            assembler.messages,
+            vec![
+                (MSG_TYPE_ID, vec![0; 8]),
+                (MSG_TYPE_ID, vec![1; 8]),
+                (MSG_TYPE_ID, vec![2; 8]),
+            ]
+        );
+        assert_eq!(times_called, 0);
+    }
+
+    #[test]
+    fn should_controlled_read_with_stateful_handler() {
+        let context = OneToOneRingBufferTest::default();
+        for _ in 0..3 {
+            assert!(context
+                .ring_buffer
+                .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        }
+
+        let mut handler = StopAfter { remaining: 2 };
+
+        assert_eq!(context.ring_buffer.controlled_read_with(&mut handler, u32::MAX), 2);
+        assert_eq!(handler.remaining, 0);
+        let mut remaining = |_, _: &ReadOnlyBuffer, _, _| ControlledAction::Continue;
+        assert_eq!(
+            context
+                .ring_buffer
+                .controlled_read_with(&mut remaining, u32::MAX),
+            1
+        );
+    }
+
     #[test]
     fn should_write_to_empty_buffer() {
         let context = OneToOneRingBufferTest::default();
@@ -369,6 +718,76 @@ mod tests {
         assert_eq!(context.ab.get::<i64>(TAIL_COUNTER_INDEX), tail);
     }
 
+    #[test]
+    fn should_write_blocking_when_capacity_available() {
+        let context = OneToOneRingBufferTest::default();
+
+        assert_eq!(
+            context.ring_buffer.write_blocking(
+                MSG_TYPE_ID,
+                &context.src_ab,
+                0,
+                8,
+                &BusySpinIdleStrategy,
+                Duration::ZERO
+            ),
+            Ok(())
+        );
+        assert_eq!(context.ab.get::<i64>(TAIL_COUNTER_INDEX), 16);
+    }
+
+    #[test]
+    fn should_time_out_write_blocking_when_buffer_full() {
+        let context = OneToOneRingBufferTest::default();
+        let timeout = Duration::from_millis(20);
+
+        context.ab.put_i64(HEAD_COUNTER_INDEX, 0);
+        context.ab.put_i64(TAIL_COUNTER_INDEX, CAPACITY as i64);
+
+        let start = Instant::now();
+        assert_eq!(
+            context.ring_buffer.write_blocking(
+                MSG_TYPE_ID,
+                &context.src_ab,
+                0,
+                8,
+                &YieldingIdleStrategy,
+                timeout
+            ),
+            Err(TimeoutError { timeout })
+        );
+        assert!(start.elapsed() >= timeout);
+        assert_eq!(context.ab.get_i64(TAIL_COUNTER_INDEX), CAPACITY as i64);
+    }
+
+    #[test]
+    fn should_write_blocking_once_consumer_frees_capacity() {
+        let context = OneToOneRingBufferTest::default();
+        let ring_buffer = &context.ring_buffer;
+        let src = [0_u8; 100];
+
+        while ring_buffer.write(MSG_TYPE_ID, &src[..], 0, 100) {}
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                ring_buffer.read(|_, _, _, _| {}, u32::MAX);
+            });
+
+            assert_eq!(
+                ring_buffer.write_blocking(
+                    MSG_TYPE_ID,
+                    &src[..],
+                    0,
+                    100,
+                    &YieldingIdleStrategy,
+                    Duration::from_secs(10)
+                ),
+                Ok(())
+            );
+        });
+    }
+
     #[test]
     fn should_insert_padding_record_plus_message_on_buffer_wrap() {
         let context = OneToOneRingBufferTest::default();
@@ -449,6 +868,138 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_write_batch_with_single_tail_update() {
+        let context = OneToOneRingBufferTest::default();
+        let first = [1_u8; 8];
+        let second = [2_u8; 20];
+        let first_aligned = bit_util::align(
+            first.len() as i32 + RecordDescriptor::HEADER_LENGTH,
+            RecordDescriptor::ALIGNMENT,
+        );
+        let second_aligned = bit_util::align(
+            second.len() as i32 + RecordDescriptor::HEADER_LENGTH,
+            RecordDescriptor::ALIGNMENT,
+        );
+
+        assert!(context
+            .ring_buffer
+            .write_batch(&[(MSG_TYPE_ID, &first[..]), (MSG_TYPE_ID + 1, &second[..])]));
+
+        assert_eq!(
+            context.ab.get::<i64>(TAIL_COUNTER_INDEX),
+            (first_aligned + second_aligned) as i64
+        );
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::type_offset(first_aligned)),
+            MSG_TYPE_ID + 1
+        );
+
+        let mut received = Vec::new();
+        let messages_read = context.ring_buffer.read(
+            |msg_type_id, buffer, index, length| {
+                let mut bytes = vec![0_u8; length as usize];
+                buffer.get_bytes(index, &mut bytes);
+                received.push((msg_type_id, bytes));
+            },
+            u32::MAX,
+        );
+
+        assert_eq!(messages_read, 2);
+        assert_eq!(
+            received,
+            vec![(MSG_TYPE_ID, first.to_vec()), (MSG_TYPE_ID + 1, second.to_vec())]
+        );
+    }
+
+    #[test]
+    fn should_reject_whole_batch_when_insufficient_space() {
+        let context = OneToOneRingBufferTest::default();
+        let msg = [0_u8; 56];
+        let tail = (CAPACITY - 120) as i64;
+
+        context.ab.put_i64(HEAD_COUNTER_INDEX, 0);
+        context.ab.put_i64(TAIL_COUNTER_INDEX, tail);
+
+        assert!(!context
+            .ring_buffer
+            .write_batch(&[(MSG_TYPE_ID, &msg[..]), (MSG_TYPE_ID, &msg[..])]));
+
+        assert_eq!(context.ab.get_i64(TAIL_COUNTER_INDEX), tail);
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::length_offset(tail as i32)),
+            0
+        );
+    }
+
+    #[test]
+    fn should_insert_padding_record_before_batch_on_buffer_wrap() {
+        let context = OneToOneRingBufferTest::default();
+        let msg = [0_u8; 8];
+        let aligned_record_length = bit_util::align(
+            msg.len() as i32 + RecordDescriptor::HEADER_LENGTH,
+            RecordDescriptor::ALIGNMENT,
+        );
+        let tail = CAPACITY - RecordDescriptor::ALIGNMENT;
+        let head = tail - (RecordDescriptor::ALIGNMENT * 4);
+
+        context.ab.put_i64(HEAD_COUNTER_INDEX, head as i64);
+        context.ab.put_i64(TAIL_COUNTER_INDEX, tail as i64);
+
+        assert!(context
+            .ring_buffer
+            .write_batch(&[(MSG_TYPE_ID, &msg[..]), (MSG_TYPE_ID, &msg[..])]));
+
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::type_offset(tail)),
+            RecordDescriptor::PADDING_MSG_TYPE_ID
+        );
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::type_offset(0)),
+            MSG_TYPE_ID
+        );
+        assert_eq!(
+            context
+                .ab
+                .get::<i32>(RecordDescriptor::type_offset(aligned_record_length)),
+            MSG_TYPE_ID
+        );
+        assert_eq!(
+            context.ab.get::<i32>(TAIL_COUNTER_INDEX),
+            tail + RecordDescriptor::ALIGNMENT + aligned_record_length * 2
+        );
+    }
+
+    #[test]
+    fn should_write_batch_of_max_batch_length_wherever_the_tail_is() {
+        let first = [1_u8; 64];
+        let second = [2_u8; 56];
+        let batch = [(MSG_TYPE_ID, &first[..]), (MSG_TYPE_ID, &second[..])];
+
+        for tail in (0..CAPACITY as i64 * 2).step_by(RecordDescriptor::ALIGNMENT as usize) {
+            let context = OneToOneRingBufferTest::default();
+            assert_eq!(context.ring_buffer.max_batch_length(), 136);
+            context.ab.put_i64(HEAD_COUNTER_INDEX, tail);
+            context.ab.put_i64(TAIL_COUNTER_INDEX, tail);
+
+            assert!(context.ring_buffer.write_batch(&batch), "tail={}", tail);
+
+            // a batch after padding is read by a second read from the start of the buffer
+            let messages_read = context.ring_buffer.read(|_, _, _, _| {}, u32::MAX)
+                + context.ring_buffer.read(|_, _, _, _| {}, u32::MAX);
+            assert_eq!(messages_read, 2, "tail={}", tail);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "batch exceeds maxBatchLength of 136 length=144")]
+    fn should_panic_when_batch_exceeds_capacity() {
+        let context = OneToOneRingBufferTest::default();
+        let msg = [0_u8; 64];
+
+        context.ring_buffer.write_batch(&[(MSG_TYPE_ID, &msg[..]); 2]);
+    }
+
     #[test]
     fn should_read_nothing_from_empty_buffer() {
         let context = OneToOneRingBufferTest::default();
@@ -461,7 +1012,7 @@ mod tests {
         let mut times_called = 0;
         let messages_read = context
             .ring_buffer
-            .read(|_, _, _, _| times_called += 1, u32::max_value());
+            .read(|_, _, _, _| times_called += 1, u32::MAX);
 
         assert_eq!(messages_read, 0);
         assert_eq!(times_called, 0);
@@ -489,7 +1040,7 @@ mod tests {
         let mut times_called = 0;
         let messages_read = context
             .ring_buffer
-            .read(|_, _, _, _| times_called += 1, u32::max_value());
+            .read(|_, _, _, _| times_called += 1, u32::MAX);
         assert_eq!(messages_read, 1);
         assert_eq!(times_called, 1);
         assert_eq!(
@@ -508,6 +1059,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn should_pass_handler_a_view_of_only_the_message() {
+        let context = OneToOneRingBufferTest::default();
+        let length = 12;
+        context.src_ab.put_slice(0, b"first");
+        context.src_ab.put_slice(16, b"second");
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, length));
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 16, length));
+
+        let mut messages = vec![];
+        let messages_read = context.ring_buffer.read(
+            |_, buffer, index, length| {
+                let mut msg = vec![0_u8; length as usize];
+                buffer.get_bytes(index, &mut msg);
+                messages.push((buffer.capacity(), index, msg));
+            },
+            u32::MAX,
+        );
+
+        assert_eq!(messages_read, 2);
+        assert_eq!(messages[0].0, length);
+        assert_eq!(messages[0].1, 0);
+        assert_eq!(&messages[0].2[..5], b"first");
+        assert_eq!(messages[1].0, length);
+        assert_eq!(messages[1].1, 0);
+        assert_eq!(&messages[1].2[..6], b"second");
+    }
+
     #[test]
     fn should_not_read_single_message_part_way_through_writing() {
         let context = OneToOneRingBufferTest::default();
@@ -528,7 +1112,7 @@ mod tests {
         let mut times_called = 0;
         let messages_read = context
             .ring_buffer
-            .read(|_, _, _, _| times_called += 1, u32::max_value());
+            .read(|_, _, _, _| times_called += 1, u32::MAX);
 
         assert_eq!(messages_read, 0);
         assert_eq!(times_called, 0);
@@ -555,18 +1139,18 @@ mod tests {
             .put::<i32>(RecordDescriptor::length_offset(0), record_length);
 
         context.ab.put::<i32>(
-            RecordDescriptor::type_offset(0 + aligned_record_length),
+            RecordDescriptor::type_offset(aligned_record_length),
             MSG_TYPE_ID,
         );
         context.ab.put::<i32>(
-            RecordDescriptor::length_offset(0 + aligned_record_length),
+            RecordDescriptor::length_offset(aligned_record_length),
             record_length,
         );
 
         let mut times_called = 0;
         let messages_read = context
             .ring_buffer
-            .read(|_, _, _, _| times_called += 1, u32::max_value());
+            .read(|_, _, _, _| times_called += 1, u32::MAX);
 
         assert_eq!(messages_read, 2);
         assert_eq!(times_called, 2);
@@ -606,11 +1190,11 @@ mod tests {
             .put::<i32>(RecordDescriptor::length_offset(0), record_length);
 
         context.ab.put::<i32>(
-            RecordDescriptor::type_offset(0 + aligned_record_length),
+            RecordDescriptor::type_offset(aligned_record_length),
             MSG_TYPE_ID,
         );
         context.ab.put::<i32>(
-            RecordDescriptor::length_offset(0 + aligned_record_length),
+            RecordDescriptor::length_offset(aligned_record_length),
             record_length,
         );
 
@@ -661,11 +1245,11 @@ mod tests {
             .put::<i32>(RecordDescriptor::length_offset(0), record_length);
 
         context.ab.put::<i32>(
-            RecordDescriptor::type_offset(0 + aligned_record_length),
+            RecordDescriptor::type_offset(aligned_record_length),
             MSG_TYPE_ID,
         );
         context.ab.put::<i32>(
-            RecordDescriptor::length_offset(0 + aligned_record_length),
+            RecordDescriptor::length_offset(aligned_record_length),
             record_length,
         );
 
@@ -679,7 +1263,7 @@ mod tests {
                         panic!("expected exception")
                     }
                 },
-                u32::max_value(),
+                u32::MAX,
             );
         }));
 
@@ -701,12 +1285,184 @@ mod tests {
         }
     }
 
+    #[test]
+    fn should_claim_and_commit_message() {
+        let context = OneToOneRingBufferTest::default();
+        let length = 8;
+        let record_length = length + RecordDescriptor::HEADER_LENGTH;
+
+        let index = context.ring_buffer.try_claim(MSG_TYPE_ID, length);
+
+        assert_eq!(index, RecordDescriptor::HEADER_LENGTH);
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::length_offset(0)),
+            -record_length
+        );
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::type_offset(0)),
+            MSG_TYPE_ID
+        );
+
+        context.ring_buffer.buffer().put_i64(index, 42);
+        context.ring_buffer.commit(index);
+
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::length_offset(0)),
+            record_length
+        );
+
+        let mut values = vec![];
+        let messages_read = context.ring_buffer.read(
+            |msg_type_id, buffer, index, length| {
+                values.push((msg_type_id, buffer.get_i64(index), length));
+            },
+            u32::MAX,
+        );
+
+        assert_eq!(messages_read, 1);
+        assert_eq!(values, vec![(MSG_TYPE_ID, 42, length)]);
+    }
+
+    #[test]
+    fn should_abort_claimed_message() {
+        let context = OneToOneRingBufferTest::default();
+        let length = 8;
+        let record_length = length + RecordDescriptor::HEADER_LENGTH;
+
+        let index = context.ring_buffer.try_claim(MSG_TYPE_ID, length);
+        context.ring_buffer.abort(index);
+
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::length_offset(0)),
+            record_length
+        );
+        assert_eq!(
+            context.ab.get::<i32>(RecordDescriptor::type_offset(0)),
+            RecordDescriptor::PADDING_MSG_TYPE_ID
+        );
+
+        let mut times_called = 0;
+        let messages_read = context
+            .ring_buffer
+            .read(|_, _, _, _| times_called += 1, u32::MAX);
+
+        assert_eq!(messages_read, 0);
+        assert_eq!(times_called, 0);
+        assert_eq!(
+            context.ring_buffer.consumer_position(),
+            bit_util::align(record_length, RecordDescriptor::ALIGNMENT) as i64
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "claimed space previously committed")]
+    fn should_panic_when_committing_twice() {
+        let context = OneToOneRingBufferTest::default();
+        let index = context.ring_buffer.try_claim(MSG_TYPE_ID, 8);
+
+        context.ring_buffer.commit(index);
+        context.ring_buffer.commit(index);
+    }
+
+    #[test]
+    #[should_panic(expected = "claimed space previously aborted")]
+    fn should_panic_when_committing_aborted_claim() {
+        let context = OneToOneRingBufferTest::default();
+        let index = context.ring_buffer.try_claim(MSG_TYPE_ID, 8);
+
+        context.ring_buffer.abort(index);
+        context.ring_buffer.commit(index);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid message index 4")]
+    fn should_panic_when_committing_invalid_index() {
+        let context = OneToOneRingBufferTest::default();
+
+        context.ring_buffer.commit(4);
+    }
+
+    #[test]
+    fn should_return_insufficient_capacity_when_claim_does_not_fit() {
+        let context = OneToOneRingBufferTest::default();
+
+        context.ab.put_i64(TAIL_COUNTER_INDEX, CAPACITY as i64);
+
+        assert_eq!(
+            context.ring_buffer.try_claim(MSG_TYPE_ID, 8),
+            OneToOneRingBuffer::INSUFFICIENT_CAPACITY
+        );
+        assert_eq!(context.ab.get_i64(TAIL_COUNTER_INDEX), CAPACITY as i64);
+    }
+
+    #[test]
+    fn should_commit_and_abort_in_controlled_read() {
+        let context = OneToOneRingBufferTest::default();
+        let length = 8;
+        let record_length = length + RecordDescriptor::HEADER_LENGTH;
+        let aligned_record_length = bit_util::align(record_length, RecordDescriptor::ALIGNMENT);
+
+        for _ in 0..3 {
+            assert!(context
+                .ring_buffer
+                .write(MSG_TYPE_ID, &context.src_ab, 0, length));
+        }
+
+        let mut times_called = 0;
+        let messages_read = context.ring_buffer.controlled_read(
+            |_, _, _, _| {
+                times_called += 1;
+                match times_called {
+                    1 => ControlledAction::Commit,
+                    2 => ControlledAction::Continue,
+                    _ => ControlledAction::Abort,
+                }
+            },
+            u32::MAX,
+        );
+
+        assert_eq!(messages_read, 2);
+        assert_eq!(times_called, 3);
+        assert_eq!(
+            context.ab.get::<i64>(HEAD_COUNTER_INDEX),
+            (aligned_record_length * 2) as i64
+        );
+        for i in (0..aligned_record_length * 2).step_by(4) {
+            assert_eq!(context.ab.get::<i32>(i), 0);
+        }
+
+        let messages_read = context
+            .ring_buffer
+            .controlled_read(|_, _, _, _| ControlledAction::Break, u32::MAX);
+
+        assert_eq!(messages_read, 1);
+        assert_eq!(
+            context.ab.get::<i64>(HEAD_COUNTER_INDEX),
+            (aligned_record_length * 3) as i64
+        );
+    }
+
+    #[test]
+    fn should_set_consumer_heartbeat_time() {
+        let context = OneToOneRingBufferTest::default();
+
+        context.ring_buffer.set_consumer_heartbeat_time(1234);
+
+        assert_eq!(context.ring_buffer.consumer_heartbeat_time(), 1234);
+        assert_eq!(
+            context
+                .ab
+                .get_i64(CAPACITY + RingBufferDescriptor::CONSUMER_HEARTBEAT_OFFSET),
+            1234
+        );
+    }
+
     const NUM_MESSAGES: i32 = 10 * 1000 * 1000;
     const NUM_IDS_PER_THREAD: i32 = 10 * 1000 * 1000;
 
     #[test]
     fn should_provide_correlation_ids() {
-        let mut spsc_buffer = Align16::new([0 as u8; BUFFER_SZ]);
+        let mut spsc_buffer = Align16::new([0_u8; BUFFER_SZ]);
         let spsc_ab = AtomicBuffer::wrap(&mut *spsc_buffer);
         let ring_buffer = Arc::new(OneToOneRingBuffer::new(spsc_ab));
 
@@ -741,13 +1497,13 @@ mod tests {
 
     #[test]
     fn should_exchange_messages() {
-        let mut spsc_buffer = Align16::new([0 as u8; BUFFER_SZ]);
+        let mut spsc_buffer = Align16::new([0_u8; BUFFER_SZ]);
         let spsc_ab = AtomicBuffer::wrap(&mut *spsc_buffer);
         let ring_buffer = Arc::new(OneToOneRingBuffer::new(spsc_ab));
 
         let rb = ring_buffer.clone();
         let thread = thread::spawn(move || {
-            let mut src_buffer = Align16::new([0 as u8; BUFFER_SZ]);
+            let mut src_buffer = Align16::new([0_u8; BUFFER_SZ]);
             let src_ab = AtomicBuffer::wrap(&mut *src_buffer);
 
             for m in 0..NUM_IDS_PER_THREAD {
@@ -772,7 +1528,7 @@ mod tests {
                     assert_eq!(counts, message_number);
                     counts += 1;
                 },
-                u32::max_value(),
+                u32::MAX,
             );
 
             if 0 == read_count {
@@ -0,0 +1,275 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::*;
+
+/// Snapshot of the counters of an instrumented ring buffer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RingBufferStats {
+    /// Messages written or committed.
+    pub writes: i64,
+    /// Writes and claims rejected for insufficient capacity, a batch counting once.
+    pub failed_writes: i64,
+    /// Message bytes written or committed, excluding headers and alignment.
+    pub bytes_written: i64,
+    /// Padding records inserted to wrap to the start of the buffer.
+    pub padding_records: i64,
+    /// Reads which returned at least one message.
+    pub read_batches: i64,
+    /// Messages passed to handlers by reads.
+    pub messages_read: i64,
+    /// Calls to `unblock` which released a blocked record.
+    pub unblocks: i64,
+}
+
+impl RingBufferStats {
+    /// The average number of messages returned by reads which returned any.
+    pub fn messages_per_read(&self) -> f64 {
+        if self.read_batches == 0 {
+            0.0
+        } else {
+            self.messages_read as f64 / self.read_batches as f64
+        }
+    }
+}
+
+/// Counters instrumenting a ring buffer, held in an [`AtomicBuffer`] of
+/// [`LENGTH`](RingBufferCounters::LENGTH) bytes.
+///
+/// The buffer can be a memory-mapped file so that external tools can watch the counters by
+/// wrapping the same region and taking a [`snapshot`](RingBufferCounters::snapshot). Producer
+/// and consumer counters are on separate cache lines so they do not contend. Counters are added
+/// to atomically, so several ring buffers can share them to count their totals.
+#[derive(Clone, Copy)]
+pub struct RingBufferCounters {
+    buffer: AtomicBuffer,
+}
+
+impl RingBufferCounters {
+    pub const WRITES_OFFSET: Index = 0;
+    pub const FAILED_WRITES_OFFSET: Index = 8;
+    pub const BYTES_WRITTEN_OFFSET: Index = 16;
+    pub const PADDING_RECORDS_OFFSET: Index = 24;
+    pub const READ_BATCHES_OFFSET: Index = CACHE_LINE_LENGTH * 2;
+    pub const MESSAGES_READ_OFFSET: Index = CACHE_LINE_LENGTH * 2 + 8;
+    pub const UNBLOCKS_OFFSET: Index = CACHE_LINE_LENGTH * 2 + 16;
+
+    /// Total length of the counters in bytes.
+    pub const LENGTH: Index = CACHE_LINE_LENGTH * 4;
+
+    pub fn new(buffer: AtomicBuffer) -> RingBufferCounters {
+        if buffer.capacity() < Self::LENGTH {
+            panic!(
+                "buffer too small for counters: capacity={} required={}",
+                buffer.capacity(),
+                Self::LENGTH
+            )
+        }
+
+        RingBufferCounters { buffer }
+    }
+
+    pub fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
+    pub fn snapshot(&self) -> RingBufferStats {
+        RingBufferStats {
+            writes: self.buffer.get_i64_volatile(Self::WRITES_OFFSET),
+            failed_writes: self.buffer.get_i64_volatile(Self::FAILED_WRITES_OFFSET),
+            bytes_written: self.buffer.get_i64_volatile(Self::BYTES_WRITTEN_OFFSET),
+            padding_records: self.buffer.get_i64_volatile(Self::PADDING_RECORDS_OFFSET),
+            read_batches: self.buffer.get_i64_volatile(Self::READ_BATCHES_OFFSET),
+            messages_read: self.buffer.get_i64_volatile(Self::MESSAGES_READ_OFFSET),
+            unblocks: self.buffer.get_i64_volatile(Self::UNBLOCKS_OFFSET),
+        }
+    }
+
+    /// Add to a counter atomically, as the counters may be shared by several ring buffers.
+    #[inline]
+    pub(crate) fn add(&self, offset: Index, delta: i64) {
+        self.buffer.get_and_add_i64(offset, delta);
+    }
+
+    #[inline]
+    pub(crate) fn record_read(&self, messages_read: u32) {
+        if messages_read > 0 {
+            self.add(Self::READ_BATCHES_OFFSET, 1);
+            self.add(Self::MESSAGES_READ_OFFSET, messages_read as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::{Align64, AlignedBox};
+
+    const CAPACITY: usize = 1024;
+    const BUFFER_SZ: usize = CAPACITY + RingBufferDescriptor::TRAILER_LENGTH as usize;
+    const MSG_TYPE_ID: i32 = 7;
+
+    struct RingBufferStatsTest {
+        _buffer: Box<Align64<[u8; BUFFER_SZ]>>,
+        _counters_buffer: AlignedBox<[u8]>,
+        counters: RingBufferCounters,
+        ab: AtomicBuffer,
+    }
+
+    impl Default for RingBufferStatsTest {
+        fn default() -> Self {
+            let mut buffer = Box::new(Align64::new([0_u8; BUFFER_SZ]));
+            let mut counters_buffer = AlignedBox::<[u8]>::zeroed(
+                RingBufferCounters::LENGTH as usize,
+                CACHE_LINE_LENGTH as usize,
+            );
+            let ab = AtomicBuffer::wrap(&mut **buffer);
+            let counters = RingBufferCounters::new(AtomicBuffer::wrap(&mut counters_buffer));
+
+            RingBufferStatsTest {
+                _buffer: buffer,
+                _counters_buffer: counters_buffer,
+                counters,
+                ab,
+            }
+        }
+    }
+
+    fn exercise<R: RingBuffer>(ring_buffer: &R) {
+        let msg = [0_u8; 100];
+
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 100));
+        assert!(ring_buffer.write_batch(&[(MSG_TYPE_ID, &msg[..8]), (MSG_TYPE_ID, &msg[..8])]));
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, 2), 2);
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, 2), 1);
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, 2), 0);
+
+        while ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 100) {}
+        ring_buffer.read(|_, _, _, _| {}, u32::MAX);
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 100));
+    }
+
+    #[test]
+    fn should_count_one_to_one_writes_and_reads() {
+        let context = RingBufferStatsTest::default();
+        let mut ring_buffer = OneToOneRingBuffer::new(context.ab);
+        ring_buffer.set_counters(context.counters);
+
+        exercise(&ring_buffer);
+
+        let stats = ring_buffer.stats().unwrap();
+        assert_eq!(stats, context.counters.snapshot());
+        assert_eq!(stats.failed_writes, 1);
+        assert_eq!(stats.writes, 3 + 8 + 1);
+        assert_eq!(stats.bytes_written, 100 + 16 + 8 * 100 + 100);
+        assert_eq!(stats.padding_records, 1);
+        assert_eq!(stats.read_batches, 3);
+        assert_eq!(stats.messages_read, 3 + 7);
+        assert_eq!(stats.messages_per_read(), 10.0 / 3.0);
+        assert_eq!(stats.unblocks, 0);
+    }
+
+    #[test]
+    fn should_count_many_to_one_writes_reads_and_unblocks() {
+        let context = RingBufferStatsTest::default();
+        let mut ring_buffer = ManyToOneRingBuffer::new(context.ab);
+        ring_buffer.set_counters(context.counters);
+
+        exercise(&ring_buffer);
+        ring_buffer.read(|_, _, _, _| {}, u32::MAX);
+        ring_buffer.try_claim(MSG_TYPE_ID, 8);
+        assert!(ring_buffer.unblock());
+
+        let stats = ring_buffer.stats().unwrap();
+        assert_eq!(stats.failed_writes, 1);
+        assert_eq!(stats.writes, 3 + 8 + 1);
+        assert_eq!(stats.bytes_written, 100 + 16 + 8 * 100 + 100);
+        assert_eq!(stats.padding_records, 1);
+        assert_eq!(stats.read_batches, 4);
+        assert_eq!(stats.messages_read, 3 + 7 + 2);
+        assert_eq!(stats.unblocks, 1);
+    }
+
+    fn claim_commit_and_abort<R: RingBuffer>(ring_buffer: &R) {
+        let index = ring_buffer.try_claim(MSG_TYPE_ID, 20);
+        assert_eq!(ring_buffer.stats().unwrap().writes, 0);
+        ring_buffer.commit(index);
+
+        let index = ring_buffer.try_claim(MSG_TYPE_ID, 40);
+        ring_buffer.abort(index);
+
+        let stats = ring_buffer.stats().unwrap();
+        assert_eq!(stats.writes, 1);
+        assert_eq!(stats.bytes_written, 20);
+        assert_eq!(stats.failed_writes, 0);
+    }
+
+    #[test]
+    fn should_count_one_to_one_claims_on_commit_only() {
+        let context = RingBufferStatsTest::default();
+        let mut ring_buffer = OneToOneRingBuffer::new(context.ab);
+        ring_buffer.set_counters(context.counters);
+
+        claim_commit_and_abort(&ring_buffer);
+    }
+
+    #[test]
+    fn should_count_many_to_one_claims_on_commit_only() {
+        let context = RingBufferStatsTest::default();
+        let mut ring_buffer = ManyToOneRingBuffer::new(context.ab);
+        ring_buffer.set_counters(context.counters);
+
+        claim_commit_and_abort(&ring_buffer);
+    }
+
+    #[test]
+    fn should_total_counts_of_ring_buffers_sharing_counters() {
+        let context = RingBufferStatsTest::default();
+        let mut other_buffer = Box::new(Align64::new([0_u8; BUFFER_SZ]));
+        let mut one_to_one = OneToOneRingBuffer::new(context.ab);
+        let mut many_to_one = ManyToOneRingBuffer::new(AtomicBuffer::wrap(&mut **other_buffer));
+        one_to_one.set_counters(context.counters);
+        many_to_one.set_counters(context.counters);
+
+        exercise(&one_to_one);
+        exercise(&many_to_one);
+
+        let stats = context.counters.snapshot();
+        assert_eq!(stats.writes, 2 * (3 + 8 + 1));
+        assert_eq!(stats.failed_writes, 2);
+        assert_eq!(stats.bytes_written, 2 * (100 + 16 + 8 * 100 + 100));
+        assert_eq!(stats.read_batches, 2 * 3);
+        assert_eq!(stats.messages_read, 2 * (3 + 7));
+    }
+
+    #[test]
+    fn should_not_report_stats_without_counters() {
+        let context = RingBufferStatsTest::default();
+        let ring_buffer = OneToOneRingBuffer::new(context.ab);
+
+        exercise(&ring_buffer);
+
+        assert_eq!(ring_buffer.stats(), None);
+        assert_eq!(context.counters.snapshot(), RingBufferStats::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer too small for counters: capacity=64 required=256")]
+    fn should_panic_when_counters_buffer_too_small() {
+        let mut bytes = Align64::new([0_u8; 64]);
+        RingBufferCounters::new(AtomicBuffer::wrap(&mut *bytes));
+    }
+}
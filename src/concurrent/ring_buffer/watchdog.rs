@@ -0,0 +1,238 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/aeron/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::RingBuffer;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
+
+/// Details of a record unblocked by an [`UnblockWatchdog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnblockEvent {
+    pub consumer_position: i64,
+    pub producer_position: i64,
+    /// How long the consumer position had not moved before the record was unblocked.
+    pub stalled_for: Duration,
+}
+
+/// Unblocks a ring buffer left stuck by a producer which died between claiming capacity and
+/// committing its record.
+///
+/// The watchdog is polled from a duty cycle, typically the consumer's own. When the consumer
+/// position has not moved for longer than the timeout while the producer position is ahead of it,
+/// [`RingBuffer::unblock`] is called and, if it succeeds, the handler is notified. This is the same
+/// check the Aeron media driver conductor applies to its to-driver ring buffer.
+///
+/// The timeout should comfortably exceed the longest time a live producer can take to write a
+/// record, otherwise a slow producer will have its record turned into padding.
+pub struct UnblockWatchdog<R, H>
+where
+    R: Deref,
+    R::Target: RingBuffer,
+    H: FnMut(UnblockEvent),
+{
+    ring_buffer: R,
+    timeout: Duration,
+    handler: H,
+    consumer_position: i64,
+    time_of_last_position_change: Instant,
+    unblocked_count: u64,
+}
+
+impl<R, H> UnblockWatchdog<R, H>
+where
+    R: Deref,
+    R::Target: RingBuffer,
+    H: FnMut(UnblockEvent),
+{
+    const NULL_POSITION: i64 = -1;
+
+    pub fn new(ring_buffer: R, timeout: Duration, handler: H) -> UnblockWatchdog<R, H> {
+        UnblockWatchdog {
+            ring_buffer,
+            timeout,
+            handler,
+            consumer_position: Self::NULL_POSITION,
+            time_of_last_position_change: Instant::now(),
+            unblocked_count: 0,
+        }
+    }
+
+    /// Check for progress of the consumer and unblock the ring buffer if it has stalled.
+    ///
+    /// The first poll establishes the baseline the timeout is measured from. Returns true if a
+    /// record was unblocked.
+    pub fn poll(&mut self, now: Instant) -> bool {
+        let consumer_position = self.ring_buffer.consumer_position();
+        let producer_position = self.ring_buffer.producer_position();
+
+        if consumer_position != self.consumer_position || producer_position <= consumer_position {
+            self.consumer_position = consumer_position;
+            self.time_of_last_position_change = now;
+            return false;
+        }
+
+        let stalled_for = now.saturating_duration_since(self.time_of_last_position_change);
+        if stalled_for <= self.timeout || !self.ring_buffer.unblock() {
+            return false;
+        }
+
+        self.unblocked_count += 1;
+        (self.handler)(UnblockEvent {
+            consumer_position,
+            producer_position,
+            stalled_for,
+        });
+
+        true
+    }
+
+    /// Number of times a record has been unblocked by this watchdog.
+    pub fn unblocked_count(&self) -> u64 {
+        self.unblocked_count
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn ring_buffer(&self) -> &R::Target {
+        &self.ring_buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{
+        ManyToOneRingBuffer, RecordDescriptor, RingBufferDescriptor,
+    };
+    use crate::concurrent::AtomicBuffer;
+    use crate::mem::AlignedBox;
+    use crate::util::Index;
+
+    const CAPACITY: i32 = 1024;
+    const BUFFER_SZ: usize = (CAPACITY + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    const MSG_TYPE_ID: i32 = 101;
+    const TAIL_COUNTER_INDEX: Index = CAPACITY + RingBufferDescriptor::TAIL_POSITION_OFFSET;
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    struct UnblockWatchdogTest {
+        _buffer: AlignedBox<[u8]>,
+        _src_buffer: AlignedBox<[u8]>,
+        ab: AtomicBuffer,
+        src_ab: AtomicBuffer,
+        ring_buffer: ManyToOneRingBuffer,
+    }
+
+    impl Default for UnblockWatchdogTest {
+        fn default() -> Self {
+            let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let mut src_buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+            let ab = AtomicBuffer::wrap(&mut buffer);
+            let src_ab = AtomicBuffer::wrap(&mut src_buffer);
+
+            UnblockWatchdogTest {
+                _buffer: buffer,
+                _src_buffer: src_buffer,
+                ab,
+                src_ab,
+                ring_buffer: ManyToOneRingBuffer::new(ab),
+            }
+        }
+    }
+
+    #[test]
+    fn should_not_unblock_empty_buffer() {
+        let context = UnblockWatchdogTest::default();
+        let mut events = vec![];
+        let mut watchdog = UnblockWatchdog::new(&context.ring_buffer, TIMEOUT, |e| events.push(e));
+        let start = Instant::now();
+
+        assert!(!watchdog.poll(start));
+        assert!(!watchdog.poll(start + TIMEOUT * 2));
+        assert_eq!(watchdog.unblocked_count(), 0);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn should_not_unblock_before_timeout() {
+        let context = UnblockWatchdogTest::default();
+        let mut watchdog = UnblockWatchdog::new(&context.ring_buffer, TIMEOUT, |_| {});
+        let start = Instant::now();
+
+        context.ab.put_i64(TAIL_COUNTER_INDEX, 16);
+
+        assert!(!watchdog.poll(start));
+        assert!(!watchdog.poll(start + TIMEOUT));
+        assert_eq!(watchdog.unblocked_count(), 0);
+    }
+
+    #[test]
+    fn should_unblock_stalled_consumer_after_timeout() {
+        let context = UnblockWatchdogTest::default();
+        let mut events = vec![];
+        let mut watchdog = UnblockWatchdog::new(&context.ring_buffer, TIMEOUT, |e| events.push(e));
+        let start = Instant::now();
+
+        context
+            .ab
+            .put_i64(0, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+        context.ab.put_i64(TAIL_COUNTER_INDEX, 16);
+
+        assert!(!watchdog.poll(start));
+        assert!(watchdog.poll(start + TIMEOUT + Duration::from_millis(1)));
+        assert_eq!(watchdog.unblocked_count(), 1);
+
+        assert_eq!(
+            events,
+            vec![UnblockEvent {
+                consumer_position: 0,
+                producer_position: 16,
+                stalled_for: TIMEOUT + Duration::from_millis(1),
+            }]
+        );
+        assert_eq!(context.ring_buffer.read(|_, _, _, _| {}, u32::MAX), 0);
+        assert_eq!(context.ring_buffer.consumer_position(), 16);
+    }
+
+    #[test]
+    fn should_restart_timeout_when_consumer_makes_progress() {
+        let context = UnblockWatchdogTest::default();
+        let mut watchdog = UnblockWatchdog::new(&context.ring_buffer, TIMEOUT, |_| {});
+        let start = Instant::now();
+
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        assert!(context
+            .ring_buffer
+            .write(MSG_TYPE_ID, &context.src_ab, 0, 8));
+        context
+            .ab
+            .put_i64(32, RecordDescriptor::make_header(-16, MSG_TYPE_ID));
+        context.ab.put_i64(TAIL_COUNTER_INDEX, 48);
+
+        assert!(!watchdog.poll(start));
+        assert_eq!(context.ring_buffer.read(|_, _, _, _| {}, u32::MAX), 2);
+        assert!(!watchdog.poll(start + TIMEOUT * 2));
+        assert!(!watchdog.poll(start + TIMEOUT * 3));
+        assert!(watchdog.poll(start + TIMEOUT * 4));
+        assert_eq!(watchdog.unblocked_count(), 1);
+    }
+}
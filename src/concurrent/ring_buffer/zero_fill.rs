@@ -0,0 +1,478 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::*;
+use crate::concurrent::atomic;
+
+/// How the consumer of a ring buffer zeroes the records it has read before releasing the space
+/// to producers.
+///
+/// Producers rely on released space reading as zero where a record header may be written, so
+/// that the consumer stops at records which are claimed but not yet written.
+///
+/// Java producers using Agrona's ring buffers only read the head and the zeroed space behind it,
+/// so `Eager` and `Batched` work with Agrona producers of either kind, and `HeadersOnly` with
+/// Agrona's `OneToOneRingBuffer` producer for the reasons given below. Only `Eager` leaves a
+/// buffer exactly as an Agrona consumer would, so an Agrona consumer can take over from it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ZeroFill {
+    /// Zero all bytes read before moving the head on each read, as Agrona does. Compatible with
+    /// any producer, and writes nothing Agrona does not define.
+    #[default]
+    Eager,
+    /// Keep consumed bytes until at least `chunk_length` have been read, the read reaches the
+    /// end of the buffer, or a read finds no message, then zero them and move the head in one
+    /// go. Compatible with any producer, at the cost of releasing space later. `chunk_length` must
+    /// be at most a quarter of the capacity so producers can always make progress.
+    ///
+    /// The position the consumer has read up to is published at
+    /// [`CONSUMER_POSITION_OFFSET`](RingBufferDescriptor::CONSUMER_POSITION_OFFSET) in the
+    /// trailer before bytes are held back or zeroed, so [`unblock`](RingBuffer::unblock) and a
+    /// restarted consumer carry on from there rather than from the head. An `Eager` consumer, or
+    /// an Agrona consumer, taking over starts from the head and so reads the held back messages
+    /// again.
+    Batched { chunk_length: Index },
+    /// Zero only the header of each record read, publishing the consumer position first as for
+    /// `Batched`. Only for a [`OneToOneRingBuffer`], whose producer, like Agrona's
+    /// `OneToOneRingBuffer`, zeroes the header following each record it claims, so stale message
+    /// bytes are never read as a header. Not compatible with producers that rely on the consumer
+    /// zeroing everything, such as a `ManyToOneRingBuffer` or Agrona's.
+    HeadersOnly,
+}
+
+/// Consumer side of a [`ZeroFill`] strategy.
+pub(crate) struct ZeroFiller {
+    strategy: ZeroFill,
+    capacity: Index,
+    head_position_index: Index,
+    consumer_position_index: Index,
+}
+
+impl ZeroFiller {
+    pub(crate) fn new(strategy: ZeroFill, capacity: Index) -> ZeroFiller {
+        if let ZeroFill::Batched { chunk_length } = strategy {
+            if chunk_length <= 0 || chunk_length > capacity / 4 {
+                panic!(
+                    "chunkLength must be positive and at most a quarter of capacity: chunkLength={} capacity={}",
+                    chunk_length, capacity
+                )
+            }
+        }
+
+        ZeroFiller {
+            strategy,
+            capacity,
+            head_position_index: capacity + RingBufferDescriptor::HEAD_POSITION_OFFSET,
+            consumer_position_index: capacity + RingBufferDescriptor::CONSUMER_POSITION_OFFSET,
+        }
+    }
+
+    /// The position the consumer has read up to given the `head` position, which is behind it by
+    /// the bytes a batched consumer has not yet released.
+    ///
+    /// The published consumer position is only ahead of the head while bytes are held back or
+    /// being zeroed, so this holds whichever strategy the consumer used, including one that has
+    /// since restarted. Observers which do not know the consumer's strategy use this.
+    #[inline]
+    pub(crate) fn consumer_position(&self, buffer: &AtomicBuffer, head: i64) -> i64 {
+        head.max(buffer.get_i64_volatile(self.consumer_position_index))
+    }
+
+    /// The position this consumer reads from, which for [`ZeroFill::Eager`] is the head as for
+    /// Agrona's consumers, without loading the published consumer position.
+    #[inline]
+    pub(crate) fn read_position(&self, buffer: &AtomicBuffer, head: i64) -> i64 {
+        match self.strategy {
+            ZeroFill::Eager => head,
+            _ => self.consumer_position(buffer, head),
+        }
+    }
+
+    /// Release `read` bytes consumed from `position`, along with any bytes held back between the
+    /// `head` and `position`. Returns the head position after the release.
+    ///
+    /// Other than for [`ZeroFill::Eager`], which writes only what Agrona's consumers write, the
+    /// consumer position is published before any bytes are zeroed, so an observer such as a
+    /// [`RingBufferCursor`] which copied a record and then finds the consumer position has not
+    /// passed it knows the copy is intact.
+    pub(crate) fn release(
+        &self,
+        buffer: &AtomicBuffer,
+        head: i64,
+        position: i64,
+        read: Index,
+    ) -> i64 {
+        let pending = (position - head) as Index;
+        let length = pending + read;
+        if 0 == length {
+            return head;
+        }
+
+        let head_index = (head & (self.capacity - 1) as i64) as Index;
+        let position = position + read as i64;
+
+        match self.strategy {
+            ZeroFill::Eager => buffer.set_memory(head_index, length, 0),
+            ZeroFill::Batched { chunk_length } => {
+                buffer.put_i64_ordered(self.consumer_position_index, position);
+                if read != 0 && length < chunk_length && head_index + length < self.capacity {
+                    return head;
+                }
+
+                atomic::release();
+                buffer.set_memory(head_index, length, 0);
+            }
+            ZeroFill::HeadersOnly => {
+                buffer.put_i64_ordered(self.consumer_position_index, position);
+                atomic::release();
+                let mut record_index = head_index;
+                while record_index < head_index + length {
+                    let record_length =
+                        buffer.get::<i32>(RecordDescriptor::length_offset(record_index));
+                    buffer.put_i64(record_index, 0);
+                    record_index += bit_util::align(record_length, RecordDescriptor::ALIGNMENT);
+                }
+            }
+        }
+
+        buffer.put_i64_ordered(self.head_position_index, position);
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+
+    const CAPACITY: usize = 1024;
+    const BUFFER_SZ: usize = CAPACITY + RingBufferDescriptor::TRAILER_LENGTH as usize;
+    const MSG_TYPE_ID: i32 = 7;
+    const HEAD_COUNTER_INDEX: Index =
+        CAPACITY as Index + RingBufferDescriptor::HEAD_POSITION_OFFSET;
+
+    struct ZeroFillTest {
+        buffer: Box<Align64<[u8; BUFFER_SZ]>>,
+    }
+
+    impl Default for ZeroFillTest {
+        fn default() -> Self {
+            ZeroFillTest {
+                buffer: Box::new(Align64::new([0_u8; BUFFER_SZ])),
+            }
+        }
+    }
+
+    impl ZeroFillTest {
+        fn atomic_buffer(&mut self) -> AtomicBuffer {
+            AtomicBuffer::wrap(&mut **self.buffer)
+        }
+    }
+
+    const MESSAGES: usize = 2_000;
+
+    /// Messages of 1 to 56 bytes, so a pair written as a batch is within the max batch length.
+    fn message(sequence: usize) -> Vec<u8> {
+        let length = 1 + sequence * 5 % 56;
+        (0..length).map(|i| (sequence + i) as u8 | 1).collect()
+    }
+
+    /// Exchange messages of varying length over many laps, some written as batches, checking
+    /// every byte arrives, so stale bytes left by a strategy are never taken for a record.
+    fn exchange_messages<R: RingBuffer>(ring_buffer: &R) {
+        let mut sent = 0;
+        let mut received = 0;
+
+        while received < MESSAGES {
+            while sent < MESSAGES {
+                let first = message(sent);
+                let written = if sent % 3 == 0 && sent + 1 < MESSAGES {
+                    let second = message(sent + 1);
+                    let batch = [(MSG_TYPE_ID, &first[..]), (MSG_TYPE_ID, &second[..])];
+                    if ring_buffer.write_batch(&batch) {
+                        2
+                    } else {
+                        0
+                    }
+                } else if ring_buffer.write(MSG_TYPE_ID, &first[..], 0, first.len() as Index) {
+                    1
+                } else {
+                    0
+                };
+                if written == 0 {
+                    break;
+                }
+                sent += written;
+            }
+
+            let limit = 1 + (received % 5) as u32;
+            ring_buffer.read(
+                |msg_type_id, buffer, index, length| {
+                    let mut bytes = vec![0_u8; length as usize];
+                    buffer.get_bytes(index, &mut bytes);
+                    assert_eq!(msg_type_id, MSG_TYPE_ID);
+                    assert_eq!(bytes, message(received));
+                    received += 1;
+                },
+                limit,
+            );
+        }
+    }
+
+    /// Exchange messages with the producer on another thread, so the consumer races the
+    /// producer publishing records.
+    fn exchange_messages_across_threads<R: RingBuffer + Sync>(ring_buffer: &R, messages: usize) {
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut sent = 0;
+                while sent < messages {
+                    let first = message(sent);
+                    let second = message(sent + 1);
+                    let batch = [(MSG_TYPE_ID, &first[..]), (MSG_TYPE_ID, &second[..])];
+                    if ring_buffer.write_batch(&batch) {
+                        sent += 2;
+                    } else {
+                        std::thread::yield_now();
+                    }
+                }
+            });
+
+            let mut received = 0;
+            while received < messages {
+                let read_count = ring_buffer.read(
+                    |_, buffer, index, length| {
+                        let mut bytes = vec![0_u8; length as usize];
+                        buffer.get_bytes(index, &mut bytes);
+                        assert_eq!(bytes, message(received));
+                        received += 1;
+                    },
+                    u32::MAX,
+                );
+                if read_count == 0 {
+                    std::thread::yield_now();
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn should_exchange_messages_with_one_to_one_producer_for_each_strategy() {
+        for strategy in [
+            ZeroFill::Eager,
+            ZeroFill::Batched { chunk_length: 256 },
+            ZeroFill::HeadersOnly,
+        ] {
+            let mut context = ZeroFillTest::default();
+            let ring_buffer = OneToOneRingBuffer::with_zero_fill(context.atomic_buffer(), strategy);
+
+            exchange_messages(&ring_buffer);
+            exchange_messages_across_threads(&ring_buffer, 200_000);
+        }
+    }
+
+    #[test]
+    fn should_exchange_messages_with_many_to_one_producer_for_full_zeroing_strategies() {
+        for strategy in [ZeroFill::Eager, ZeroFill::Batched { chunk_length: 256 }] {
+            let mut context = ZeroFillTest::default();
+            let ring_buffer =
+                ManyToOneRingBuffer::with_zero_fill(context.atomic_buffer(), strategy);
+
+            exchange_messages(&ring_buffer);
+            exchange_messages_across_threads(&ring_buffer, 200_000);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "HeadersOnly zero fill requires a producer which pre-zeroes headers")]
+    fn should_panic_for_headers_only_with_many_to_one() {
+        let mut context = ZeroFillTest::default();
+        ManyToOneRingBuffer::with_zero_fill(context.atomic_buffer(), ZeroFill::HeadersOnly);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "chunkLength must be positive and at most a quarter of capacity: chunkLength=512 capacity=1024"
+    )]
+    fn should_panic_for_chunk_larger_than_quarter_of_capacity() {
+        let mut context = ZeroFillTest::default();
+        OneToOneRingBuffer::with_zero_fill(
+            context.atomic_buffer(),
+            ZeroFill::Batched { chunk_length: 512 },
+        );
+    }
+
+    #[test]
+    fn should_release_batched_bytes_once_chunk_is_read() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let ring_buffer =
+            OneToOneRingBuffer::with_zero_fill(ab, ZeroFill::Batched { chunk_length: 64 });
+        let msg = [1_u8; 8];
+
+        for _ in 0..4 {
+            assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        }
+
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, 3), 3);
+        assert_eq!(ring_buffer.consumer_position(), 48);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 0);
+        assert_eq!(ab.get::<i32>(RecordDescriptor::length_offset(0)), 16);
+
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+        assert_eq!(ring_buffer.consumer_position(), 64);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 64);
+        for i in (0..64).step_by(8) {
+            assert_eq!(ab.get_i64(i), 0, "not zeroed at index {}", i);
+        }
+    }
+
+    #[test]
+    fn should_release_batched_bytes_when_read_finds_no_message() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let ring_buffer =
+            OneToOneRingBuffer::with_zero_fill(ab, ZeroFill::Batched { chunk_length: 64 });
+        let msg = [1_u8; 8];
+
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 0);
+
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 0);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 16);
+        assert_eq!(ring_buffer.consumer_position(), 16);
+        assert_eq!(ab.get_i64(0), 0);
+    }
+
+    #[test]
+    fn should_unblock_record_behind_batched_bytes() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let ring_buffer =
+            ManyToOneRingBuffer::with_zero_fill(ab, ZeroFill::Batched { chunk_length: 256 });
+        let msg = [1_u8; 8];
+
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        let _dead_producer_claim = ring_buffer.try_claim(MSG_TYPE_ID, 8);
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, 2), 2);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 0);
+        assert_eq!(ring_buffer.consumer_position(), 32);
+
+        let other_process = ManyToOneRingBuffer::new(ab);
+        assert!(other_process.unblock());
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+        assert_eq!(ring_buffer.consumer_position(), 64);
+    }
+
+    #[test]
+    fn should_not_redeliver_batched_bytes_after_restart() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let strategy = ZeroFill::Batched { chunk_length: 256 };
+
+        for i in 0..3_i64 {
+            assert!(ManyToOneRingBuffer::new(ab).write(MSG_TYPE_ID, &i.to_ne_bytes()[..], 0, 8));
+        }
+        let consumer = ManyToOneRingBuffer::with_zero_fill(ab, strategy);
+        assert_eq!(consumer.read(|_, _, _, _| {}, 2), 2);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 0);
+
+        let values = |ring_buffer: &ManyToOneRingBuffer| {
+            let mut values = vec![];
+            ring_buffer.read(
+                |_, buffer, index, _| values.push(buffer.get_i64(index)),
+                u32::MAX,
+            );
+            values
+        };
+
+        let restarted = ManyToOneRingBuffer::with_zero_fill(ab, strategy);
+        assert_eq!(values(&restarted), vec![2]);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 0);
+
+        // like an Agrona consumer, an eager one starts from the head
+        let restarted_eager = ManyToOneRingBuffer::new(ab);
+        assert_eq!(values(&restarted_eager), vec![0, 1, 2]);
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 48);
+        for i in (0..48).step_by(8) {
+            assert_eq!(ab.get_i64(i), 0, "not zeroed at index {}", i);
+        }
+    }
+
+    #[test]
+    fn should_zero_only_headers_of_read_records() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let ring_buffer = OneToOneRingBuffer::with_zero_fill(ab, ZeroFill::HeadersOnly);
+        let msg = [1_u8; 8];
+
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 2);
+
+        assert_eq!(ring_buffer.consumer_position(), 32);
+        assert_eq!(ab.get_i64(0), 0);
+        assert_eq!(ab.get_i64(16), 0);
+        assert_eq!(ab.get::<[u8; 8]>(8), msg);
+        assert_eq!(ab.get::<[u8; 8]>(24), msg);
+    }
+
+    #[test]
+    fn should_write_only_what_agrona_writes_when_eager() {
+        let mut context = ZeroFillTest::default();
+        let ab = context.atomic_buffer();
+        let ring_buffer = ManyToOneRingBuffer::new(ab);
+        let msg = [1_u8; 24];
+
+        for _ in 0..3 {
+            assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 24));
+        }
+        assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 3);
+
+        let agrona_fields = [
+            RingBufferDescriptor::TAIL_POSITION_OFFSET,
+            RingBufferDescriptor::HEAD_CACHE_POSITION_OFFSET,
+            RingBufferDescriptor::HEAD_POSITION_OFFSET,
+        ];
+        for index in (0..BUFFER_SZ as Index).step_by(8) {
+            if !agrona_fields.contains(&(index - CAPACITY as Index)) {
+                assert_eq!(ab.get_i64(index), 0, "written at index {}", index);
+            }
+        }
+        assert_eq!(ab.get_i64(HEAD_COUNTER_INDEX), 96);
+    }
+
+    #[test]
+    fn should_publish_consumer_position_when_batched_or_headers_only() {
+        let consumer_position_index =
+            CAPACITY as Index + RingBufferDescriptor::CONSUMER_POSITION_OFFSET;
+        let msg = [1_u8; 8];
+
+        for strategy in [ZeroFill::Batched { chunk_length: 256 }, ZeroFill::HeadersOnly] {
+            let mut context = ZeroFillTest::default();
+            let ab = context.atomic_buffer();
+            let ring_buffer = OneToOneRingBuffer::with_zero_fill(ab, strategy);
+
+            assert!(ring_buffer.write(MSG_TYPE_ID, &msg[..], 0, 8));
+            assert_eq!(ring_buffer.read(|_, _, _, _| {}, u32::MAX), 1);
+
+            assert_eq!(ab.get_i64(consumer_position_index), 16, "{:?}", strategy);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::clock::{EpochClock, SystemEpochClock};
+use super::{atomic, AtomicBuffer};
+use crate::util::Index;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+enum TimestampSequence {
+    Local(AtomicI64),
+    Shared(AtomicBuffer, Index),
+}
+
+impl TimestampSequence {
+    #[inline]
+    fn get(&self) -> i64 {
+        match self {
+            TimestampSequence::Local(value) => value.load(Ordering::Acquire),
+            TimestampSequence::Shared(buffer, index) => buffer.get_i64_volatile(*index),
+        }
+    }
+
+    #[inline]
+    fn compare_and_set(&self, expected: i64, updated: i64) -> bool {
+        match self {
+            TimestampSequence::Local(value) => value
+                .compare_exchange(expected, updated, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok(),
+            TimestampSequence::Shared(buffer, index) => {
+                buffer.compare_and_set_i64(*index, expected, updated)
+            }
+        }
+    }
+}
+
+/**
+* Generate unique identifiers based on the Twitter
+* <a href="https://en.wikipedia.org/wiki/Snowflake_ID">Snowflake</a> algorithm, as in Agrona.
+* <p>
+* Identifiers are 63 bits made up of a timestamp in milliseconds since an offset epoch, a node id
+* and a sequence within the millisecond, so they are unique across nodes and roughly ordered by time.
+* <pre>
+*   0                   1                   2                   3
+*   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |0|                       Timestamp (41 bits)                   |
+*  +-+                                         +-+-+-+-+-+-+-+-+-+-+
+*  |                                           |  Node Id (10)     |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |   Node Id     |        Sequence (12 bits)                     |
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+* </pre>
+* The state may be kept in an 8 byte aligned slot of an [`AtomicBuffer`] so generators in several
+* processes on a host, configured with the same node id, share one sequence.
+*/
+pub struct SnowflakeIdGenerator<C = SystemEpochClock>
+where
+    C: EpochClock,
+{
+    node_id_bits: u32,
+    sequence_bits: u32,
+    node_id: i64,
+    max_node_id: i64,
+    max_sequence: i64,
+    timestamp_offset_ms: i64,
+    clock: C,
+    timestamp_sequence: TimestampSequence,
+}
+
+unsafe impl<C: EpochClock + Send> Send for SnowflakeIdGenerator<C> {}
+
+unsafe impl<C: EpochClock + Sync> Sync for SnowflakeIdGenerator<C> {}
+
+impl SnowflakeIdGenerator {
+    /// Generator for `node_id` using the default bit allocation, the Unix epoch and the system
+    /// clock.
+    pub fn with_node_id(node_id: i64) -> SnowflakeIdGenerator {
+        SnowflakeIdGenerator::new(
+            Self::NODE_ID_BITS_DEFAULT,
+            Self::SEQUENCE_BITS_DEFAULT,
+            node_id,
+            0,
+            SystemEpochClock,
+        )
+    }
+}
+
+impl<C> SnowflakeIdGenerator<C>
+where
+    C: EpochClock,
+{
+    pub const UNUSED_BITS: u32 = 1;
+    pub const EPOCH_BITS: u32 = 41;
+    pub const MAX_NODE_ID_AND_SEQUENCE_BITS: u32 = 22;
+    pub const NODE_ID_BITS_DEFAULT: u32 = 10;
+    pub const SEQUENCE_BITS_DEFAULT: u32 = 12;
+
+    /// Create a generator, with `timestamp_offset_ms` moving the epoch of the generated timestamps
+    /// forward from the epoch of the clock.
+    pub fn new(
+        node_id_bits: u32,
+        sequence_bits: u32,
+        node_id: i64,
+        timestamp_offset_ms: i64,
+        clock: C,
+    ) -> SnowflakeIdGenerator<C> {
+        if node_id_bits + sequence_bits > Self::MAX_NODE_ID_AND_SEQUENCE_BITS {
+            panic!(
+                "too many bits used: nodeIdBits={} + sequenceBits={} > {}",
+                node_id_bits,
+                sequence_bits,
+                Self::MAX_NODE_ID_AND_SEQUENCE_BITS
+            );
+        }
+
+        let max_node_id = (1_i64 << node_id_bits) - 1;
+        if node_id < 0 || node_id > max_node_id {
+            panic!("must be >= 0 && <= {}: nodeId={}", max_node_id, node_id);
+        }
+
+        if timestamp_offset_ms < 0 {
+            panic!("must be >= 0: timestampOffsetMs={}", timestamp_offset_ms);
+        }
+
+        let now_ms = clock.time();
+        if timestamp_offset_ms > now_ms {
+            panic!(
+                "timestampOffsetMs={} > nowMs={}",
+                timestamp_offset_ms, now_ms
+            );
+        }
+
+        SnowflakeIdGenerator {
+            node_id_bits,
+            sequence_bits,
+            node_id: node_id << sequence_bits,
+            max_node_id,
+            max_sequence: (1_i64 << sequence_bits) - 1,
+            timestamp_offset_ms,
+            clock,
+            timestamp_sequence: TimestampSequence::Local(AtomicI64::new(0)),
+        }
+    }
+
+    /// Keep the state in the 8 byte aligned slot at `index` of `buffer`, which must outlive the
+    /// generator. The slot is advanced to at least the current local state.
+    pub fn backed_by(mut self, buffer: AtomicBuffer, index: Index) -> SnowflakeIdGenerator<C> {
+        if index % 8 != 0 {
+            panic!("index must be 8 byte aligned: index={}", index);
+        }
+
+        let timestamp_sequence = self.timestamp_sequence.get();
+        self.timestamp_sequence = TimestampSequence::Shared(buffer, index);
+        self.advance_to(timestamp_sequence);
+
+        self
+    }
+
+    /// Ensure ids generated from now on are greater than `last_id`, e.g. the last id persisted
+    /// before a restart.
+    pub fn seed(&self, last_id: i64) {
+        self.advance_to(last_id & !(self.max_node_id << self.sequence_bits));
+    }
+
+    fn advance_to(&self, timestamp_sequence: i64) {
+        loop {
+            let old_timestamp_sequence = self.timestamp_sequence.get();
+            if old_timestamp_sequence >= timestamp_sequence
+                || self
+                    .timestamp_sequence
+                    .compare_and_set(old_timestamp_sequence, timestamp_sequence)
+            {
+                return;
+            }
+        }
+    }
+
+    /// Generate the next id, spinning while the sequence for the current millisecond is exhausted.
+    pub fn next_id(&self) -> i64 {
+        let timestamp_shift = self.node_id_bits + self.sequence_bits;
+
+        loop {
+            let old_timestamp_sequence = self.timestamp_sequence.get();
+            let timestamp_ms = self.clock.time() - self.timestamp_offset_ms;
+            let old_timestamp_ms = old_timestamp_sequence >> timestamp_shift;
+
+            if timestamp_ms > old_timestamp_ms {
+                let new_timestamp_sequence = timestamp_ms << timestamp_shift;
+                if self
+                    .timestamp_sequence
+                    .compare_and_set(old_timestamp_sequence, new_timestamp_sequence)
+                {
+                    return new_timestamp_sequence | self.node_id;
+                }
+            } else {
+                let old_sequence = old_timestamp_sequence & self.max_sequence;
+                if old_sequence < self.max_sequence {
+                    let new_timestamp_sequence = old_timestamp_sequence + 1;
+                    if self
+                        .timestamp_sequence
+                        .compare_and_set(old_timestamp_sequence, new_timestamp_sequence)
+                    {
+                        return new_timestamp_sequence | self.node_id;
+                    }
+                }
+            }
+
+            atomic::cpu_pause();
+        }
+    }
+
+    pub fn extract_timestamp(&self, id: i64) -> i64 {
+        id >> (self.node_id_bits + self.sequence_bits)
+    }
+
+    pub fn extract_node_id(&self, id: i64) -> i64 {
+        (id >> self.sequence_bits) & self.max_node_id
+    }
+
+    pub fn extract_sequence(&self, id: i64) -> i64 {
+        id & self.max_sequence
+    }
+
+    pub fn node_id_bits(&self) -> u32 {
+        self.node_id_bits
+    }
+
+    pub fn sequence_bits(&self) -> u32 {
+        self.sequence_bits
+    }
+
+    pub fn max_node_id(&self) -> i64 {
+        self.max_node_id
+    }
+
+    pub fn max_sequence(&self) -> i64 {
+        self.max_sequence
+    }
+
+    pub fn timestamp_offset_ms(&self) -> i64 {
+        self.timestamp_offset_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::clock::CachedEpochClock;
+    use crate::mem::Align16;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+    use std::thread;
+
+    const NODE_ID: i64 = 7;
+
+    struct SnowflakeIdGeneratorTest {
+        clock: CachedEpochClock,
+    }
+
+    impl Default for SnowflakeIdGeneratorTest {
+        fn default() -> Self {
+            SnowflakeIdGeneratorTest {
+                clock: CachedEpochClock::new(1000),
+            }
+        }
+    }
+
+    impl SnowflakeIdGeneratorTest {
+        fn generator(
+            &self,
+            node_id_bits: u32,
+            sequence_bits: u32,
+        ) -> SnowflakeIdGenerator<&CachedEpochClock> {
+            SnowflakeIdGenerator::new(node_id_bits, sequence_bits, NODE_ID, 0, &self.clock)
+        }
+    }
+
+    /// Advances by a millisecond every 100 readings.
+    struct SlowClock(AtomicI64);
+
+    impl EpochClock for SlowClock {
+        fn time(&self) -> i64 {
+            self.0.fetch_add(1, Ordering::AcqRel) / 100
+        }
+    }
+
+    #[test]
+    fn should_generate_id_from_timestamp_node_id_and_sequence() {
+        let context = SnowflakeIdGeneratorTest::default();
+        let generator = context.generator(10, 12);
+
+        let first = generator.next_id();
+        let second = generator.next_id();
+
+        assert_eq!(generator.extract_timestamp(first), 1000);
+        assert_eq!(generator.extract_node_id(first), NODE_ID);
+        assert_eq!(generator.extract_sequence(first), 0);
+        assert_eq!(generator.extract_sequence(second), 1);
+        assert_eq!(first, (1000 << 22) | (NODE_ID << 12));
+
+        context.clock.update(1001);
+        let third = generator.next_id();
+
+        assert_eq!(generator.extract_timestamp(third), 1001);
+        assert_eq!(generator.extract_sequence(third), 0);
+    }
+
+    #[test]
+    fn should_wait_for_next_millisecond_when_sequence_exhausted() {
+        let generator = SnowflakeIdGenerator::new(2, 2, 1, 0, SlowClock(AtomicI64::new(1000)));
+
+        let ids: Vec<i64> = (0..8).map(|_| generator.next_id()).collect();
+        let unique: HashSet<i64> = ids.iter().cloned().collect();
+
+        assert_eq!(unique.len(), ids.len());
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(ids.iter().all(|id| generator.extract_sequence(*id) <= 3));
+    }
+
+    #[test]
+    fn should_not_go_backwards_when_seeded() {
+        let context = SnowflakeIdGeneratorTest::default();
+        let generator = context.generator(10, 12);
+        let last_id = (2000 << 22) | (NODE_ID << 12) | 5;
+
+        generator.seed(last_id);
+        let id = generator.next_id();
+
+        assert_eq!(id, last_id + 1);
+    }
+
+    #[test]
+    fn should_share_sequence_through_buffer_slot() {
+        let context = SnowflakeIdGeneratorTest::default();
+        let mut buffer = Align16::new([0_u8; 64]);
+        let ab = AtomicBuffer::wrap(&mut *buffer);
+
+        let first = context.generator(10, 12).backed_by(ab, 8);
+        let second = context.generator(10, 12).backed_by(ab, 8);
+
+        let ids = [first.next_id(), second.next_id(), first.next_id()];
+
+        assert_eq!(
+            ids.iter()
+                .map(|id| first.extract_sequence(*id))
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(ab.get_i64(8), ids[2] & !(NODE_ID << 12));
+    }
+
+    #[test]
+    fn should_generate_unique_ids_across_threads() {
+        let generator = Arc::new(SnowflakeIdGenerator::with_node_id(NODE_ID));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = Arc::clone(&generator);
+                thread::spawn(move || (0..10_000).map(|_| generator.next_id()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for handle in handles {
+            for id in handle.join().unwrap() {
+                assert!(ids.insert(id));
+            }
+        }
+        assert_eq!(ids.len(), 40_000);
+    }
+
+    #[test]
+    #[should_panic(expected = "too many bits used: nodeIdBits=12 + sequenceBits=12 > 22")]
+    fn should_panic_when_too_many_bits_used() {
+        SnowflakeIdGeneratorTest::default().generator(12, 12);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be >= 0 && <= 3: nodeId=7")]
+    fn should_panic_when_node_id_exceeds_bits() {
+        SnowflakeIdGeneratorTest::default().generator(2, 12);
+    }
+}
@@ -0,0 +1,211 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::buffer::ReadOnlyBuffer;
+use crate::util::Index;
+use std::io;
+
+/// [`io::Read`] and [`io::BufRead`] over a region of a [`ReadOnlyBuffer`], such as a message
+/// payload passed to a read handler, similar to Agrona's `DirectBufferInputStream`.
+///
+/// The buffer may be written concurrently, so [`fill_buf`](io::BufRead::fill_buf) copies up to
+/// [`BUFFER_LENGTH`](AtomicBufferReader::BUFFER_LENGTH) bytes into the reader rather than lending
+/// out the shared memory.
+#[derive(Debug)]
+pub struct AtomicBufferReader {
+    buffer: ReadOnlyBuffer,
+    offset: Index,
+    length: Index,
+    position: Index,
+    copy: Vec<u8>,
+    copy_position: usize,
+}
+
+impl AtomicBufferReader {
+    /// Most bytes copied out of the buffer by each [`fill_buf`](io::BufRead::fill_buf).
+    pub const BUFFER_LENGTH: usize = 8 * 1024;
+
+    /// Create a reader over `length` bytes of `buffer` starting at `offset`.
+    pub fn new(buffer: &ReadOnlyBuffer, offset: Index, length: Index) -> AtomicBufferReader {
+        if offset < 0 || length < 0 || offset as i64 + length as i64 > buffer.capacity() as i64 {
+            panic!(
+                "region out of bounds: offset={} length={} capacity={}",
+                offset,
+                length,
+                buffer.capacity()
+            );
+        }
+
+        AtomicBufferReader {
+            buffer: *buffer,
+            offset,
+            length,
+            position: 0,
+            copy: Vec::new(),
+            copy_position: 0,
+        }
+    }
+
+    /// Offset of the region in the underlying buffer.
+    pub fn offset(&self) -> Index {
+        self.offset
+    }
+
+    /// Length of the region.
+    pub fn length(&self) -> Index {
+        self.length
+    }
+
+    /// Number of bytes read.
+    pub fn position(&self) -> Index {
+        self.position
+    }
+
+    /// Number of bytes left to read.
+    pub fn remaining(&self) -> Index {
+        self.length - self.position
+    }
+}
+
+impl io::Read for AtomicBufferReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let copied = &self.copy[self.copy_position..];
+        if !copied.is_empty() {
+            let length = buf.len().min(copied.len());
+            buf[..length].copy_from_slice(&copied[..length]);
+            io::BufRead::consume(self, length);
+
+            return Ok(length);
+        }
+
+        let length = buf.len().min(self.remaining() as usize);
+
+        self.buffer
+            .get_bytes(self.offset + self.position, &mut buf[..length]);
+        self.position += length as Index;
+
+        Ok(length)
+    }
+}
+
+impl io::BufRead for AtomicBufferReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.copy_position == self.copy.len() {
+            let length = (self.remaining() as usize).min(Self::BUFFER_LENGTH);
+            self.copy.resize(length, 0);
+            self.buffer
+                .get_bytes(self.offset + self.position, &mut self.copy);
+            self.copy_position = 0;
+        }
+
+        Ok(&self.copy[self.copy_position..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.copy.len() - self.copy_position);
+        self.copy_position += amt;
+        self.position += amt as Index;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{OneToOneRingBuffer, RingBuffer, RingBufferDescriptor};
+    use crate::concurrent::AtomicBuffer;
+    use crate::io::AtomicBufferWriter;
+    use crate::mem::{Align64, AlignedBox};
+    use std::io::{BufRead, Read, Write};
+
+    #[test]
+    fn should_read_region() {
+        let mut bytes = Align64::new(*b"..hello..");
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut reader = AtomicBufferReader::new(buffer.as_read_only(), 2, 5);
+        let mut buf = [0_u8; 3];
+
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf, b"hel");
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"lo");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reader.position(), 5);
+    }
+
+    #[test]
+    fn should_read_lines_from_region() {
+        let mut bytes = Align64::new(*b"first\nsecond\nignored");
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let reader = AtomicBufferReader::new(buffer.as_read_only(), 0, 13);
+
+        let lines: Vec<String> = reader.lines().map(|line| line.unwrap()).collect();
+
+        assert_eq!(lines, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn should_fill_buf_with_copy_of_region() {
+        let mut bytes = Align64::new(*b"..hello..");
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut reader = AtomicBufferReader::new(buffer.as_read_only(), 2, 5);
+        let mut buf = [0_u8; 2];
+
+        assert_eq!(reader.fill_buf().unwrap(), b"hello");
+        buffer.put_slice(2, b"HELLO");
+        assert_eq!(reader.fill_buf().unwrap(), b"hello");
+
+        reader.consume(1);
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf, b"el");
+        assert_eq!(reader.fill_buf().unwrap(), b"lo");
+        reader.consume(2);
+        assert_eq!(reader.fill_buf().unwrap(), b"");
+        assert_eq!(reader.position(), 5);
+    }
+
+    #[test]
+    fn should_round_trip_message_through_ring_buffer() {
+        const MSG_TYPE_ID: i32 = 7;
+        let mut bytes =
+            AlignedBox::<[u8]>::zeroed(1024 + RingBufferDescriptor::TRAILER_LENGTH as usize, 64);
+        let ring_buffer = OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut bytes));
+        let message = "{\"id\":1,\"name\":\"chair\"}";
+
+        let index = ring_buffer.try_claim(MSG_TYPE_ID, message.len() as Index);
+        assert!(index > 0);
+        let mut writer =
+            AtomicBufferWriter::new(ring_buffer.buffer(), index, message.len() as Index);
+        writer.write_all(message.as_bytes()).unwrap();
+        ring_buffer.commit(index);
+
+        let mut received = String::new();
+        let count = ring_buffer.read(
+            |msg_type_id, buffer, index, length| {
+                assert_eq!(msg_type_id, MSG_TYPE_ID);
+                AtomicBufferReader::new(buffer, index, length)
+                    .read_to_string(&mut received)
+                    .unwrap();
+            },
+            1,
+        );
+
+        assert_eq!(count, 1);
+        assert_eq!(received, message);
+    }
+}
@@ -0,0 +1,149 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::concurrent::AtomicBuffer;
+use crate::util::Index;
+use std::io;
+
+/**
+* [`io::Write`] over a fixed region of an [`AtomicBuffer`], similar to Agrona's
+* `DirectBufferOutputStream`.
+* <p>
+* Writes which do not fit in the remaining space are truncated to it, so `write_all` fails with
+* [`io::ErrorKind::WriteZero`] once the region is full.
+*/
+#[derive(Debug)]
+pub struct AtomicBufferWriter {
+    buffer: AtomicBuffer,
+    offset: Index,
+    length: Index,
+    position: Index,
+}
+
+impl AtomicBufferWriter {
+    /// Create a writer over `length` bytes of `buffer` starting at `offset`, e.g. space returned
+    /// by [`try_claim`](crate::concurrent::ring_buffer::RingBuffer::try_claim).
+    pub fn new(buffer: &AtomicBuffer, offset: Index, length: Index) -> AtomicBufferWriter {
+        if offset < 0 || length < 0 || offset as i64 + length as i64 > buffer.capacity() as i64 {
+            panic!(
+                "region out of bounds: offset={} length={} capacity={}",
+                offset,
+                length,
+                buffer.capacity()
+            );
+        }
+
+        AtomicBufferWriter {
+            buffer: *buffer,
+            offset,
+            length,
+            position: 0,
+        }
+    }
+
+    /// Offset of the region in the underlying buffer.
+    pub fn offset(&self) -> Index {
+        self.offset
+    }
+
+    /// Length of the region.
+    pub fn length(&self) -> Index {
+        self.length
+    }
+
+    /// Number of bytes written.
+    pub fn position(&self) -> Index {
+        self.position
+    }
+
+    /// Number of bytes which can still be written.
+    pub fn remaining(&self) -> Index {
+        self.length - self.position
+    }
+
+    /// Start writing from the beginning of the region again.
+    pub fn reset(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl io::Write for AtomicBufferWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(self.remaining() as usize);
+
+        self.buffer.put_slice(self.offset + self.position, &buf[..length]);
+        self.position += length as Index;
+
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem::Align64;
+    use std::io::Write;
+
+    #[test]
+    fn should_write_into_region() {
+        let mut bytes = Align64::new([0_u8; 16]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut writer = AtomicBufferWriter::new(&buffer, 4, 8);
+
+        writer.write_all(b"abc").unwrap();
+        write!(writer, "{}", 42).unwrap();
+
+        assert_eq!(writer.offset(), 4);
+        assert_eq!(writer.position(), 5);
+        assert_eq!(writer.remaining(), 3);
+        assert_eq!(&bytes[..10], b"\0\0\0\0abc42\0");
+    }
+
+    #[test]
+    fn should_fail_write_all_when_region_is_full() {
+        let mut bytes = Align64::new([0_u8; 8]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut writer = AtomicBufferWriter::new(&buffer, 0, 4);
+
+        assert_eq!(writer.write(b"abcdef").unwrap(), 4);
+        assert_eq!(writer.write(b"g").unwrap(), 0);
+
+        let error = writer.write_all(b"g").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(&bytes[..], b"abcd\0\0\0\0");
+    }
+
+    #[test]
+    fn should_write_from_start_after_reset() {
+        let mut bytes = Align64::new([0_u8; 4]);
+        let buffer = AtomicBuffer::wrap(&mut *bytes);
+        let mut writer = AtomicBufferWriter::new(&buffer, 0, 4);
+
+        writer.write_all(b"abcd").unwrap();
+        writer.reset();
+        writer.write_all(b"x").unwrap();
+
+        assert_eq!(writer.position(), 1);
+        assert_eq!(&bytes[..], b"xbcd");
+    }
+}
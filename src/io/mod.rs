@@ -0,0 +1,28 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! [`std::io`] adapters over regions of an [`AtomicBuffer`](crate::concurrent::AtomicBuffer), so
+//! existing serializers can write straight into claimed ring buffer space and decode from message
+//! payloads.
+
+pub use self::atomic_buffer_reader::AtomicBufferReader;
+pub use self::atomic_buffer_writer::AtomicBufferWriter;
+
+pub mod atomic_buffer_reader;
+pub mod atomic_buffer_writer;
@@ -0,0 +1,430 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/aeron/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Helpers for sharing ring buffers with Java through JNI.
+//!
+//! A [`JavaBuffer`] wraps the memory of a direct `java.nio.ByteBuffer` or an Agrona
+//! `AtomicBuffer` and holds a global reference to the Java object so the memory outlives the
+//! native code using it. A [`JavaRingBuffer`] owns both the buffer and the ring buffer over it.
+//!
+//! Native producers and consumers run as [`NativeAgent`]s, which are handed to Java as a `long`
+//! handle with [`into_handle`] and stopped and joined when the handle is passed to
+//! [`close_handle`].
+
+use std::fmt;
+use std::io;
+use std::ops::{ControlFlow, Deref};
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use ::jni::errors::Error as JniError;
+use ::jni::objects::{GlobalRef, JByteBuffer, JObject};
+use ::jni::sys::jlong;
+use ::jni::JNIEnv;
+
+use crate::buffer::ReadOnlyBuffer;
+use crate::concurrent::ring_buffer::{RingBuffer, RingBufferDescriptor};
+use crate::concurrent::{AtomicBuffer, IdleStrategy};
+use crate::util::{bit_util, Index};
+
+#[derive(Debug)]
+pub enum Error {
+    Jni(JniError),
+    /// The buffer is backed by a Java heap array rather than native memory.
+    NotDirect,
+    /// The buffer is too large to wrap or not a power of 2 + trailer for a ring buffer.
+    InvalidCapacity(i64),
+    /// The buffer address is not aligned to [`AtomicBuffer::ALIGNMENT`], e.g. a `ByteBuffer`
+    /// slice or an `UnsafeBuffer` at an odd offset.
+    Misaligned(usize),
+}
+
+impl Error {
+    /// Throw the error as a Java exception, unless it was caused by one already pending.
+    pub fn throw(&self, env: &mut JNIEnv) {
+        if let Error::Jni(JniError::JavaException) = self {
+            return;
+        }
+
+        let _ = env.throw_new("java/lang/IllegalArgumentException", self.to_string());
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Jni(e) => write!(f, "JNI call failed: {}", e),
+            Error::NotDirect => write!(f, "buffer is not backed by native memory"),
+            Error::InvalidCapacity(capacity) => write!(f, "invalid buffer capacity={}", capacity),
+            Error::Misaligned(address) => write!(
+                f,
+                "buffer address={} is not aligned to {}",
+                address,
+                AtomicBuffer::ALIGNMENT
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<JniError> for Error {
+    fn from(e: JniError) -> Self {
+        Error::Jni(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Native memory of a Java buffer, kept reachable for as long as this value lives.
+pub struct JavaBuffer {
+    buffer: AtomicBuffer,
+    _owner: GlobalRef,
+}
+
+unsafe impl Send for JavaBuffer {}
+
+unsafe impl Sync for JavaBuffer {}
+
+impl JavaBuffer {
+    /// Wrap a direct `java.nio.ByteBuffer`.
+    pub fn from_byte_buffer(env: &mut JNIEnv, byte_buffer: &JByteBuffer) -> Result<JavaBuffer> {
+        let address = env.get_direct_buffer_address(byte_buffer)?;
+        let capacity = env.get_direct_buffer_capacity(byte_buffer)?;
+        let owner = env.new_global_ref(byte_buffer)?;
+
+        unsafe { Self::wrap(address, capacity as i64, owner) }
+    }
+
+    /// Wrap an Agrona `AtomicBuffer`, such as an `UnsafeBuffer` over a direct `ByteBuffer` or
+    /// a memory-mapped file.
+    ///
+    /// A buffer wrapping a raw address does not own its memory, which must then be kept alive by
+    /// the Java side for as long as this value lives.
+    pub fn from_agrona_buffer(env: &mut JNIEnv, buffer: &JObject) -> Result<JavaBuffer> {
+        let byte_array = env.call_method(buffer, "byteArray", "()[B", &[])?.l()?;
+        if !byte_array.is_null() {
+            return Err(Error::NotDirect);
+        }
+
+        let address = env.call_method(buffer, "addressOffset", "()J", &[])?.j()?;
+        let capacity = env.call_method(buffer, "capacity", "()I", &[])?.i()?;
+        let owner = env.new_global_ref(buffer)?;
+
+        unsafe { Self::wrap(address as *mut u8, capacity as i64, owner) }
+    }
+
+    unsafe fn wrap(address: *mut u8, capacity: i64, owner: GlobalRef) -> Result<JavaBuffer> {
+        Ok(JavaBuffer {
+            buffer: wrap_memory(address, capacity)?,
+            _owner: owner,
+        })
+    }
+}
+
+/// Wrap memory supplied by Java, checking it rather than panicking inside a JNI call.
+unsafe fn wrap_memory(address: *mut u8, capacity: i64) -> Result<AtomicBuffer> {
+    if address.is_null() {
+        return Err(Error::NotDirect);
+    }
+    if capacity < 0 || capacity > Index::MAX as i64 {
+        return Err(Error::InvalidCapacity(capacity));
+    }
+
+    AtomicBuffer::try_wrap(slice::from_raw_parts_mut(address, capacity as usize))
+        .map_err(|_| Error::Misaligned(address as usize))
+}
+
+impl Deref for JavaBuffer {
+    type Target = AtomicBuffer;
+
+    fn deref(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+}
+
+/// A ring buffer over the memory of a [`JavaBuffer`] which it keeps alive.
+pub struct JavaRingBuffer<R> {
+    ring_buffer: R,
+    _buffer: JavaBuffer,
+}
+
+impl<R: RingBuffer> JavaRingBuffer<R> {
+    /// Create a ring buffer over `buffer`, e.g. `JavaRingBuffer::new(buffer, OneToOneRingBuffer::new)`.
+    ///
+    /// The capacity is checked up front so a bad buffer is reported rather than panicking.
+    pub fn new(buffer: JavaBuffer, new_ring_buffer: fn(AtomicBuffer) -> R) -> Result<Self> {
+        let capacity = buffer.capacity() - RingBufferDescriptor::TRAILER_LENGTH;
+        if !bit_util::is_power_of_two(capacity) {
+            return Err(Error::InvalidCapacity(buffer.capacity() as i64));
+        }
+
+        Ok(JavaRingBuffer {
+            ring_buffer: new_ring_buffer(*buffer),
+            _buffer: buffer,
+        })
+    }
+}
+
+impl<R> Deref for JavaRingBuffer<R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.ring_buffer
+    }
+}
+
+/// A duty cycle run on its own thread until it breaks or the agent is closed.
+pub struct NativeAgent {
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl NativeAgent {
+    /// Start a thread calling `duty_cycle` until it returns `Break`, idling with the work count
+    /// it returns in `Continue`.
+    pub fn start<S, F>(name: &str, idle_strategy: S, mut duty_cycle: F) -> io::Result<NativeAgent>
+    where
+        S: IdleStrategy + Send + 'static,
+        F: FnMut() -> ControlFlow<(), i8> + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let agent_running = Arc::clone(&running);
+
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                while agent_running.load(Ordering::Acquire) {
+                    match duty_cycle() {
+                        ControlFlow::Continue(work_count) => idle_strategy.idle_work(work_count),
+                        ControlFlow::Break(()) => break,
+                    }
+                }
+                agent_running.store(false, Ordering::Release);
+            })?;
+
+        Ok(NativeAgent {
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// Start a producer calling `produce` with the ring buffer until it returns `Break`.
+    pub fn producer<T, S, F>(
+        name: &str,
+        ring_buffer: T,
+        idle_strategy: S,
+        mut produce: F,
+    ) -> io::Result<NativeAgent>
+    where
+        T: Deref + Send + 'static,
+        T::Target: RingBuffer,
+        S: IdleStrategy + Send + 'static,
+        F: FnMut(&T::Target) -> ControlFlow<(), i8> + Send + 'static,
+    {
+        Self::start(name, idle_strategy, move || produce(&ring_buffer))
+    }
+
+    /// Start a consumer reading up to `message_count_limit` messages per duty cycle into `handler`.
+    pub fn consumer<T, S, H>(
+        name: &str,
+        ring_buffer: T,
+        idle_strategy: S,
+        mut handler: H,
+        message_count_limit: u32,
+    ) -> io::Result<NativeAgent>
+    where
+        T: Deref + Send + 'static,
+        T::Target: RingBuffer,
+        S: IdleStrategy + Send + 'static,
+        H: FnMut(i32, &ReadOnlyBuffer, Index, Index) + Send + 'static,
+    {
+        Self::start(name, idle_strategy, move || {
+            let messages_read = ring_buffer.read(&mut handler, message_count_limit);
+            ControlFlow::Continue(messages_read.min(i8::MAX as u32) as i8)
+        })
+    }
+
+    /// True until the duty cycle breaks or the agent is closed.
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Stop the duty cycle and wait for the thread to exit.
+    pub fn close(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for NativeAgent {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Move `value` to the heap and return its address as a handle for Java to hold in a `long`.
+pub fn into_handle<T>(value: T) -> jlong {
+    Box::into_raw(Box::new(value)) as jlong
+}
+
+/// Borrow the value behind a handle.
+///
+/// # Safety
+///
+/// `handle` must have been returned by [`into_handle`] for a `T` and not yet been closed.
+pub unsafe fn from_handle<'a, T>(handle: jlong) -> &'a T {
+    &*(handle as *const T)
+}
+
+/// Drop the value behind a handle, ignoring a null handle.
+///
+/// # Safety
+///
+/// `handle` must have been returned by [`into_handle`] for a `T` and not yet been closed.
+pub unsafe fn close_handle<T>(handle: jlong) {
+    if handle != 0 {
+        drop(Box::from_raw(handle as *mut T));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::OneToOneRingBuffer;
+    use crate::concurrent::{BusySpinIdleStrategy, YieldingIdleStrategy};
+    use crate::mem::{Align16, AlignedBox};
+    use std::sync::atomic::AtomicI64;
+    use std::sync::Mutex;
+
+    const CAPACITY: i32 = 1024;
+    const BUFFER_SZ: usize = (CAPACITY + RingBufferDescriptor::TRAILER_LENGTH) as usize;
+    const MSG_TYPE_ID: i32 = 101;
+
+    #[test]
+    fn should_return_error_for_misaligned_memory() {
+        let mut bytes = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, AtomicBuffer::ALIGNMENT);
+        let address = unsafe { bytes.as_mut_ptr().add(4) };
+
+        match unsafe { wrap_memory(address, 64) } {
+            Err(Error::Misaligned(misaligned)) => assert_eq!(misaligned, address as usize),
+            result => panic!("expected misaligned error: {:?}", result.map(|_| ())),
+        }
+        assert_eq!(
+            unsafe { wrap_memory(bytes.as_mut_ptr(), 64) }.unwrap().capacity(),
+            64
+        );
+    }
+
+    #[test]
+    fn should_stop_agent_when_duty_cycle_breaks() {
+        let mut count = 0;
+        let mut agent = NativeAgent::start("test-agent", BusySpinIdleStrategy, move || {
+            count += 1;
+            if count < 10 {
+                ControlFlow::Continue(1)
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+        .unwrap();
+
+        while agent.is_running() {
+            thread::yield_now();
+        }
+        agent.close();
+    }
+
+    #[test]
+    fn should_consume_messages_from_producer_until_closed() {
+        let mut buffer = AlignedBox::<[u8]>::zeroed(BUFFER_SZ, 64);
+        let ring_buffer = Arc::new(OneToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer)));
+        let sum = Arc::new(AtomicI64::new(0));
+
+        let mut sent = 0_i64;
+        let producer = NativeAgent::producer(
+            "test-producer",
+            Arc::clone(&ring_buffer),
+            YieldingIdleStrategy,
+            move |ring_buffer| {
+                if sent == 100 {
+                    return ControlFlow::Break(());
+                }
+
+                let mut src = Align16::new([0_u8; 8]);
+                let src_buffer = AtomicBuffer::wrap(&mut *src);
+                src_buffer.put_i64(0, sent);
+                if ring_buffer.write(MSG_TYPE_ID, &src_buffer, 0, 8) {
+                    sent += 1;
+                    ControlFlow::Continue(1)
+                } else {
+                    ControlFlow::Continue(0)
+                }
+            },
+        )
+        .unwrap();
+
+        let consumer_sum = Arc::clone(&sum);
+        let mut consumer = NativeAgent::consumer(
+            "test-consumer",
+            Arc::clone(&ring_buffer),
+            YieldingIdleStrategy,
+            move |_, buffer, index, _| {
+                consumer_sum.fetch_add(buffer.get_i64(index), Ordering::AcqRel);
+            },
+            u32::MAX,
+        )
+        .unwrap();
+
+        while sum.load(Ordering::Acquire) != (0..100).sum::<i64>() {
+            thread::yield_now();
+        }
+
+        assert!(consumer.is_running());
+        consumer.close();
+        assert!(!consumer.is_running());
+        drop(producer);
+    }
+
+    #[test]
+    fn should_drop_value_when_handle_closed() {
+        let dropped = Arc::new(Mutex::new(false));
+
+        struct SetOnDrop(Arc<Mutex<bool>>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                *self.0.lock().unwrap() = true;
+            }
+        }
+
+        let handle = into_handle(SetOnDrop(Arc::clone(&dropped)));
+
+        unsafe {
+            assert!(!*from_handle::<SetOnDrop>(handle).0.lock().unwrap());
+            close_handle::<SetOnDrop>(handle);
+            close_handle::<SetOnDrop>(0);
+        }
+        assert!(*dropped.lock().unwrap());
+    }
+}
@@ -1,6 +1,12 @@
 #[macro_use(defer)]
 extern crate scopeguard;
 
+pub mod buffer;
+#[cfg(feature = "capi")]
+pub mod capi;
 pub mod concurrent;
+pub mod io;
+#[cfg(feature = "jni")]
+pub mod jni;
 pub mod mem;
 pub mod util;
@@ -0,0 +1,131 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// Heap allocation with a chosen alignment, for buffers whose size is only known at runtime, e.g.
+/// `AlignedBox::<[u8]>::zeroed(capacity + TRAILER_LENGTH, CACHE_LINE_LENGTH as usize)` to back a
+/// ring buffer.
+pub struct AlignedBox<T: ?Sized> {
+    ptr: NonNull<T>,
+    layout: Layout,
+}
+
+unsafe impl<T: ?Sized + Send> Send for AlignedBox<T> {}
+
+unsafe impl<T: ?Sized + Sync> Sync for AlignedBox<T> {}
+
+impl AlignedBox<[u8]> {
+    /// Allocate `length` zeroed bytes aligned to `alignment`, which must be a power of 2.
+    pub fn zeroed(length: usize, alignment: usize) -> AlignedBox<[u8]> {
+        let layout = match Layout::from_size_align(length.max(1), alignment) {
+            Ok(layout) => layout,
+            Err(_) => panic!(
+                "alignment must be a power of 2: alignment={} length={}",
+                alignment, length
+            ),
+        };
+
+        let data = unsafe { alloc::alloc_zeroed(layout) };
+        if data.is_null() {
+            alloc::handle_alloc_error(layout);
+        }
+
+        AlignedBox {
+            ptr: NonNull::slice_from_raw_parts(unsafe { NonNull::new_unchecked(data) }, length),
+            layout,
+        }
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.layout.align()
+    }
+}
+
+impl<T: ?Sized> Deref for AlignedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for AlignedBox<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for AlignedBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized> Drop for AlignedBox<T> {
+    fn drop(&mut self) {
+        unsafe {
+            std::ptr::drop_in_place(self.ptr.as_ptr());
+            alloc::dealloc(self.ptr.as_ptr() as *mut u8, self.layout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{ManyToOneRingBuffer, RingBuffer, RingBufferDescriptor};
+    use crate::concurrent::AtomicBuffer;
+
+    #[test]
+    fn should_allocate_zeroed_and_aligned() {
+        for alignment in [8, 64, 4096] {
+            let buffer = AlignedBox::<[u8]>::zeroed(100, alignment);
+
+            assert_eq!(buffer.len(), 100);
+            assert_eq!(buffer.alignment(), alignment);
+            assert_eq!(buffer.as_ptr() as usize % alignment, 0);
+            assert!(buffer.iter().all(|b| *b == 0));
+        }
+    }
+
+    #[test]
+    fn should_back_ring_buffer() {
+        let mut buffer =
+            AlignedBox::<[u8]>::zeroed(1024 + RingBufferDescriptor::TRAILER_LENGTH as usize, 64);
+        let ring_buffer = ManyToOneRingBuffer::new(AtomicBuffer::wrap(&mut buffer));
+
+        assert!(ring_buffer.write(1, &[1_u8, 2, 3][..], 0, 3));
+        assert_eq!(ring_buffer.read(|_, _, _, length| assert_eq!(length, 3), 1), 1);
+    }
+
+    #[test]
+    fn should_allocate_empty_slice() {
+        let buffer = AlignedBox::<[u8]>::zeroed(0, 64);
+
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "alignment must be a power of 2: alignment=48 length=8")]
+    fn should_panic_when_alignment_not_power_of_two() {
+        AlignedBox::<[u8]>::zeroed(8, 48);
+    }
+}
@@ -0,0 +1,523 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/aeron/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use super::mark_file::MarkFile;
+use crate::concurrent::ring_buffer::RingBufferDescriptor;
+use crate::concurrent::{AtomicBuffer, EpochClock, SystemEpochClock};
+use crate::util::bit_util;
+use crate::util::bit_util::CACHE_LINE_LENGTH;
+use crate::util::Index;
+
+/**
+* Layout of a command and control (CnC) file, following the Aeron media driver's `cnc.dat` with an
+* activity timestamp added in the header padding.
+* <pre>
+*   0                   1                   2                   3
+*   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+*  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*  |                          CnC Version                          |
+*  +---------------------------------------------------------------+
+*  |                    to-driver buffer length                    |
+*  +---------------------------------------------------------------+
+*  |                   to-clients buffer length                    |
+*  +---------------------------------------------------------------+
+*  |                Counters Metadata buffer length                |
+*  +---------------------------------------------------------------+
+*  |                 Counters Values buffer length                 |
+*  +---------------------------------------------------------------+
+*  |                   Error Log buffer length                     |
+*  +---------------------------------------------------------------+
+*  |                   Client Liveness Timeout                     |
+*  |                                                               |
+*  +---------------------------------------------------------------+
+*  |                        Start Timestamp                        |
+*  |                                                               |
+*  +---------------------------------------------------------------+
+*  |                              PID                              |
+*  |                                                               |
+*  +---------------------------------------------------------------+
+*  |                      Activity Timestamp                       |
+*  |                                                               |
+*  +---------------------------------------------------------------+
+*  |                         CnC Padding                          ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+*  |                       to-driver Buffer                       ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+*  |                      to-clients Buffer                       ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+*  |                   Counters Metadata Buffer                   ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+*  |                    Counters Values Buffer                    ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+*  |                          Error Log                           ...
+* ...                                                              |
+*  +---------------------------------------------------------------+
+* </pre>
+*/
+pub struct CncFileDescriptor;
+
+impl CncFileDescriptor {
+    pub const CNC_FILE: &'static str = "cnc.dat";
+
+    /// Semantic version 0.2.0 with the major version in bits 16-23.
+    pub const CNC_VERSION: i32 = 2 << 8;
+
+    pub const CNC_VERSION_FIELD_OFFSET: Index = 0;
+    pub const TO_DRIVER_BUFFER_LENGTH_FIELD_OFFSET: Index = 4;
+    pub const TO_CLIENTS_BUFFER_LENGTH_FIELD_OFFSET: Index = 8;
+    pub const COUNTERS_METADATA_BUFFER_LENGTH_FIELD_OFFSET: Index = 12;
+    pub const COUNTERS_VALUES_BUFFER_LENGTH_FIELD_OFFSET: Index = 16;
+    pub const ERROR_LOG_BUFFER_LENGTH_FIELD_OFFSET: Index = 20;
+    pub const CLIENT_LIVENESS_TIMEOUT_FIELD_OFFSET: Index = 24;
+    pub const START_TIMESTAMP_FIELD_OFFSET: Index = 32;
+    pub const PID_FIELD_OFFSET: Index = 40;
+    pub const ACTIVITY_TIMESTAMP_FIELD_OFFSET: Index = 48;
+
+    /* Length of the header, padded so the buffers following it are aligned. */
+    pub const META_DATA_LENGTH: Index = CACHE_LINE_LENGTH * 2;
+
+    #[inline]
+    pub fn major_version(version: i32) -> i32 {
+        (version >> 16) & 0xFF
+    }
+}
+
+/// Lengths of the regions of a [`CncFile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CncLengths {
+    /// Length of the to-driver ring buffer including its trailer.
+    pub to_driver_buffer_length: Index,
+    /// Length of the to-clients broadcast buffer.
+    pub to_clients_buffer_length: Index,
+    pub counters_metadata_buffer_length: Index,
+    pub counters_values_buffer_length: Index,
+    pub error_log_buffer_length: Index,
+    pub client_liveness_timeout_ns: i64,
+}
+
+impl CncLengths {
+    /// The to-driver length must be a power of 2 plus the ring buffer trailer, and the other
+    /// lengths non-negative multiples of 8 so that every region starts aligned.
+    fn is_valid(&self) -> bool {
+        let to_driver_capacity = self
+            .to_driver_buffer_length
+            .saturating_sub(RingBufferDescriptor::TRAILER_LENGTH);
+        let alignment = AtomicBuffer::ALIGNMENT as Index;
+
+        bit_util::is_power_of_two(to_driver_capacity)
+            && to_driver_capacity >= alignment
+            && [
+                self.to_clients_buffer_length,
+                self.counters_metadata_buffer_length,
+                self.counters_values_buffer_length,
+                self.error_log_buffer_length,
+            ]
+            .iter()
+            .all(|&length| length >= 0 && length % alignment == 0)
+    }
+
+    fn total_length(&self) -> i64 {
+        CncFileDescriptor::META_DATA_LENGTH as i64
+            + self.to_driver_buffer_length as i64
+            + self.to_clients_buffer_length as i64
+            + self.counters_metadata_buffer_length as i64
+            + self.counters_values_buffer_length as i64
+            + self.error_log_buffer_length as i64
+    }
+}
+
+/// A command and control file, a [`MarkFile`] through which a process publishes a to-driver ring
+/// buffer, a to-clients broadcast buffer, counters and an error log to other processes.
+pub struct CncFile {
+    mark_file: MarkFile,
+    lengths: CncLengths,
+}
+
+impl CncFile {
+    /// Create the file with its header filled in. The owner should initialise the regions it
+    /// needs and then call [`signal_ready`](CncFile::signal_ready).
+    pub fn create(path: &Path, lengths: CncLengths, timeout_ms: i64) -> io::Result<CncFile> {
+        if !lengths.is_valid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid CnC lengths {:?}", lengths),
+            ));
+        }
+
+        let mark_file = MarkFile::create(
+            path,
+            lengths.total_length() as usize,
+            CncFileDescriptor::CNC_VERSION_FIELD_OFFSET,
+            CncFileDescriptor::ACTIVITY_TIMESTAMP_FIELD_OFFSET,
+            timeout_ms,
+        )?;
+
+        let buffer = mark_file.buffer();
+        buffer.put::<i32>(
+            CncFileDescriptor::TO_DRIVER_BUFFER_LENGTH_FIELD_OFFSET,
+            lengths.to_driver_buffer_length,
+        );
+        buffer.put::<i32>(
+            CncFileDescriptor::TO_CLIENTS_BUFFER_LENGTH_FIELD_OFFSET,
+            lengths.to_clients_buffer_length,
+        );
+        buffer.put::<i32>(
+            CncFileDescriptor::COUNTERS_METADATA_BUFFER_LENGTH_FIELD_OFFSET,
+            lengths.counters_metadata_buffer_length,
+        );
+        buffer.put::<i32>(
+            CncFileDescriptor::COUNTERS_VALUES_BUFFER_LENGTH_FIELD_OFFSET,
+            lengths.counters_values_buffer_length,
+        );
+        buffer.put::<i32>(
+            CncFileDescriptor::ERROR_LOG_BUFFER_LENGTH_FIELD_OFFSET,
+            lengths.error_log_buffer_length,
+        );
+        buffer.put_i64(
+            CncFileDescriptor::CLIENT_LIVENESS_TIMEOUT_FIELD_OFFSET,
+            lengths.client_liveness_timeout_ns,
+        );
+
+        let now_ms = SystemEpochClock.time();
+        buffer.put_i64(CncFileDescriptor::START_TIMESTAMP_FIELD_OFFSET, now_ms);
+        buffer.put_i64(CncFileDescriptor::PID_FIELD_OFFSET, process::id() as i64);
+        mark_file.timestamp_ordered(now_ms);
+
+        Ok(CncFile { mark_file, lengths })
+    }
+
+    /// Map an existing file, waiting up to `timeout` for it to become ready and checking its major
+    /// version matches.
+    pub fn map_existing(path: &Path, timeout: Duration) -> io::Result<CncFile> {
+        let mark_file = MarkFile::map_existing(
+            path,
+            CncFileDescriptor::CNC_VERSION_FIELD_OFFSET,
+            CncFileDescriptor::ACTIVITY_TIMESTAMP_FIELD_OFFSET,
+            timeout,
+            |version| {
+                CncFileDescriptor::major_version(version)
+                    == CncFileDescriptor::major_version(CncFileDescriptor::CNC_VERSION)
+            },
+        )?;
+
+        let buffer = mark_file.buffer();
+        let lengths = CncLengths {
+            to_driver_buffer_length: buffer
+                .get::<i32>(CncFileDescriptor::TO_DRIVER_BUFFER_LENGTH_FIELD_OFFSET),
+            to_clients_buffer_length: buffer
+                .get::<i32>(CncFileDescriptor::TO_CLIENTS_BUFFER_LENGTH_FIELD_OFFSET),
+            counters_metadata_buffer_length: buffer
+                .get::<i32>(CncFileDescriptor::COUNTERS_METADATA_BUFFER_LENGTH_FIELD_OFFSET),
+            counters_values_buffer_length: buffer
+                .get::<i32>(CncFileDescriptor::COUNTERS_VALUES_BUFFER_LENGTH_FIELD_OFFSET),
+            error_log_buffer_length: buffer
+                .get::<i32>(CncFileDescriptor::ERROR_LOG_BUFFER_LENGTH_FIELD_OFFSET),
+            client_liveness_timeout_ns: buffer
+                .get_i64(CncFileDescriptor::CLIENT_LIVENESS_TIMEOUT_FIELD_OFFSET),
+        };
+
+        if !lengths.is_valid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid CnC lengths {:?}: {}", lengths, path.display()),
+            ));
+        }
+
+        if lengths.total_length() > buffer.capacity() as i64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("CnC file shorter than its regions: {}", path.display()),
+            ));
+        }
+
+        Ok(CncFile { mark_file, lengths })
+    }
+
+    /// True if a ready file exists at `path` with activity within `timeout_ms` of `now_ms`.
+    pub fn is_active(path: &Path, timeout_ms: i64, now_ms: i64) -> io::Result<bool> {
+        MarkFile::is_active(
+            path,
+            CncFileDescriptor::CNC_VERSION_FIELD_OFFSET,
+            CncFileDescriptor::ACTIVITY_TIMESTAMP_FIELD_OFFSET,
+            timeout_ms,
+            now_ms,
+        )
+    }
+
+    pub fn signal_ready(&self) {
+        self.mark_file.signal_ready(CncFileDescriptor::CNC_VERSION);
+    }
+
+    /// Record that the owner is still alive.
+    pub fn update_activity_timestamp(&self, now_ms: i64) {
+        self.mark_file.timestamp_ordered(now_ms);
+    }
+
+    pub fn activity_timestamp(&self) -> i64 {
+        self.mark_file.timestamp_volatile()
+    }
+
+    pub fn version(&self) -> i32 {
+        self.mark_file.version_volatile()
+    }
+
+    pub fn start_timestamp(&self) -> i64 {
+        self.mark_file
+            .buffer()
+            .get_i64(CncFileDescriptor::START_TIMESTAMP_FIELD_OFFSET)
+    }
+
+    pub fn pid(&self) -> i64 {
+        self.mark_file
+            .buffer()
+            .get_i64(CncFileDescriptor::PID_FIELD_OFFSET)
+    }
+
+    pub fn lengths(&self) -> &CncLengths {
+        &self.lengths
+    }
+
+    pub fn to_driver_buffer(&self) -> AtomicBuffer {
+        self.mark_file.region(
+            CncFileDescriptor::META_DATA_LENGTH,
+            self.lengths.to_driver_buffer_length,
+        )
+    }
+
+    pub fn to_clients_buffer(&self) -> AtomicBuffer {
+        self.mark_file.region(
+            CncFileDescriptor::META_DATA_LENGTH + self.lengths.to_driver_buffer_length,
+            self.lengths.to_clients_buffer_length,
+        )
+    }
+
+    pub fn counters_metadata_buffer(&self) -> AtomicBuffer {
+        self.mark_file.region(
+            CncFileDescriptor::META_DATA_LENGTH
+                + self.lengths.to_driver_buffer_length
+                + self.lengths.to_clients_buffer_length,
+            self.lengths.counters_metadata_buffer_length,
+        )
+    }
+
+    pub fn counters_values_buffer(&self) -> AtomicBuffer {
+        self.mark_file.region(
+            CncFileDescriptor::META_DATA_LENGTH
+                + self.lengths.to_driver_buffer_length
+                + self.lengths.to_clients_buffer_length
+                + self.lengths.counters_metadata_buffer_length,
+            self.lengths.counters_values_buffer_length,
+        )
+    }
+
+    pub fn error_log_buffer(&self) -> AtomicBuffer {
+        self.mark_file.region(
+            CncFileDescriptor::META_DATA_LENGTH
+                + self.lengths.to_driver_buffer_length
+                + self.lengths.to_clients_buffer_length
+                + self.lengths.counters_metadata_buffer_length
+                + self.lengths.counters_values_buffer_length,
+            self.lengths.error_log_buffer_length,
+        )
+    }
+
+    pub fn mark_file(&self) -> &MarkFile {
+        &self.mark_file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{ManyToOneRingBuffer, RingBuffer};
+    use crate::mem::mark_file::tests::temp_path;
+    use crate::mem::Align16;
+    use std::fs;
+    use std::path::PathBuf;
+
+    const MSG_TYPE_ID: i32 = 101;
+    const TIMEOUT_MS: i64 = 1000;
+
+    const LENGTHS: CncLengths = CncLengths {
+        to_driver_buffer_length: 1024 + RingBufferDescriptor::TRAILER_LENGTH,
+        to_clients_buffer_length: 1024,
+        counters_metadata_buffer_length: 512,
+        counters_values_buffer_length: 256,
+        error_log_buffer_length: 128,
+        client_liveness_timeout_ns: 10_000_000_000,
+    };
+
+    struct CncFileTest {
+        path: PathBuf,
+    }
+
+    impl Default for CncFileTest {
+        fn default() -> Self {
+            CncFileTest {
+                path: temp_path(CncFileDescriptor::CNC_FILE),
+            }
+        }
+    }
+
+    impl Drop for CncFileTest {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    #[test]
+    fn should_create_and_map_existing_cnc_file() {
+        let context = CncFileTest::default();
+        let cnc = CncFile::create(&context.path, LENGTHS, TIMEOUT_MS).unwrap();
+        cnc.signal_ready();
+
+        let existing = CncFile::map_existing(&context.path, Duration::from_millis(100)).unwrap();
+
+        assert_eq!(existing.version(), CncFileDescriptor::CNC_VERSION);
+        assert_eq!(*existing.lengths(), LENGTHS);
+        assert_eq!(existing.pid(), process::id() as i64);
+        assert_eq!(existing.start_timestamp(), cnc.start_timestamp());
+        assert_eq!(
+            fs::metadata(&context.path).unwrap().len(),
+            128 + 1792 + 1024 + 512 + 256 + 128
+        );
+    }
+
+    #[test]
+    fn should_share_to_driver_ring_buffer_between_mappings() {
+        let context = CncFileTest::default();
+        let cnc = CncFile::create(&context.path, LENGTHS, TIMEOUT_MS).unwrap();
+        cnc.signal_ready();
+        let existing = CncFile::map_existing(&context.path, Duration::from_millis(100)).unwrap();
+
+        let producer = ManyToOneRingBuffer::new(existing.to_driver_buffer());
+        let consumer = ManyToOneRingBuffer::new(cnc.to_driver_buffer());
+        let mut src = Align16::new([0_u8; 8]);
+        let src_buffer = AtomicBuffer::wrap(&mut *src);
+        src_buffer.put_i64(0, 42);
+
+        assert!(producer.write(MSG_TYPE_ID, &src_buffer, 0, 8));
+
+        let mut values = vec![];
+        consumer.read(
+            |msg_type_id, buffer, index, _| values.push((msg_type_id, buffer.get_i64(index))),
+            u32::MAX,
+        );
+        assert_eq!(values, vec![(MSG_TYPE_ID, 42)]);
+    }
+
+    #[test]
+    fn should_lay_out_regions_after_header() {
+        let context = CncFileTest::default();
+        let cnc = CncFile::create(&context.path, LENGTHS, TIMEOUT_MS).unwrap();
+
+        cnc.to_driver_buffer().put_i64(0, 1);
+        cnc.to_clients_buffer().put_i64(0, 2);
+        cnc.counters_metadata_buffer().put_i64(0, 3);
+        cnc.counters_values_buffer().put_i64(0, 4);
+        cnc.error_log_buffer().put_i64(0, 5);
+
+        let buffer = cnc.mark_file().buffer();
+        assert_eq!(buffer.get_i64(128), 1);
+        assert_eq!(buffer.get_i64(128 + 1792), 2);
+        assert_eq!(buffer.get_i64(128 + 1792 + 1024), 3);
+        assert_eq!(buffer.get_i64(128 + 1792 + 1024 + 512), 4);
+        assert_eq!(buffer.get_i64(128 + 1792 + 1024 + 512 + 256), 5);
+        assert_eq!(cnc.error_log_buffer().capacity(), 128);
+    }
+
+    #[test]
+    fn should_be_active_only_while_activity_timestamp_is_recent() {
+        let context = CncFileTest::default();
+        let cnc = CncFile::create(&context.path, LENGTHS, TIMEOUT_MS).unwrap();
+        let now_ms = SystemEpochClock.time();
+
+        assert!(!CncFile::is_active(&context.path, TIMEOUT_MS, now_ms).unwrap());
+
+        cnc.signal_ready();
+        cnc.update_activity_timestamp(now_ms);
+
+        assert!(CncFile::is_active(&context.path, TIMEOUT_MS, now_ms).unwrap());
+        assert!(!CncFile::is_active(&context.path, TIMEOUT_MS, now_ms + TIMEOUT_MS + 1).unwrap());
+    }
+
+    #[test]
+    fn should_reject_to_driver_length_not_power_of_two_plus_trailer() {
+        let context = CncFileTest::default();
+        let lengths = CncLengths {
+            to_driver_buffer_length: 1000,
+            ..LENGTHS
+        };
+
+        let error = CncFile::create(&context.path, lengths, TIMEOUT_MS)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn should_reject_region_lengths_not_multiples_of_8() {
+        let context = CncFileTest::default();
+        let lengths = CncLengths {
+            to_clients_buffer_length: 1020,
+            ..LENGTHS
+        };
+
+        let error = CncFile::create(&context.path, lengths, TIMEOUT_MS)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn should_reject_existing_file_with_invalid_region_lengths() {
+        let context = CncFileTest::default();
+        let cnc = CncFile::create(&context.path, LENGTHS, TIMEOUT_MS).unwrap();
+        cnc.signal_ready();
+
+        for (field_offset, length) in [
+            (CncFileDescriptor::COUNTERS_METADATA_BUFFER_LENGTH_FIELD_OFFSET, 508),
+            (CncFileDescriptor::ERROR_LOG_BUFFER_LENGTH_FIELD_OFFSET, -128),
+            (CncFileDescriptor::TO_DRIVER_BUFFER_LENGTH_FIELD_OFFSET, i32::MIN),
+        ] {
+            let buffer = cnc.mark_file().buffer();
+            let original = buffer.get::<i32>(field_offset);
+            buffer.put::<i32>(field_offset, length);
+
+            let error = CncFile::map_existing(&context.path, Duration::from_millis(100))
+                .err()
+                .unwrap();
+
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            buffer.put::<i32>(field_offset, original);
+        }
+    }
+}
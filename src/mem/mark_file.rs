@@ -0,0 +1,439 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use memmap2::MmapMut;
+
+use crate::concurrent::{AtomicBuffer, EpochClock, SystemEpochClock};
+use crate::util::Index;
+
+/// A memory-mapped file compatible with Agrona's `MarkFile`, used to signal the liveness of a
+/// process and share the buffers within it with other processes.
+///
+/// A version field of zero means the file is not yet ready. The owner writes the version with
+/// [`signal_ready`](MarkFile::signal_ready) once the rest of the file is initialised and
+/// periodically updates the timestamp field with the epoch time in milliseconds to show it is
+/// alive.
+pub struct MarkFile {
+    path: PathBuf,
+    _mmap: MmapMut,
+    base: *mut u8,
+    buffer: AtomicBuffer,
+    version_field_offset: Index,
+    timestamp_field_offset: Index,
+}
+
+unsafe impl Send for MarkFile {}
+
+unsafe impl Sync for MarkFile {}
+
+impl MarkFile {
+    const POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+    /// Create a zeroed mark file of `total_length` bytes, replacing an existing file unless it is
+    /// still active within `timeout_ms`.
+    ///
+    /// A stale file is deleted and a new one created rather than truncated, so processes which
+    /// still have it mapped keep their memory instead of faulting on their next access.
+    pub fn create(
+        path: &Path,
+        total_length: usize,
+        version_field_offset: Index,
+        timestamp_field_offset: Index,
+        timeout_ms: i64,
+    ) -> io::Result<MarkFile> {
+        if Self::is_active(
+            path,
+            version_field_offset,
+            timestamp_field_offset,
+            timeout_ms,
+            SystemEpochClock.time(),
+        )? {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("active mark file detected: {}", path.display()),
+            ));
+        }
+
+        if total_length > u32::MAX as usize
+            || total_length < Self::min_length(version_field_offset, timestamp_field_offset)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid mark file length={}", total_length),
+            ));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        file.set_len(total_length as u64)?;
+
+        Self::map(path, &file, version_field_offset, timestamp_field_offset)
+    }
+
+    /// Map an existing mark file, waiting up to `timeout` for it to be created, signalled ready and
+    /// timestamped.
+    ///
+    /// `version_check` is given the version once the file is ready and returns false if the
+    /// version is not supported.
+    pub fn map_existing<F>(
+        path: &Path,
+        version_field_offset: Index,
+        timestamp_field_offset: Index,
+        timeout: Duration,
+        version_check: F,
+    ) -> io::Result<MarkFile>
+    where
+        F: Fn(i32) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        let min_length = Self::min_length(version_field_offset, timestamp_field_offset) as u64;
+
+        let file = loop {
+            match OpenOptions::new().read(true).write(true).open(path) {
+                Ok(file) if file.metadata()?.len() >= min_length => break file,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+
+            Self::wait_until(deadline, || {
+                format!("mark file not created: {}", path.display())
+            })?;
+        };
+
+        let mark_file = Self::map(path, &file, version_field_offset, timestamp_field_offset)?;
+
+        let version = loop {
+            let version = mark_file.version_volatile();
+            if version != 0 {
+                break version;
+            }
+
+            Self::wait_until(deadline, || {
+                format!("mark file is not ready: {}", path.display())
+            })?;
+        };
+
+        if !version_check(version) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "mark file version {} is not supported: {}",
+                    version,
+                    path.display()
+                ),
+            ));
+        }
+
+        while mark_file.timestamp_volatile() == 0 {
+            Self::wait_until(deadline, || {
+                format!("no timestamp in mark file: {}", path.display())
+            })?;
+        }
+
+        Ok(mark_file)
+    }
+
+    /// True if a ready mark file exists at `path` with a timestamp within `timeout_ms` of `now_ms`.
+    pub fn is_active(
+        path: &Path,
+        version_field_offset: Index,
+        timestamp_field_offset: Index,
+        timeout_ms: i64,
+        now_ms: i64,
+    ) -> io::Result<bool> {
+        let file = match OpenOptions::new().read(true).write(true).open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        let min_length = Self::min_length(version_field_offset, timestamp_field_offset) as u64;
+        if file.metadata()?.len() < min_length {
+            return Ok(false);
+        }
+
+        let mark_file = Self::map(path, &file, version_field_offset, timestamp_field_offset)?;
+
+        Ok(mark_file.version_volatile() != 0
+            && now_ms - mark_file.timestamp_volatile() <= timeout_ms)
+    }
+
+    fn map(
+        path: &Path,
+        file: &File,
+        version_field_offset: Index,
+        timestamp_field_offset: Index,
+    ) -> io::Result<MarkFile> {
+        let mut mmap = unsafe { MmapMut::map_mut(file)? };
+        if mmap.len() > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("mark file too large: {}", path.display()),
+            ));
+        }
+
+        let base = mmap.as_mut_ptr();
+        let buffer = AtomicBuffer::wrap(unsafe { slice::from_raw_parts_mut(base, mmap.len()) });
+
+        Ok(MarkFile {
+            path: path.to_path_buf(),
+            _mmap: mmap,
+            base,
+            buffer,
+            version_field_offset,
+            timestamp_field_offset,
+        })
+    }
+
+    fn min_length(version_field_offset: Index, timestamp_field_offset: Index) -> usize {
+        (version_field_offset + 4).max(timestamp_field_offset + 8) as usize
+    }
+
+    fn wait_until<F: FnOnce() -> String>(deadline: Instant, message: F) -> io::Result<()> {
+        if Instant::now() > deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, message()));
+        }
+
+        thread::sleep(Self::POLL_INTERVAL);
+        Ok(())
+    }
+
+    /// Write the version with ordered semantics, signalling the rest of the file is initialised.
+    pub fn signal_ready(&self, version: i32) {
+        self.buffer
+            .put_ordered::<i32>(self.version_field_offset, version);
+    }
+
+    pub fn version_volatile(&self) -> i32 {
+        self.buffer.get_i32_volatile(self.version_field_offset)
+    }
+
+    pub fn timestamp_ordered(&self, timestamp_ms: i64) {
+        self.buffer
+            .put_i64_ordered(self.timestamp_field_offset, timestamp_ms);
+    }
+
+    pub fn timestamp_volatile(&self) -> i64 {
+        self.buffer.get_i64_volatile(self.timestamp_field_offset)
+    }
+
+    /// The whole mapped file.
+    pub fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
+    /// A region of the mapped file, panicking if it is out of bounds.
+    pub fn region(&self, offset: Index, length: Index) -> AtomicBuffer {
+        let capacity = self.buffer.capacity();
+        if offset < 0 || length < 0 || offset as i64 + length as i64 > capacity as i64 {
+            panic!(
+                "region out of bounds: offset={} length={} capacity={}",
+                offset, length, capacity
+            );
+        }
+
+        AtomicBuffer::wrap(unsafe {
+            slice::from_raw_parts_mut(self.base.add(offset as usize), length as usize)
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const VERSION_FIELD_OFFSET: Index = 0;
+    const TIMESTAMP_FIELD_OFFSET: Index = 8;
+    const TOTAL_LENGTH: usize = 1024;
+    const TIMEOUT_MS: i64 = 1000;
+
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        env::temp_dir().join(format!(
+            "chair-rb-{}-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed),
+            name
+        ))
+    }
+
+    struct MarkFileTest {
+        path: PathBuf,
+    }
+
+    impl Default for MarkFileTest {
+        fn default() -> Self {
+            MarkFileTest {
+                path: temp_path("mark.dat"),
+            }
+        }
+    }
+
+    impl Drop for MarkFileTest {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    impl MarkFileTest {
+        fn create(&self) -> io::Result<MarkFile> {
+            MarkFile::create(
+                &self.path,
+                TOTAL_LENGTH,
+                VERSION_FIELD_OFFSET,
+                TIMESTAMP_FIELD_OFFSET,
+                TIMEOUT_MS,
+            )
+        }
+
+        fn map_existing(&self, timeout: Duration, version: i32) -> io::Result<MarkFile> {
+            MarkFile::map_existing(
+                &self.path,
+                VERSION_FIELD_OFFSET,
+                TIMESTAMP_FIELD_OFFSET,
+                timeout,
+                |v| v == version,
+            )
+        }
+    }
+
+    #[test]
+    fn should_create_and_map_existing_mark_file() {
+        let context = MarkFileTest::default();
+        let mark_file = context.create().unwrap();
+
+        mark_file.buffer().put_i64(64, 42);
+        mark_file.timestamp_ordered(SystemEpochClock.time());
+        mark_file.signal_ready(1);
+
+        let existing = context.map_existing(Duration::from_millis(100), 1).unwrap();
+
+        assert_eq!(existing.version_volatile(), 1);
+        assert_eq!(
+            existing.timestamp_volatile(),
+            mark_file.timestamp_volatile()
+        );
+        assert_eq!(existing.buffer().capacity(), TOTAL_LENGTH as Index);
+        assert_eq!(existing.buffer().get_i64(64), 42);
+    }
+
+    #[test]
+    fn should_wait_for_mark_file_to_become_ready() {
+        let context = MarkFileTest::default();
+        let mark_file = context.create().unwrap();
+
+        let signaller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            mark_file.timestamp_ordered(SystemEpochClock.time());
+            mark_file.signal_ready(1);
+            mark_file
+        });
+
+        let existing = context.map_existing(Duration::from_secs(5), 1).unwrap();
+        let _mark_file = signaller.join().unwrap();
+
+        assert_eq!(existing.version_volatile(), 1);
+    }
+
+    #[test]
+    fn should_time_out_when_mark_file_is_not_ready() {
+        let context = MarkFileTest::default();
+        let _mark_file = context.create().unwrap();
+
+        let error = context
+            .map_existing(Duration::from_millis(50), 1)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn should_reject_unsupported_version() {
+        let context = MarkFileTest::default();
+        let mark_file = context.create().unwrap();
+        mark_file.timestamp_ordered(SystemEpochClock.time());
+        mark_file.signal_ready(2);
+
+        let error = context
+            .map_existing(Duration::from_millis(50), 1)
+            .err()
+            .unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn should_not_replace_active_mark_file() {
+        let context = MarkFileTest::default();
+        let mark_file = context.create().unwrap();
+        mark_file.timestamp_ordered(SystemEpochClock.time());
+        mark_file.signal_ready(1);
+
+        let error = context.create().err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        mark_file.timestamp_ordered(SystemEpochClock.time() - TIMEOUT_MS * 2);
+        assert!(context.create().is_ok());
+    }
+
+    #[test]
+    fn should_replace_stale_mark_file_without_truncating_existing_mapping() {
+        let context = MarkFileTest::default();
+        let stale = context.create().unwrap();
+        stale.buffer().put_i64(64, 42);
+        stale.timestamp_ordered(SystemEpochClock.time() - TIMEOUT_MS * 2);
+        stale.signal_ready(1);
+
+        let mark_file = context.create().unwrap();
+
+        assert_eq!(stale.buffer().get_i64(64), 42);
+        assert_eq!(stale.version_volatile(), 1);
+        assert_eq!(mark_file.buffer().get_i64(64), 0);
+        assert_eq!(mark_file.version_volatile(), 0);
+    }
+}
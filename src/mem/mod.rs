@@ -0,0 +1,70 @@
+use std::ops::{Deref, DerefMut};
+
+pub use self::aligned_box::AlignedBox;
+pub use self::cnc_file::{CncFile, CncFileDescriptor, CncLengths};
+pub use self::mark_file::MarkFile;
+pub use self::page_aligned_buffer::{AllocationOptions, HugePages, PageAlignedBuffer};
+
+pub mod aligned_box;
+pub mod cnc_file;
+pub mod mark_file;
+pub mod page_aligned_buffer;
+
+macro_rules! aligned_wrapper {
+    ($(#[$doc:meta])* $name:ident, $alignment:literal) => {
+        $(#[$doc])*
+        #[repr(align($alignment))]
+        pub struct $name<T> {
+            pub(crate) aligned: T,
+        }
+
+        impl<T> $name<T> {
+            pub const ALIGNMENT: usize = $alignment;
+
+            pub fn new(aligned: T) -> $name<T> {
+                $name::<T> { aligned }
+            }
+        }
+
+        impl<T> Deref for $name<T> {
+            type Target = T;
+
+            fn deref(&self) -> &Self::Target {
+                &self.aligned
+            }
+        }
+
+        impl<T> DerefMut for $name<T> {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.aligned
+            }
+        }
+    };
+}
+
+aligned_wrapper!(
+    /// Aligns a value, e.g. a byte array to wrap in an
+    /// [`AtomicBuffer`](crate::concurrent::AtomicBuffer), to 16 bytes. Alignment applies to the
+    /// value itself, so a `Vec` is not aligned by its contents; use [`AlignedBox`] for those.
+    Align16,
+    16
+);
+
+aligned_wrapper!(
+    /// Aligns a value to [`CACHE_LINE_LENGTH`](crate::util::bit_util::CACHE_LINE_LENGTH).
+    Align64,
+    64
+);
+
+aligned_wrapper!(
+    /// Aligns a value to a pair of cache lines, so it shares no line with its neighbours when the
+    /// adjacent line prefetcher is enabled.
+    Align128,
+    128
+);
+
+aligned_wrapper!(
+    /// Aligns a value to a 4KB page.
+    AlignPage,
+    4096
+);
@@ -0,0 +1,231 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use memmap2::{MmapMut, MmapOptions};
+
+use crate::concurrent::AtomicBuffer;
+
+/// Huge page backing for a [`PageAlignedBuffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HugePages {
+    /// Regular pages.
+    None,
+    /// Ask the kernel to back the memory with transparent huge pages, `madvise(MADV_HUGEPAGE)`.
+    /// Linux only.
+    Transparent,
+    /// Map from the hugetlbfs pool, `MAP_HUGETLB`, with `page_bits` selecting the page size, e.g.
+    /// 21 for 2MB or 30 for 1GB, or the system default size if `None`. Fails unless huge pages
+    /// have been reserved. Linux only.
+    HugeTlb { page_bits: Option<u8> },
+}
+
+/// How a [`PageAlignedBuffer`] is allocated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AllocationOptions {
+    pub huge_pages: HugePages,
+    /// Write to every page on allocation so the first messages do not take page faults.
+    pub pre_touch: bool,
+    /// Lock the pages in memory with `mlock` so they are never swapped out, subject to
+    /// `RLIMIT_MEMLOCK`.
+    pub lock: bool,
+}
+
+impl Default for AllocationOptions {
+    fn default() -> Self {
+        AllocationOptions {
+            huge_pages: HugePages::None,
+            pre_touch: false,
+            lock: false,
+        }
+    }
+}
+
+/// Zeroed, page-aligned anonymous memory that owns the mapping behind its [`AtomicBuffer`], e.g.
+/// for `ManyToOneRingBuffer::new(*memory.buffer())`. The mapping is released on drop, so the
+/// buffer must not outlive it.
+pub struct PageAlignedBuffer {
+    _mmap: MmapMut,
+    buffer: AtomicBuffer,
+    options: AllocationOptions,
+}
+
+unsafe impl Send for PageAlignedBuffer {}
+
+unsafe impl Sync for PageAlignedBuffer {}
+
+impl PageAlignedBuffer {
+    /// Stride used to pre-touch pages, the smallest page size in common use.
+    const TOUCH_STRIDE: usize = 4096;
+
+    const DEFAULT_HUGE_PAGE_BITS: u8 = 21;
+
+    /// Allocate `length` bytes of regular pages.
+    pub fn allocate(length: usize) -> io::Result<PageAlignedBuffer> {
+        Self::with_options(length, AllocationOptions::default())
+    }
+
+    /// Allocate `length` bytes as described by `options`. With hugetlbfs the mapping is rounded up
+    /// to a whole number of huge pages, but the buffer still covers exactly `length` bytes.
+    pub fn with_options(
+        length: usize,
+        options: AllocationOptions,
+    ) -> io::Result<PageAlignedBuffer> {
+        if length == 0 || length > u32::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid buffer length={}", length),
+            ));
+        }
+
+        let mut mmap_options = MmapOptions::new();
+        match options.huge_pages {
+            HugePages::HugeTlb { page_bits } => {
+                let huge_page_size = 1_usize << page_bits.unwrap_or(Self::DEFAULT_HUGE_PAGE_BITS);
+                mmap_options
+                    .len((length + huge_page_size - 1) & !(huge_page_size - 1))
+                    .huge(page_bits);
+            }
+            _ => {
+                mmap_options.len(length);
+            }
+        }
+        let mut mmap = mmap_options.map_anon()?;
+
+        if options.huge_pages == HugePages::Transparent {
+            Self::advise_huge_pages(&mmap)?;
+        }
+
+        if options.pre_touch {
+            for page in mmap.chunks_mut(Self::TOUCH_STRIDE) {
+                unsafe { std::ptr::write_volatile(page.as_mut_ptr(), 0) };
+            }
+        }
+
+        if options.lock {
+            mmap.lock()?;
+        }
+
+        let buffer = AtomicBuffer::wrap(&mut mmap[..length]);
+
+        Ok(PageAlignedBuffer {
+            _mmap: mmap,
+            buffer,
+            options,
+        })
+    }
+
+    pub fn buffer(&self) -> &AtomicBuffer {
+        &self.buffer
+    }
+
+    pub fn options(&self) -> &AllocationOptions {
+        &self.options
+    }
+
+    #[cfg(target_os = "linux")]
+    fn advise_huge_pages(mmap: &MmapMut) -> io::Result<()> {
+        mmap.advise(memmap2::Advice::HugePage)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn advise_huge_pages(_mmap: &MmapMut) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "transparent huge pages are only supported on Linux",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrent::ring_buffer::{ManyToOneRingBuffer, RingBuffer, RingBufferDescriptor};
+
+    const LENGTH: usize = 64 * 1024 + RingBufferDescriptor::TRAILER_LENGTH as usize;
+
+    #[test]
+    fn should_allocate_zeroed_page_aligned_buffer() {
+        let memory = PageAlignedBuffer::allocate(LENGTH).unwrap();
+        let buffer = memory.buffer();
+        let mut bytes = vec![1_u8; LENGTH];
+        buffer.get_bytes(0, &mut bytes);
+
+        assert_eq!(buffer.capacity() as usize, LENGTH);
+        assert!(bytes.iter().all(|b| *b == 0));
+        assert_eq!(
+            buffer.as_ptr() as usize % PageAlignedBuffer::TOUCH_STRIDE,
+            0
+        );
+    }
+
+    #[test]
+    fn should_back_ring_buffer_with_pre_touched_locked_memory() {
+        let options = AllocationOptions {
+            pre_touch: true,
+            lock: true,
+            ..AllocationOptions::default()
+        };
+        let memory = match PageAlignedBuffer::with_options(LENGTH, options) {
+            Ok(memory) => memory,
+            // mlock is limited by RLIMIT_MEMLOCK, which may be too low where tests run
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::PermissionDenied
+                        | io::ErrorKind::OutOfMemory
+                        | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                return
+            }
+            Err(e) => panic!("{}", e),
+        };
+        let ring_buffer = ManyToOneRingBuffer::new(*memory.buffer());
+        let src = [7_u8; 8];
+
+        assert!(ring_buffer.write(1, &src[..], 0, 8));
+        assert_eq!(
+            ring_buffer.read(|_, _, _, length| assert_eq!(length, 8), 1),
+            1
+        );
+        assert_eq!(memory.options(), &options);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn should_advise_transparent_huge_pages() {
+        let options = AllocationOptions {
+            huge_pages: HugePages::Transparent,
+            pre_touch: true,
+            ..AllocationOptions::default()
+        };
+
+        match PageAlignedBuffer::with_options(LENGTH, options) {
+            Ok(memory) => assert_eq!(memory.buffer().capacity() as usize, LENGTH),
+            // EINVAL when the kernel is built without transparent huge pages
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+        }
+    }
+
+    #[test]
+    fn should_reject_empty_buffer() {
+        let error = PageAlignedBuffer::allocate(0).err().unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
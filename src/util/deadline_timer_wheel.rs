@@ -0,0 +1,547 @@
+/*
+ * Copyright 2021 Andrew Trumbo
+ * This work is a derivative of:
+ * https://github.com/real-logic/agrona/
+ * Copyright 2014-2021 Real Logic Limited.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ * https://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::util::bit_util::is_power_of_two;
+
+/**
+* Timer wheel for timers scheduled to expire on a deadline, ported from Agrona.
+* <p>
+* The wheel is polled from an agent duty cycle, e.g. alongside reading a ring buffer, so many cheap
+* timers need no threads of their own. Times are in whatever unit the caller chooses, typically
+* nanoseconds from a [`NanoClock`](crate::concurrent::NanoClock), and the wheel only checks
+* deadlines to the tick resolution.
+* <p>
+* Each tick of the wheel holds an array of deadlines which doubles in size when full. A timer id
+* encodes the tick in the upper 32 bits and the index within the tick array in the lower 32 bits.
+* <p>
+* Not thread safe.
+*/
+pub struct DeadlineTimerWheel {
+    tick_resolution: i64,
+    start_time: i64,
+    current_tick: i64,
+    timer_count: i64,
+    ticks_per_wheel: i32,
+    tick_mask: i32,
+    resolution_bits_to_shift: u32,
+    tick_allocation: i32,
+    allocation_bits_to_shift: u32,
+    poll_index: i32,
+    wheel: Vec<i64>,
+}
+
+impl DeadlineTimerWheel {
+    /// Deadline of a slot without a timer.
+    pub const NULL_DEADLINE: i64 = i64::MAX;
+
+    const INITIAL_TICK_ALLOCATION: i32 = 16;
+
+    pub fn new(start_time: i64, tick_resolution: i64, ticks_per_wheel: i32) -> DeadlineTimerWheel {
+        Self::with_tick_allocation(
+            start_time,
+            tick_resolution,
+            ticks_per_wheel,
+            Self::INITIAL_TICK_ALLOCATION,
+        )
+    }
+
+    /// Create a wheel with `initial_tick_allocation` timer slots per tick before it has to grow.
+    pub fn with_tick_allocation(
+        start_time: i64,
+        tick_resolution: i64,
+        ticks_per_wheel: i32,
+        initial_tick_allocation: i32,
+    ) -> DeadlineTimerWheel {
+        if !is_power_of_two(ticks_per_wheel) {
+            panic!("ticks per wheel must be a power of 2: {}", ticks_per_wheel);
+        }
+        if tick_resolution <= 0 || !(tick_resolution as u64).is_power_of_two() {
+            panic!("tick resolution must be a power of 2: {}", tick_resolution);
+        }
+        if !is_power_of_two(initial_tick_allocation) {
+            panic!(
+                "tick allocation must be a power of 2: {}",
+                initial_tick_allocation
+            );
+        }
+
+        DeadlineTimerWheel {
+            tick_resolution,
+            start_time,
+            current_tick: 0,
+            timer_count: 0,
+            ticks_per_wheel,
+            tick_mask: ticks_per_wheel - 1,
+            resolution_bits_to_shift: tick_resolution.trailing_zeros(),
+            tick_allocation: initial_tick_allocation,
+            allocation_bits_to_shift: initial_tick_allocation.trailing_zeros(),
+            poll_index: 0,
+            wheel: vec![Self::NULL_DEADLINE; (ticks_per_wheel * initial_tick_allocation) as usize],
+        }
+    }
+
+    pub fn tick_resolution(&self) -> i64 {
+        self.tick_resolution
+    }
+
+    pub fn ticks_per_wheel(&self) -> i32 {
+        self.ticks_per_wheel
+    }
+
+    pub fn start_time(&self) -> i64 {
+        self.start_time
+    }
+
+    /// Number of timers currently scheduled.
+    pub fn timer_count(&self) -> i64 {
+        self.timer_count
+    }
+
+    /// Reset the start time of the wheel, panicking if any timers are scheduled.
+    pub fn reset_start_time(&mut self, start_time: i64) {
+        if self.timer_count > 0 {
+            panic!("can not reset startTime with active timers");
+        }
+
+        self.start_time = start_time;
+        self.current_tick = 0;
+        self.poll_index = 0;
+    }
+
+    /// Time of the end of the current tick.
+    pub fn current_tick_time(&self) -> i64 {
+        ((self.current_tick + 1) << self.resolution_bits_to_shift) + self.start_time
+    }
+
+    /// Move the current tick forward to the tick containing `now`, e.g. after a long pause in polling.
+    pub fn set_current_tick_time(&mut self, now: i64) {
+        self.current_tick = self
+            .current_tick
+            .max((now - self.start_time) >> self.resolution_bits_to_shift);
+    }
+
+    /// Cancel all timers.
+    pub fn clear(&mut self) {
+        let mut remaining_timers = self.timer_count;
+        if remaining_timers == 0 {
+            return;
+        }
+
+        for deadline in self.wheel.iter_mut() {
+            if *deadline != Self::NULL_DEADLINE {
+                *deadline = Self::NULL_DEADLINE;
+                remaining_timers -= 1;
+                if remaining_timers <= 0 {
+                    break;
+                }
+            }
+        }
+
+        self.timer_count = 0;
+    }
+
+    /// Schedule a timer for `deadline`, returning the timer id. A deadline in the past expires on
+    /// the next poll of the current tick.
+    pub fn schedule_timer(&mut self, deadline: i64) -> i64 {
+        let deadline_tick = self
+            .current_tick
+            .max((deadline - self.start_time) >> self.resolution_bits_to_shift);
+        let spoke_index = deadline_tick as i32 & self.tick_mask;
+        let tick_start_index = spoke_index << self.allocation_bits_to_shift;
+
+        for i in 0..self.tick_allocation {
+            let index = (tick_start_index + i) as usize;
+            if self.wheel[index] == Self::NULL_DEADLINE {
+                self.wheel[index] = deadline;
+                self.timer_count += 1;
+
+                return Self::timer_id_for_slot(spoke_index, i);
+            }
+        }
+
+        self.increase_capacity(deadline, spoke_index)
+    }
+
+    /// Cancel a timer, returning false if it has already expired or been cancelled.
+    pub fn cancel_timer(&mut self, timer_id: i64) -> bool {
+        match self.wheel_index(timer_id) {
+            Some(wheel_index) if self.wheel[wheel_index] != Self::NULL_DEADLINE => {
+                self.wheel[wheel_index] = Self::NULL_DEADLINE;
+                self.timer_count -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Deadline of a timer, or [`NULL_DEADLINE`](Self::NULL_DEADLINE) if it is not scheduled.
+    pub fn deadline(&self, timer_id: i64) -> i64 {
+        match self.wheel_index(timer_id) {
+            Some(wheel_index) => self.wheel[wheel_index],
+            None => Self::NULL_DEADLINE,
+        }
+    }
+
+    /// Expire timers in the current tick with deadlines at or before `now`, up to `expiry_limit`,
+    /// and move to the next tick once the current one has passed.
+    ///
+    /// The handler is called with `now` and the timer id, and returns false to keep the timer
+    /// scheduled and stop polling. Returns the number of timers expired.
+    pub fn poll<F>(&mut self, now: i64, mut handler: F, expiry_limit: i32) -> i32
+    where
+        F: FnMut(i64, i64) -> bool,
+    {
+        let mut timers_expired = 0;
+
+        if self.timer_count > 0 {
+            let spoke_index = self.current_tick as i32 & self.tick_mask;
+
+            for _ in 0..self.tick_allocation {
+                if timers_expired >= expiry_limit {
+                    break;
+                }
+
+                let wheel_index =
+                    ((spoke_index << self.allocation_bits_to_shift) + self.poll_index) as usize;
+                let deadline = self.wheel[wheel_index];
+
+                if now >= deadline {
+                    self.wheel[wheel_index] = Self::NULL_DEADLINE;
+                    self.timer_count -= 1;
+                    timers_expired += 1;
+
+                    if !handler(now, Self::timer_id_for_slot(spoke_index, self.poll_index)) {
+                        self.wheel[wheel_index] = deadline;
+                        self.timer_count += 1;
+
+                        return timers_expired - 1;
+                    }
+                }
+
+                self.poll_index = if self.poll_index + 1 >= self.tick_allocation {
+                    0
+                } else {
+                    self.poll_index + 1
+                };
+            }
+
+            if expiry_limit > timers_expired && self.current_tick_time() <= now {
+                self.current_tick += 1;
+                self.poll_index = 0;
+            } else if self.poll_index >= self.tick_allocation {
+                self.poll_index = 0;
+            }
+        } else if self.current_tick_time() <= now {
+            self.current_tick += 1;
+            self.poll_index = 0;
+        }
+
+        timers_expired
+    }
+
+    /// Call `consumer` with the deadline and id of each scheduled timer, starting from the current
+    /// tick.
+    pub fn for_each<F>(&self, mut consumer: F)
+    where
+        F: FnMut(i64, i64),
+    {
+        let mut timers_left = self.timer_count;
+        if timers_left == 0 {
+            return;
+        }
+
+        for tick in self.current_tick..=self.current_tick + self.ticks_per_wheel as i64 {
+            let spoke_index = tick as i32 & self.tick_mask;
+
+            for i in 0..self.tick_allocation {
+                let deadline =
+                    self.wheel[((spoke_index << self.allocation_bits_to_shift) + i) as usize];
+
+                if deadline != Self::NULL_DEADLINE {
+                    consumer(deadline, Self::timer_id_for_slot(spoke_index, i));
+
+                    timers_left -= 1;
+                    if timers_left <= 0 {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn increase_capacity(&mut self, deadline: i64, spoke_index: i32) -> i64 {
+        let new_tick_allocation = self.tick_allocation << 1;
+        let new_allocation_bits_to_shift = new_tick_allocation.trailing_zeros();
+        let new_capacity = self.ticks_per_wheel as i64 * new_tick_allocation as i64;
+        if new_capacity > 1 << 30 {
+            panic!(
+                "max capacity reached at tickAllocation={}",
+                self.tick_allocation
+            );
+        }
+
+        let mut new_wheel = vec![Self::NULL_DEADLINE; new_capacity as usize];
+        for j in 0..self.ticks_per_wheel {
+            let old_tick_start_index = (j << self.allocation_bits_to_shift) as usize;
+            let new_tick_start_index = (j << new_allocation_bits_to_shift) as usize;
+            let tick_allocation = self.tick_allocation as usize;
+
+            new_wheel[new_tick_start_index..new_tick_start_index + tick_allocation]
+                .copy_from_slice(
+                    &self.wheel[old_tick_start_index..old_tick_start_index + tick_allocation],
+                );
+        }
+
+        new_wheel
+            [((spoke_index << new_allocation_bits_to_shift) + self.tick_allocation) as usize] =
+            deadline;
+        let timer_id = Self::timer_id_for_slot(spoke_index, self.tick_allocation);
+        self.timer_count += 1;
+
+        self.tick_allocation = new_tick_allocation;
+        self.allocation_bits_to_shift = new_allocation_bits_to_shift;
+        self.wheel = new_wheel;
+
+        timer_id
+    }
+
+    fn wheel_index(&self, timer_id: i64) -> Option<usize> {
+        let spoke_index = Self::tick_for_timer_id(timer_id);
+        let tick_index = Self::index_in_tick_array(timer_id);
+
+        if spoke_index < 0
+            || spoke_index >= self.ticks_per_wheel
+            || tick_index < 0
+            || tick_index >= self.tick_allocation
+        {
+            return None;
+        }
+
+        Some(((spoke_index << self.allocation_bits_to_shift) + tick_index) as usize)
+    }
+
+    #[inline]
+    fn timer_id_for_slot(tick_on_wheel: i32, tick_array_index: i32) -> i64 {
+        ((tick_on_wheel as i64) << 32) | tick_array_index as i64
+    }
+
+    #[inline]
+    fn tick_for_timer_id(timer_id: i64) -> i32 {
+        (timer_id >> 32) as i32
+    }
+
+    #[inline]
+    fn index_in_tick_array(timer_id: i64) -> i32 {
+        timer_id as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLUTION: i64 = 1 << 20;
+
+    struct DeadlineTimerWheelTest {
+        wheel: DeadlineTimerWheel,
+    }
+
+    impl Default for DeadlineTimerWheelTest {
+        fn default() -> Self {
+            DeadlineTimerWheelTest {
+                wheel: DeadlineTimerWheel::new(0, RESOLUTION, 1024),
+            }
+        }
+    }
+
+    impl DeadlineTimerWheelTest {
+        /// Poll until a timer expires or `until` is reached, returning the expiries seen.
+        fn poll_until(&mut self, start: i64, until: i64) -> Vec<(i64, i64)> {
+            let mut expired = vec![];
+            let mut now = start;
+
+            while now <= until {
+                self.wheel.poll(
+                    now,
+                    |now, timer_id| {
+                        expired.push((now, timer_id));
+                        true
+                    },
+                    i32::MAX,
+                );
+
+                if !expired.is_empty() {
+                    break;
+                }
+                now += RESOLUTION;
+            }
+
+            expired
+        }
+    }
+
+    #[test]
+    fn should_be_able_to_calculate_tick_time() {
+        let mut context = DeadlineTimerWheelTest::default();
+
+        assert_eq!(context.wheel.current_tick_time(), RESOLUTION);
+
+        context.wheel.set_current_tick_time(RESOLUTION * 5 + 1);
+        assert_eq!(context.wheel.current_tick_time(), RESOLUTION * 6);
+    }
+
+    #[test]
+    fn should_handle_non_zero_start_time() {
+        let start_time = 100 * RESOLUTION;
+        let mut context = DeadlineTimerWheelTest {
+            wheel: DeadlineTimerWheel::new(start_time, RESOLUTION, 1024),
+        };
+
+        let timer_id = context.wheel.schedule_timer(start_time + 5 * RESOLUTION);
+        let expired = context.poll_until(start_time, start_time + 10 * RESOLUTION);
+
+        assert_eq!(expired, vec![(start_time + 6 * RESOLUTION, timer_id)]);
+        assert_eq!(context.wheel.timer_count(), 0);
+    }
+
+    #[test]
+    fn should_expire_timer_in_the_past_on_next_poll() {
+        let mut context = DeadlineTimerWheelTest::default();
+        context.wheel.set_current_tick_time(10 * RESOLUTION);
+
+        let timer_id = context.wheel.schedule_timer(RESOLUTION);
+        let expired = context.poll_until(10 * RESOLUTION, 10 * RESOLUTION);
+
+        assert_eq!(expired, vec![(10 * RESOLUTION, timer_id)]);
+    }
+
+    #[test]
+    fn should_not_expire_cancelled_timer() {
+        let mut context = DeadlineTimerWheelTest::default();
+
+        let timer_id = context.wheel.schedule_timer(5 * RESOLUTION);
+        assert_eq!(context.wheel.deadline(timer_id), 5 * RESOLUTION);
+        assert!(context.wheel.cancel_timer(timer_id));
+        assert!(!context.wheel.cancel_timer(timer_id));
+        assert_eq!(
+            context.wheel.deadline(timer_id),
+            DeadlineTimerWheel::NULL_DEADLINE
+        );
+
+        let expired = context.poll_until(0, 20 * RESOLUTION);
+
+        assert!(expired.is_empty());
+        assert_eq!(context.wheel.timer_count(), 0);
+    }
+
+    #[test]
+    fn should_keep_timer_when_handler_declines_expiry() {
+        let mut context = DeadlineTimerWheelTest::default();
+        let timer_id = context.wheel.schedule_timer(0);
+
+        assert_eq!(context.wheel.poll(0, |_, _| false, i32::MAX), 0);
+        assert_eq!(context.wheel.timer_count(), 1);
+
+        let mut expired = vec![];
+        let count = context.wheel.poll(
+            0,
+            |_, timer_id| {
+                expired.push(timer_id);
+                true
+            },
+            i32::MAX,
+        );
+
+        assert_eq!(count, 1);
+        assert_eq!(expired, vec![timer_id]);
+    }
+
+    #[test]
+    fn should_limit_expiries_per_poll() {
+        let mut context = DeadlineTimerWheelTest::default();
+        for _ in 0..5 {
+            context.wheel.schedule_timer(0);
+        }
+
+        assert_eq!(context.wheel.poll(0, |_, _| true, 2), 2);
+        assert_eq!(context.wheel.poll(0, |_, _| true, 2), 2);
+        assert_eq!(context.wheel.poll(0, |_, _| true, 2), 1);
+        assert_eq!(context.wheel.timer_count(), 0);
+    }
+
+    #[test]
+    fn should_grow_tick_allocation_when_tick_is_full() {
+        let mut context = DeadlineTimerWheelTest {
+            wheel: DeadlineTimerWheel::with_tick_allocation(0, RESOLUTION, 8, 2),
+        };
+
+        let timer_ids: Vec<i64> = (0..5)
+            .map(|i| context.wheel.schedule_timer(3 * RESOLUTION + i))
+            .collect();
+
+        assert_eq!(context.wheel.timer_count(), 5);
+        for (i, timer_id) in timer_ids.iter().enumerate() {
+            assert_eq!(context.wheel.deadline(*timer_id), 3 * RESOLUTION + i as i64);
+        }
+
+        let mut deadlines = vec![];
+        context
+            .wheel
+            .for_each(|deadline, _| deadlines.push(deadline));
+        deadlines.sort_unstable();
+        assert_eq!(
+            deadlines,
+            (0..5).map(|i| 3 * RESOLUTION + i).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn should_clear_all_timers() {
+        let mut context = DeadlineTimerWheelTest::default();
+        let first = context.wheel.schedule_timer(RESOLUTION);
+        let second = context.wheel.schedule_timer(RESOLUTION * 100);
+
+        context.wheel.clear();
+
+        assert_eq!(context.wheel.timer_count(), 0);
+        assert_eq!(
+            context.wheel.deadline(first),
+            DeadlineTimerWheel::NULL_DEADLINE
+        );
+        assert_eq!(
+            context.wheel.deadline(second),
+            DeadlineTimerWheel::NULL_DEADLINE
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "can not reset startTime with active timers")]
+    fn should_panic_when_resetting_start_time_with_active_timers() {
+        let mut context = DeadlineTimerWheelTest::default();
+        context.wheel.schedule_timer(RESOLUTION);
+
+        context.wheel.reset_start_time(RESOLUTION);
+    }
+
+    #[test]
+    #[should_panic(expected = "ticks per wheel must be a power of 2: 10")]
+    fn should_panic_when_ticks_per_wheel_not_power_of_two() {
+        DeadlineTimerWheel::new(0, RESOLUTION, 10);
+    }
+}
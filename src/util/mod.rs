@@ -17,6 +17,10 @@
  * limitations under the License.
  */
 
+pub use self::deadline_timer_wheel::DeadlineTimerWheel;
+
+pub mod deadline_timer_wheel;
+
 pub type Index = i32;
 
 pub mod bit_util {